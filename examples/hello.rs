@@ -1,75 +1,55 @@
-use chrono::Utc;
-use serde::Deserialize;
+//! Serves the files of the current directory and the icons of the system on http://localhost:8888,
+//! like `http://localhost:8888/geticon?ext=.pdf&size=32`.
+//!
+//! `cargo run --example hello --features http`
 use tokio;
 use warp::{
-    fs::File,
-    http::HeaderValue,
-    hyper::{self, Body, HeaderMap, Response},
-    Filter, Reply,
+    http::{HeaderMap, Response},
+    hyper::Body,
+    Filter,
 };
 
-#[derive(Deserialize)]
-struct GetIcon {
-    ext: String,
-    size: i32,
-}
-
 #[cfg(target_os = "linux")]
 fn init() {
-    gtk::init().unwrap();
+    if let Err(err) = systemicons::init() {
+        eprintln!("{}", err);
+    }
 }
 #[cfg(target_os = "windows")]
 fn init() {}
 #[cfg(target_os = "macos")]
 fn init() {}
 
+/// warp still uses version 0.2 of the `http` crate, so request and response are copied
+fn get_icon(query: String, headers: HeaderMap) -> Response<Body> {
+    let mut request = http::Request::builder().uri(format!("/geticon?{}", query));
+    for (name, value) in headers.iter() {
+        request = request.header(name.as_str(), value.as_bytes());
+    }
+    let icon = match request.body(()) {
+        Ok(request) => systemicons::http::handle(&request),
+        Err(err) => http::Response::builder().status(400).body(err.to_string().into_bytes()).unwrap_or_default(),
+    };
+    let mut response = Response::builder().status(icon.status().as_u16());
+    for (name, value) in icon.headers() {
+        response = response.header(name.as_str(), value.as_bytes());
+    }
+    response.body(Body::from(icon.into_body())).unwrap_or_default()
+}
+
 #[tokio::main]
 async fn main() {
     init();
 
-    async fn get_icon(param: GetIcon) -> Result<impl warp::Reply, warp::Rejection> {
-        let bytes = systemicons::get_icon(&param.ext, param.size).unwrap();
-        let body = hyper::Body::from(bytes);
-        let mut response = Response::new(body);
-        let headers = response.headers_mut();
-        let mut header_map = create_headers();
-        header_map.insert("Content-Type", HeaderValue::from_str("image/png").unwrap());
-        headers.extend(header_map);
-        Ok(response)
-    }
-
     let route_get_icon = warp::path("geticon")
         .and(warp::path::end())
-        .and(warp::query::query())
-        .and_then(get_icon);
-
-    fn add_headers(reply: File) -> Response<Body> {
-        let mut header_map = HeaderMap::new();
-        let now = Utc::now();
-        let now_str = now.format("%a, %d %h %Y %T GMT").to_string();
-        header_map.insert("Expires", HeaderValue::from_str(now_str.as_str()).unwrap());
-        header_map.insert("Server", HeaderValue::from_str("My Server").unwrap());
-
-        let mut res = reply.into_response();
-        let headers = res.headers_mut();
-        headers.extend(header_map);
-        res
-    }
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::header::headers_cloned())
+        .map(get_icon);
 
-    let route = warp::fs::dir(".").map(add_headers);
-
-    let routes = route.or(route_get_icon);
+    let routes = warp::fs::dir(".").or(route_get_icon);
 
     let port = 8888;
     println!("Serving example on http://localhost:{}", port);
     warp::serve(routes).run(([127, 0, 0, 1], port)).await;
 }
-
-fn create_headers() -> HeaderMap {
-    let mut header_map = HeaderMap::new();
-    let now = Utc::now();
-    let now_str = now.format("%a, %d %h %Y %T GMT").to_string();
-    header_map.insert("Expires", HeaderValue::from_str(now_str.as_str()).unwrap());
-    header_map.insert("Server", HeaderValue::from_str("My Server").unwrap());
-    header_map
-}
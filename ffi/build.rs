@@ -0,0 +1,24 @@
+//! Generates the header `systemicons.h` from the exported functions and types into `OUT_DIR`. The header in
+//! `include` is the one which is shipped, it is only overwritten when `SYSTEMICONS_UPDATE_HEADER` is set, so the
+//! crate also builds from a read-only source directory. A test checks that both headers match
+use std::{env, path::PathBuf};
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is not set"));
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is not set"));
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=SYSTEMICONS_UPDATE_HEADER");
+    // the test compiles a C program for the same target
+    println!("cargo:rustc-env=TARGET={}", env::var("TARGET").expect("TARGET is not set"));
+    println!("cargo:rustc-env=HOST={}", env::var("HOST").expect("HOST is not set"));
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).expect("reading cbindgen.toml failed");
+    let bindings = cbindgen::generate_with_config(&crate_dir, config).expect("generating the C header failed");
+    let header = out_dir.join("systemicons.h");
+    bindings.write_to_file(&header);
+    println!("cargo:rustc-env=SYSTEMICONS_GENERATED_HEADER={}", header.display());
+    if env::var_os("SYSTEMICONS_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(crate_dir.join("include").join("systemicons.h"));
+    }
+}
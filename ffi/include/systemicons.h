@@ -0,0 +1,148 @@
+/*
+ * systemicons: the icons which the system shows for file extensions and files, formatted as png.
+ *
+ * Thread safety:
+ * - All functions may be called from any thread. The lookups of all providers are serialized by a process wide
+ *   lock, because the icon theme of GTK is not thread safe. If the application uses GTK itself, like Electron on
+ *   Linux, it should call the lookups on its GTK main thread.
+ * - A provider may be shared by threads, its cache is locked. systemicons_provider_free must not be called while
+ *   another thread still uses the provider.
+ * - Buffers belong to the caller and may be freed on any thread.
+ * - systemicons_last_error_message refers to the last call on the calling thread.
+ */
+
+#ifndef SYSTEMICONS_H
+#define SYSTEMICONS_H
+
+/* Generated by cbindgen from ffi/src/lib.rs when systemicons-ffi is built, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The result of a call
+ */
+typedef enum SystemiconsStatus {
+  SYSTEMICONS_STATUS_OK = 0,
+  /**
+   * An argument is NULL, not UTF-8 or out of range
+   */
+  SYSTEMICONS_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * There is no icon
+   */
+  SYSTEMICONS_STATUS_NOT_FOUND = 2,
+  /**
+   * The icon backend could not be initialized, like GTK without display
+   */
+  SYSTEMICONS_STATUS_BACKEND_UNAVAILABLE = 3,
+  /**
+   * A file could not be read
+   */
+  SYSTEMICONS_STATUS_IO = 4,
+  /**
+   * An icon or a string of the system could not be decoded
+   */
+  SYSTEMICONS_STATUS_DECODE = 5,
+  /**
+   * An icon could not be encoded
+   */
+  SYSTEMICONS_STATUS_ENCODE = 6,
+  /**
+   * An unexpected error inside the library, like a panic
+   */
+  SYSTEMICONS_STATUS_INTERNAL = 7,
+} SystemiconsStatus;
+
+/**
+ * Looks up icons and caches the icons of file extensions. Create a new provider when the icon theme changes
+ */
+typedef struct SystemiconsProvider SystemiconsProvider;
+
+/**
+ * An icon formatted as png. The buffer belongs to the caller, who frees it with `systemicons_free_buffer`
+ */
+typedef struct SystemiconsBuffer {
+  uint8_t *data;
+  size_t len;
+} SystemiconsBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Initializes the icon backend, which is GTK on Linux, if the application does not use GTK itself.
+ * Call it once on the main thread before the first lookup. Fails with `SYSTEMICONS_STATUS_BACKEND_UNAVAILABLE`
+ * if GTK cannot be initialized, like without display
+ */
+enum SystemiconsStatus systemicons_init(void);
+
+/**
+ * Creates a provider, free it with `systemicons_provider_free`
+ */
+struct SystemiconsProvider *systemicons_provider_new(void);
+
+/**
+ * Frees a provider, NULL is ignored
+ *
+ * # Safety
+ *
+ * `provider` has been created by `systemicons_provider_new`, has not been freed yet and no other thread uses it
+ */
+void systemicons_provider_free(struct SystemiconsProvider *provider);
+
+/**
+ * Retrieves the icon of a file extension like `.pdf` in `size` pixels (1 to 1024) into `icon`.
+ * On failure `icon` is empty
+ *
+ * # Safety
+ *
+ * `provider` is a valid provider, `extension` a NUL terminated string and `icon` points to a writable buffer
+ */
+enum SystemiconsStatus systemicons_get_icon(const struct SystemiconsProvider *provider,
+                                            const char *extension,
+                                            int32_t size,
+                                            struct SystemiconsBuffer *icon);
+
+/**
+ * Retrieves the icon of a file or directory on disk in `size` pixels (1 to 1024) into `icon`. The icons of
+ * files are not cached, they may change. On failure `icon` is empty
+ *
+ * # Safety
+ *
+ * `provider` is a valid provider, `path` a NUL terminated UTF-8 string and `icon` points to a writable buffer
+ */
+enum SystemiconsStatus systemicons_get_icon_for_path(const struct SystemiconsProvider *provider,
+                                                     const char *path,
+                                                     int32_t size,
+                                                     struct SystemiconsBuffer *icon);
+
+/**
+ * Frees the icon and empties the buffer, so freeing it twice does no harm. NULL and empty buffers are ignored
+ *
+ * # Safety
+ *
+ * `buffer` is NULL or has been filled by this library
+ */
+void systemicons_free_buffer(struct SystemiconsBuffer *buffer);
+
+/**
+ * A static description of a status, like "not found"
+ */
+const char *systemicons_status_message(enum SystemiconsStatus status);
+
+/**
+ * The message of the last failed call on this thread, NULL if the last call has succeeded.
+ * The string is valid until the next call of this library on this thread
+ */
+const char *systemicons_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SYSTEMICONS_H */
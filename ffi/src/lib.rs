@@ -0,0 +1,266 @@
+//! # systemicons-ffi
+//!
+//! The C ABI of systemicons for C and C++ programs and native addons of Node. The crate builds a shared and
+//! a static library, the header `include/systemicons.h` is generated by cbindgen during the build.
+//!
+//! ```c
+//! SystemiconsProvider *provider = systemicons_provider_new();
+//! SystemiconsBuffer icon;
+//! if (systemicons_get_icon(provider, ".pdf", 32, &icon) == SYSTEMICONS_STATUS_OK) {
+//!     fwrite(icon.data, 1, icon.len, file);
+//!     systemicons_free_buffer(&icon);
+//! } else {
+//!     fprintf(stderr, "%s\n", systemicons_last_error_message());
+//! }
+//! systemicons_provider_free(provider);
+//! ```
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use systemicons::Error;
+
+const MAX_SIZE: i32 = 1024;
+
+/// Serializes the lookups of all providers, GTK is not thread safe
+static BACKEND: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The result of a call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemiconsStatus {
+    Ok = 0,
+    /// An argument is NULL, not UTF-8 or out of range
+    InvalidArgument = 1,
+    /// There is no icon
+    NotFound = 2,
+    /// The icon backend could not be initialized, like GTK without display
+    BackendUnavailable = 3,
+    /// A file could not be read
+    Io = 4,
+    /// An icon or a string of the system could not be decoded
+    Decode = 5,
+    /// An icon could not be encoded
+    Encode = 6,
+    /// An unexpected error inside the library, like a panic
+    Internal = 7,
+}
+
+/// An icon formatted as png. The buffer belongs to the caller, who frees it with `systemicons_free_buffer`
+#[repr(C)]
+#[derive(Debug)]
+pub struct SystemiconsBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Looks up icons and caches the icons of file extensions. Create a new provider when the icon theme changes
+pub struct SystemiconsProvider {
+    icons: Mutex<HashMap<(String, i32), Vec<u8>>>,
+}
+
+/// Initializes the icon backend, which is GTK on Linux, if the application does not use GTK itself.
+/// Call it once on the main thread before the first lookup. Fails with `SYSTEMICONS_STATUS_BACKEND_UNAVAILABLE`
+/// if GTK cannot be initialized, like without display
+#[no_mangle]
+pub extern "C" fn systemicons_init() -> SystemiconsStatus {
+    #[cfg(target_os = "linux")]
+    let result = catch(|| Ok(systemicons::init()?));
+    #[cfg(not(target_os = "linux"))]
+    let result = Ok(());
+    finish(result)
+}
+
+/// Creates a provider, free it with `systemicons_provider_free`
+#[no_mangle]
+pub extern "C" fn systemicons_provider_new() -> *mut SystemiconsProvider {
+    Box::into_raw(Box::new(SystemiconsProvider { icons: Mutex::new(HashMap::new()) }))
+}
+
+/// Frees a provider, NULL is ignored
+///
+/// # Safety
+///
+/// `provider` has been created by `systemicons_provider_new`, has not been freed yet and no other thread uses it
+#[no_mangle]
+pub unsafe extern "C" fn systemicons_provider_free(provider: *mut SystemiconsProvider) {
+    if !provider.is_null() {
+        drop(Box::from_raw(provider));
+    }
+}
+
+/// Retrieves the icon of a file extension like `.pdf` in `size` pixels (1 to 1024) into `icon`.
+/// On failure `icon` is empty
+///
+/// # Safety
+///
+/// `provider` is a valid provider, `extension` a NUL terminated string and `icon` points to a writable buffer
+#[no_mangle]
+pub unsafe extern "C" fn systemicons_get_icon(provider: *const SystemiconsProvider, extension: *const c_char, size: i32, icon: *mut SystemiconsBuffer) -> SystemiconsStatus {
+    complete(icon, || {
+        let provider = provider.as_ref().ok_or_else(|| invalid("provider is NULL"))?;
+        let extension = string(extension, "extension")?;
+        check_size(size)?;
+        let key = (extension.to_lowercase(), size);
+        if let Some(icon) = lock(&provider.icons).get(&key) {
+            return Ok(icon.clone());
+        }
+        let icon = {
+            let _backend = lock(&BACKEND);
+            systemicons::get_icon(extension, size)?
+        };
+        lock(&provider.icons).insert(key, icon.clone());
+        Ok(icon)
+    })
+}
+
+/// Retrieves the icon of a file or directory on disk in `size` pixels (1 to 1024) into `icon`. The icons of
+/// files are not cached, they may change. On failure `icon` is empty
+///
+/// # Safety
+///
+/// `provider` is a valid provider, `path` a NUL terminated UTF-8 string and `icon` points to a writable buffer
+#[no_mangle]
+pub unsafe extern "C" fn systemicons_get_icon_for_path(provider: *const SystemiconsProvider, path: *const c_char, size: i32, icon: *mut SystemiconsBuffer) -> SystemiconsStatus {
+    complete(icon, || {
+        provider.as_ref().ok_or_else(|| invalid("provider is NULL"))?;
+        let path = string(path, "path")?;
+        check_size(size)?;
+        let _backend = lock(&BACKEND);
+        Ok(systemicons::get_icon_for_path(path, size)?)
+    })
+}
+
+/// Frees the icon and empties the buffer, so freeing it twice does no harm. NULL and empty buffers are ignored
+///
+/// # Safety
+///
+/// `buffer` is NULL or has been filled by this library
+#[no_mangle]
+pub unsafe extern "C" fn systemicons_free_buffer(buffer: *mut SystemiconsBuffer) {
+    let Some(buffer) = buffer.as_mut() else {
+        return;
+    };
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)));
+    }
+    *buffer = SystemiconsBuffer::EMPTY;
+}
+
+/// A static description of a status, like "not found"
+#[no_mangle]
+pub extern "C" fn systemicons_status_message(status: SystemiconsStatus) -> *const c_char {
+    let message: &'static CStr = match status {
+        SystemiconsStatus::Ok => c"ok",
+        SystemiconsStatus::InvalidArgument => c"invalid argument",
+        SystemiconsStatus::NotFound => c"not found",
+        SystemiconsStatus::BackendUnavailable => c"icon backend unavailable",
+        SystemiconsStatus::Io => c"i/o error",
+        SystemiconsStatus::Decode => c"decoding error",
+        SystemiconsStatus::Encode => c"encoding error",
+        SystemiconsStatus::Internal => c"internal error",
+    };
+    message.as_ptr()
+}
+
+/// The message of the last failed call on this thread, NULL if the last call has succeeded.
+/// The string is valid until the next call of this library on this thread
+#[no_mangle]
+pub extern "C" fn systemicons_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+impl SystemiconsBuffer {
+    const EMPTY: SystemiconsBuffer = SystemiconsBuffer { data: ptr::null_mut(), len: 0 };
+}
+
+struct Failure {
+    status: SystemiconsStatus,
+    message: String,
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        let status = match error {
+            Error::NotFound { .. } => SystemiconsStatus::NotFound,
+            Error::InvalidInput { .. } => SystemiconsStatus::InvalidArgument,
+            Error::BackendUnavailable { .. } => SystemiconsStatus::BackendUnavailable,
+            Error::Io { .. } => SystemiconsStatus::Io,
+            Error::Decode { .. } => SystemiconsStatus::Decode,
+            Error::Encode { .. } => SystemiconsStatus::Encode,
+            _ => SystemiconsStatus::Internal,
+        };
+        Failure { status, message: format!("{:#}", error) }
+    }
+}
+
+fn invalid(message: &str) -> Failure {
+    Failure { status: SystemiconsStatus::InvalidArgument, message: message.to_string() }
+}
+
+unsafe fn string<'a>(value: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if value.is_null() {
+        return Err(invalid(&format!("{} is NULL", name)));
+    }
+    CStr::from_ptr(value).to_str().map_err(|_| invalid(&format!("{} is not UTF-8", name)))
+}
+
+fn check_size(size: i32) -> Result<(), Failure> {
+    if (1..=MAX_SIZE).contains(&size) {
+        Ok(())
+    } else {
+        Err(invalid(&format!("size {} is not between 1 and {}", size, MAX_SIZE)))
+    }
+}
+
+/// A panicking lookup does not make the locks unusable
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Panics must not unwind into C
+fn catch<T>(call: impl FnOnce() -> Result<T, Failure>) -> Result<T, Failure> {
+    panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or_else(|panic| Err(Failure { status: SystemiconsStatus::Internal, message: panic_message(panic) }))
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "The lookup has panicked".to_string())
+}
+
+/// Stores the message of a failure for `systemicons_last_error_message` and returns the status
+fn finish(result: Result<(), Failure>) -> SystemiconsStatus {
+    let (status, message) = match result {
+        Ok(()) => (SystemiconsStatus::Ok, None),
+        // messages with NUL characters are cut there
+        Err(failure) => (failure.status, Some(CString::new(failure.message.split('\0').next().unwrap_or_default()).unwrap_or_default())),
+    };
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+    status
+}
+
+/// Runs a lookup and hands its icon over to `icon`
+unsafe fn complete(icon: *mut SystemiconsBuffer, lookup: impl FnOnce() -> Result<Vec<u8>, Failure>) -> SystemiconsStatus {
+    if icon.is_null() {
+        return finish(Err(invalid("icon is NULL")));
+    }
+    icon.write(SystemiconsBuffer::EMPTY);
+    let result = catch(lookup).map(|data| {
+        let len = data.len();
+        icon.write(SystemiconsBuffer { data: Box::into_raw(data.into_boxed_slice()) as *mut u8, len });
+    });
+    finish(result)
+}
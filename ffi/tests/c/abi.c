@@ -0,0 +1,144 @@
+/* Calls the C ABI like a C program does. Prints the failed checks and exits with 1 if any check fails. */
+#include <stdio.h>
+#include <string.h>
+
+#include "systemicons.h"
+
+#ifndef _WIN32
+#include <pthread.h>
+#endif
+
+static int failures = 0;
+
+#define CHECK(condition)                                                        \
+    do {                                                                        \
+        if (!(condition)) {                                                     \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition); \
+            failures++;                                                         \
+        }                                                                       \
+    } while (0)
+
+static int is_empty(const SystemiconsBuffer *buffer) {
+    return buffer->data == NULL && buffer->len == 0;
+}
+
+static int is_png(const SystemiconsBuffer *buffer) {
+    return buffer->len > 8 && memcmp(buffer->data, "\x89PNG\r\n\x1a\n", 8) == 0;
+}
+
+static void status_messages(void) {
+    CHECK(strcmp(systemicons_status_message(SYSTEMICONS_STATUS_OK), "ok") == 0);
+    CHECK(strcmp(systemicons_status_message(SYSTEMICONS_STATUS_NOT_FOUND), "not found") == 0);
+    CHECK(strlen(systemicons_status_message(SYSTEMICONS_STATUS_INTERNAL)) > 0);
+}
+
+static void invalid_arguments(SystemiconsProvider *provider) {
+    SystemiconsBuffer icon = { (uint8_t *)&icon, 42 };
+    CHECK(systemicons_get_icon(NULL, ".txt", 16, &icon) == SYSTEMICONS_STATUS_INVALID_ARGUMENT);
+    CHECK(is_empty(&icon));
+    CHECK(strstr(systemicons_last_error_message(), "provider") != NULL);
+
+    CHECK(systemicons_get_icon(provider, NULL, 16, &icon) == SYSTEMICONS_STATUS_INVALID_ARGUMENT);
+    CHECK(strstr(systemicons_last_error_message(), "extension") != NULL);
+    CHECK(systemicons_get_icon(provider, "\xff\xfe", 16, &icon) == SYSTEMICONS_STATUS_INVALID_ARGUMENT);
+    CHECK(strstr(systemicons_last_error_message(), "UTF-8") != NULL);
+    CHECK(systemicons_get_icon(provider, ".txt", 0, &icon) == SYSTEMICONS_STATUS_INVALID_ARGUMENT);
+    CHECK(systemicons_get_icon(provider, ".txt", 1025, &icon) == SYSTEMICONS_STATUS_INVALID_ARGUMENT);
+    CHECK(systemicons_get_icon(provider, ".txt", 16, NULL) == SYSTEMICONS_STATUS_INVALID_ARGUMENT);
+    CHECK(systemicons_get_icon_for_path(provider, NULL, 16, &icon) == SYSTEMICONS_STATUS_INVALID_ARGUMENT);
+    CHECK(strstr(systemicons_last_error_message(), "path") != NULL);
+    CHECK(is_empty(&icon));
+}
+
+static void free_buffer(void) {
+    SystemiconsBuffer icon = { NULL, 0 };
+    systemicons_free_buffer(NULL);
+    systemicons_free_buffer(&icon);
+    CHECK(is_empty(&icon));
+}
+
+/* The icons depend on the system: without display GTK is not available, but the failure is reported */
+static void lookups(SystemiconsProvider *provider) {
+    SystemiconsBuffer icon;
+    SystemiconsStatus status = systemicons_get_icon(provider, ".txt", 16, &icon);
+    if (status == SYSTEMICONS_STATUS_OK) {
+        CHECK(is_png(&icon));
+        CHECK(systemicons_last_error_message() == NULL);
+        SystemiconsBuffer cached;
+        CHECK(systemicons_get_icon(provider, ".TXT", 16, &cached) == SYSTEMICONS_STATUS_OK);
+        CHECK(cached.len == icon.len && memcmp(cached.data, icon.data, icon.len) == 0);
+        systemicons_free_buffer(&cached);
+        systemicons_free_buffer(&icon);
+        CHECK(is_empty(&icon));
+        systemicons_free_buffer(&icon);
+    } else {
+        CHECK(status == SYSTEMICONS_STATUS_NOT_FOUND || status == SYSTEMICONS_STATUS_BACKEND_UNAVAILABLE);
+        CHECK(is_empty(&icon));
+        CHECK(systemicons_last_error_message() != NULL);
+    }
+
+    status = systemicons_get_icon_for_path(provider, "/systemicons/does/not/exist.txt", 16, &icon);
+    if (status == SYSTEMICONS_STATUS_OK) {
+        systemicons_free_buffer(&icon);
+    } else {
+        CHECK(systemicons_last_error_message() != NULL);
+    }
+}
+
+#ifndef _WIN32
+/* Every thread has its own last error message */
+static void *invalid_size(void *provider) {
+    for (int i = 0; i < 100; i++) {
+        SystemiconsBuffer icon;
+        if (systemicons_get_icon(provider, ".txt", -1, &icon) != SYSTEMICONS_STATUS_INVALID_ARGUMENT
+            || strstr(systemicons_last_error_message(), "size -1") == NULL) {
+            return (void *)"wrong status or message";
+        }
+    }
+    return NULL;
+}
+
+static void *invalid_extension(void *provider) {
+    for (int i = 0; i < 100; i++) {
+        SystemiconsBuffer icon;
+        if (systemicons_get_icon(provider, NULL, 16, &icon) != SYSTEMICONS_STATUS_INVALID_ARGUMENT
+            || strstr(systemicons_last_error_message(), "extension") == NULL) {
+            return (void *)"wrong status or message";
+        }
+    }
+    return NULL;
+}
+
+static void threads(SystemiconsProvider *provider) {
+    pthread_t size_thread, extension_thread;
+    void *size_result, *extension_result;
+    CHECK(pthread_create(&size_thread, NULL, invalid_size, provider) == 0);
+    CHECK(pthread_create(&extension_thread, NULL, invalid_extension, provider) == 0);
+    CHECK(pthread_join(size_thread, &size_result) == 0);
+    CHECK(pthread_join(extension_thread, &extension_result) == 0);
+    CHECK(size_result == NULL);
+    CHECK(extension_result == NULL);
+}
+#endif
+
+int main(void) {
+    status_messages();
+    SystemiconsProvider *provider = systemicons_provider_new();
+    CHECK(provider != NULL);
+    invalid_arguments(provider);
+    free_buffer();
+
+    SystemiconsStatus status = systemicons_init();
+    CHECK(status == SYSTEMICONS_STATUS_OK || status == SYSTEMICONS_STATUS_BACKEND_UNAVAILABLE);
+    lookups(provider);
+#ifndef _WIN32
+    threads(provider);
+#endif
+
+    systemicons_provider_free(provider);
+    systemicons_provider_free(NULL);
+    if (failures == 0) {
+        printf("ok\n");
+    }
+    return failures == 0 ? 0 : 1;
+}
@@ -0,0 +1,47 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+/// The directory containing the shared library, the test executable is in the same directory
+fn library_dir() -> PathBuf {
+    env::current_exe().unwrap().parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn c_program() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let library_dir = library_dir();
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(if cfg!(windows) { "abi.exe" } else { "abi" });
+    let compiler = cc::Build::new().target(env!("TARGET")).host(env!("HOST")).opt_level(0).cargo_metadata(false).warnings(true).get_compiler();
+
+    let mut compile = compiler.to_command();
+    compile.arg(manifest_dir.join("tests").join("c").join("abi.c"));
+    if compiler.is_like_msvc() {
+        compile.arg(format!("/I{}", manifest_dir.join("include").display()));
+        compile.arg(format!("/Fe{}", program.display()));
+        compile.arg(library_dir.join("systemicons_ffi.dll.lib"));
+    } else {
+        compile.arg("-I").arg(manifest_dir.join("include"));
+        compile.arg("-o").arg(&program);
+        compile.arg("-L").arg(&library_dir).arg("-lsystemicons_ffi");
+        if !cfg!(windows) {
+            compile.arg("-pthread").arg(format!("-Wl,-rpath,{}", library_dir.display()));
+        }
+    }
+    let status = compile.status().unwrap();
+    assert!(status.success(), "compiling the C program failed: {:?}", compile);
+
+    // Windows finds the DLL in the PATH
+    let path = env::join_paths(std::iter::once(library_dir).chain(env::split_paths(&env::var_os("PATH").unwrap_or_default()))).unwrap();
+    let output = Command::new(&program).env("PATH", path).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
+
+#[test]
+fn header_is_up_to_date() {
+    let header = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("include").join("systemicons.h");
+    let generated = fs::read_to_string(env!("SYSTEMICONS_GENERATED_HEADER")).unwrap();
+    assert!(
+        fs::read_to_string(header).unwrap() == generated,
+        "include/systemicons.h is outdated, update it with `SYSTEMICONS_UPDATE_HEADER=1 cargo build -p systemicons-ffi`"
+    );
+}
@@ -0,0 +1,15 @@
+//! Looks up a custom icon in an arbitrary GVfs metadata tree and journal, the first two bytes are the length of the tree
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let len = (u16::from_be_bytes([data[0], data[1]]) as usize).min(data.len() - 2);
+    let (tree, journal) = data[2..].split_at(len);
+    for path in ["/", "/Documents", "/Documents/Projects"] {
+        systemicons::fuzzing::gvfs_metadata(tree, journal, path, "custom-icon");
+    }
+});
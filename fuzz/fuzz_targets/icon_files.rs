@@ -0,0 +1,24 @@
+//! Parses arbitrary data as ICO, ICNS, PE and shortcut files and decodes the icons which have been found
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use systemicons::{icns::IconFamily, ico::{IconDir, IconEntry}, pe::PeFile, shortcut::Shortcut};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(dir) = IconDir::read(data) {
+        for entry in &dir.entries {
+            let _ = entry.decode();
+        }
+    }
+    if let Ok(family) = IconFamily::read(data) {
+        let _ = family.get_png(32);
+    }
+    if let Ok(pe_file) = PeFile::parse(data.to_vec()) {
+        for group in pe_file.icon_groups() {
+            let _ = pe_file.get_icon(&group, 32);
+        }
+    }
+    let _ = IconEntry::from_data(data.to_vec()).map(|entry| entry.decode());
+    let _ = Shortcut::parse_lnk(data);
+    let _ = Shortcut::parse_url(&String::from_utf8_lossy(data));
+});
@@ -0,0 +1,22 @@
+//! Validates arbitrary strings and serves them as query of an icon request, with a loader which does not need GTK
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use systemicons::{http::IconParams, input, Error};
+
+fuzz_target!(|query: &str| {
+    let _ = input::text(query, "file extension");
+    let _ = input::file_name(query);
+    if let Ok(params) = IconParams::parse(query) {
+        assert!(params.ext.starts_with('.'));
+        assert!(input::size(params.pixels()).is_ok());
+    }
+    if let Ok(request) = http::Request::builder().uri(format!("/icon?{}", query)).header("accept", query).body(()) {
+        let response = systemicons::http::handle_with(&request, |ext, size, _| {
+            input::text(ext, "file extension")?;
+            input::size(size)?;
+            Err(Error::NotFound { input: ext.to_string(), size: Some(size), reason: String::new() })
+        });
+        assert!(!response.status().is_server_error());
+    }
+});
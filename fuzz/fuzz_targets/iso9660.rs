@@ -0,0 +1,8 @@
+//! Reads the icon of arbitrary data as ISO 9660 image with Rock Ridge extensions, the file system of type 1 AppImages
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    systemicons::fuzzing::iso9660(data);
+});
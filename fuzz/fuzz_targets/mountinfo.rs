@@ -0,0 +1,8 @@
+//! Parses arbitrary text as `/proc/self/mountinfo`
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    systemicons::fuzzing::mountinfo(text);
+});
@@ -0,0 +1,11 @@
+//! Parses arbitrary data as property list, the XML format is tried as well when the data starts with the binary magic
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    systemicons::fuzzing::plist(data);
+    if let Some(xml) = data.strip_prefix(b"bplist00") {
+        systemicons::fuzzing::plist(xml);
+    }
+});
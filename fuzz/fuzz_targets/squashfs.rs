@@ -0,0 +1,8 @@
+//! Reads the icon of arbitrary data as SquashFS image, the file system of type 2 AppImages
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    systemicons::fuzzing::squashfs(data);
+});
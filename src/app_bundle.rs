@@ -0,0 +1,38 @@
+//! Reading the icons of macOS application bundles (`.app` directories) without Cocoa, for example
+//! from a mounted DMG or a bundle copied to another system.
+use std::{fs, path::Path};
+
+use crate::{icns::IconFamily, plist, Error};
+
+/// Reads the icon named in the `Info.plist` of a bundle from its resources and returns the image which fits
+/// `size` best as PNG. `CFBundleIconFile` is preferred, `CFBundleIconName` refers to the asset catalog,
+/// but Xcode also copies that icon as `.icns` file into the resources
+pub fn get_icon(path: &Path, size: i32) -> Result<Vec<u8>, Error> {
+    let contents = path.join("Contents");
+    let info = fs::read(contents.join("Info.plist")).map_err(|_| Error::invalid_input(&path.to_string_lossy(), "The directory is no application bundle"))?;
+    let info = plist::parse(&info)?;
+    let resources = contents.join("Resources");
+    let icon = ["CFBundleIconFile", "CFBundleIconName"]
+        .iter()
+        .filter_map(|key| info.get(key)?.as_str())
+        .filter(|name| !name.is_empty())
+        .find_map(|name| find_icns(&resources, name))
+        .ok_or_else(|| Error::not_found(&path.to_string_lossy(), Some(size), "No icon found in the application bundle"))?;
+    IconFamily::read(&fs::read(icon)?)?.get_png(size)
+}
+
+/// Finds the icon file in the resources, the extension `.icns` may be omitted in the `Info.plist`.
+/// Bundles are usually created on case insensitive file systems, so the case of the name is ignored
+fn find_icns(resources: &Path, name: &str) -> Option<std::path::PathBuf> {
+    let file_name = if name.to_ascii_lowercase().ends_with(".icns") { name.to_string() } else { format!("{}.icns", name) };
+    let exact = resources.join(&file_name);
+    if exact.is_file() {
+        return Some(exact);
+    }
+    fs::read_dir(resources)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(&file_name))
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+}
@@ -0,0 +1,200 @@
+//! Packs the icons of several requests into one PNG, a sprite sheet, so that a web UI downloads one image
+//! instead of one image per row of a file listing.
+//!
+//! The manifest maps each request to its rectangle in the atlas. With the feature `json` it is serialized as JSON,
+//! [`Manifest::to_css`] writes a stylesheet with one class per request.
+use std::{collections::HashMap, io::Cursor};
+
+use image::{imageops, ImageFormat, RgbaImage};
+
+#[cfg(any(not(target_os = "linux"), feature = "backend-gtk"))]
+use crate::batch;
+use crate::{batch::IconBatch, Error};
+
+/// How the atlas is packed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasOptions {
+    /// Transparent pixels between the icons, so that scaled icons do not bleed into each other
+    pub padding: u32,
+    /// The atlas is packed in rows up to this width
+    pub max_width: u32,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        AtlasOptions { padding: 1, max_width: 1024 }
+    }
+}
+
+/// An atlas and where the icons are
+pub struct Atlas {
+    pub png: Vec<u8>,
+    pub manifest: Manifest,
+    /// The requests without icon, they are missing in the manifest
+    pub errors: Vec<(String, i32, Error)>,
+}
+
+/// The rectangles of the icons in the atlas
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct Manifest {
+    pub width: u32,
+    pub height: u32,
+    pub sprites: Vec<Sprite>,
+}
+
+/// The rectangle of a request in the atlas, requests with the same icon and size share the rectangle
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct Sprite {
+    pub input: String,
+    pub size: i32,
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Manifest {
+    /// Serializes the manifest like `{"width":64,"height":32,"sprites":[{"input":".pdf","size":32,"x":0,...}]}`
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|err| Error::encode("manifest", None, err))
+    }
+
+    /// A stylesheet with the class `prefix` showing the atlas and one class per sprite like `.icon-pdf-32`
+    /// positioning it. Use them together: `<span class="icon icon-pdf-32"></span>`
+    pub fn to_css(&self, image_url: &str, prefix: &str) -> String {
+        let mut css = format!(
+            ".{} {{ display: inline-block; background-image: url(\"{}\"); background-repeat: no-repeat; }}\n",
+            prefix,
+            image_url.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        let mut classes: Vec<String> = Vec::new();
+        for sprite in &self.sprites {
+            let class = format!("{}-{}-{}", prefix, class_name(&sprite.input), sprite.size);
+            if classes.contains(&class) {
+                continue;
+            }
+            css.push_str(&format!(
+                ".{} {{ width: {}px; height: {}px; background-position: {} {}; }}\n",
+                class,
+                sprite.w,
+                sprite.h,
+                offset(sprite.x),
+                offset(sprite.y)
+            ));
+            classes.push(class);
+        }
+        css
+    }
+}
+
+/// Retrieves the icons of file extensions with their sizes and packs them into an atlas
+#[cfg(any(not(target_os = "linux"), feature = "backend-gtk"))]
+pub fn build_atlas(requests: &[(&str, i32)], options: &AtlasOptions) -> Result<Atlas, Error> {
+    build_atlas_from(requests, batch::get_icons(requests), options)
+}
+
+/// Packs the icons of a batch into an atlas, `requests` are the requests of the batch. Icons are scaled to fit
+/// into the requested size
+pub fn build_atlas_from(requests: &[(&str, i32)], batch: IconBatch, options: &AtlasOptions) -> Result<Atlas, Error> {
+    let mut images: Vec<RgbaImage> = Vec::new();
+    let mut image_indices: HashMap<(usize, i32), usize> = HashMap::new();
+    let mut placements: Vec<(String, i32, usize)> = Vec::new();
+    let mut errors = Vec::new();
+    for (&(input, size), entry) in requests.iter().zip(batch.entries) {
+        if placements.iter().any(|(known, known_size, _)| known == input && *known_size == size)
+            || errors.iter().any(|(known, known_size, _)| known == input && *known_size == size)
+        {
+            continue;
+        }
+        let icon = match entry {
+            Ok(icon) => icon,
+            Err(err) => {
+                errors.push((input.to_string(), size, err));
+                continue;
+            }
+        };
+        let image = match image_indices.get(&(icon, size)) {
+            Some(&image) => image,
+            None => {
+                let image = image::load_from_memory_with_format(&batch.icons[icon], ImageFormat::Png).map_err(|err| Error::from(err).context(input, Some(size)))?;
+                images.push(fit(image.to_rgba8(), size));
+                image_indices.insert((icon, size), images.len() - 1);
+                images.len() - 1
+            }
+        };
+        placements.push((input.to_string(), size, image));
+    }
+
+    let positions = pack(&images, options);
+    let width = images.iter().zip(&positions).map(|(image, (x, _))| x + image.width()).max().unwrap_or(0);
+    let height = images.iter().zip(&positions).map(|(image, (_, y))| y + image.height()).max().unwrap_or(0);
+    let mut atlas = RgbaImage::new(width.max(1), height.max(1));
+    for (image, &(x, y)) in images.iter().zip(&positions) {
+        imageops::replace(&mut atlas, image, x as i64, y as i64);
+    }
+    let mut png = Cursor::new(Vec::new());
+    atlas.write_to(&mut png, ImageFormat::Png).map_err(|err| Error::encode("atlas", None, err))?;
+
+    let sprites = placements
+        .into_iter()
+        .map(|(input, size, image)| {
+            let (x, y) = positions[image];
+            Sprite { input, size, x, y, w: images[image].width(), h: images[image].height() }
+        })
+        .collect();
+    Ok(Atlas { png: png.into_inner(), manifest: Manifest { width: atlas.width(), height: atlas.height(), sprites }, errors })
+}
+
+/// Scales an image down or up to fit into `size`, keeping the aspect ratio
+pub(crate) fn fit(image: RgbaImage, size: i32) -> RgbaImage {
+    let size = size.max(1) as u32;
+    let longest = image.width().max(image.height()).max(1);
+    if longest == size {
+        return image;
+    }
+    let width = (image.width() * size / longest).max(1);
+    let height = (image.height() * size / longest).max(1);
+    imageops::resize(&image, width, height, imageops::FilterType::Lanczos3)
+}
+
+/// Shelf packing: the images sorted by height are placed in rows, a new row starts when the maximum width is reached
+fn pack(images: &[RgbaImage], options: &AtlasOptions) -> Vec<(u32, u32)> {
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    order.sort_by_key(|&image| std::cmp::Reverse((images[image].height(), images[image].width())));
+    let max_width = images.iter().map(|image| image.width()).max().unwrap_or(0).max(options.max_width);
+    let mut positions = vec![(0, 0); images.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for image in order {
+        let (width, height) = images[image].dimensions();
+        if x > 0 && x + width > max_width {
+            x = 0;
+            y += row_height + options.padding;
+            row_height = 0;
+        }
+        positions[image] = (x, y);
+        x += width + options.padding;
+        row_height = row_height.max(height);
+    }
+    positions
+}
+
+fn offset(position: u32) -> String {
+    if position == 0 {
+        "0".to_string()
+    } else {
+        format!("-{}px", position)
+    }
+}
+
+/// `.pdf` becomes `pdf`, characters which are not allowed in class names become `-`
+fn class_name(input: &str) -> String {
+    let name: String = input.trim_start_matches('.').chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '-' }).collect();
+    if name.is_empty() {
+        "none".to_string()
+    } else {
+        name
+    }
+}
@@ -0,0 +1,295 @@
+//! Icon backends which can be combined and replaced at runtime.
+//!
+//! An [`IconBackend`] resolves the MIME type of a file extension, resolves an extension, MIME type or icon name to
+//! an icon and loads it. The backends of the platforms are enabled with cargo features:
+//!
+//! | Feature               | Backend                 | Platform |
+//! |-----------------------|-------------------------|----------|
+//! | `backend-gtk`         | [`GtkBackend`]          | Linux    |
+//! | `backend-freedesktop` | [`FreedesktopBackend`]  | Linux    |
+//! | `backend-windows`     | [`WindowsBackend`]      | Windows  |
+//! | `backend-macos`       | [`MacosBackend`]        | macOS    |
+//!
+//! All but `backend-freedesktop` are enabled by default. Without `backend-gtk` GTK is not linked and the Linux
+//! functions of the crate root which need it are not available.
+//!
+//! An [`IconProvider`] asks its backends by priority, so a company icon set may override the icons of the system
+//! and a mock may replace them in tests:
+//!
+//! ```ignore
+//! let provider = IconProvider::system().with_backend(100, CompanyIcons::load("/opt/company/icons"));
+//! let icon = provider.get_icon(".pdf", 32)?;
+//! ```
+use std::{fs, path::PathBuf};
+
+use crate::{input, Error};
+
+/// What an icon is requested for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconName<'a> {
+    /// A file extension like `.pdf` or a file name
+    Extension(&'a str),
+    MimeType(&'a str),
+    /// The name of an icon of the icon theme like `folder`
+    Icon(&'a str),
+}
+
+impl IconName<'_> {
+    /// The extension, MIME type or icon name
+    pub fn as_str(&self) -> &str {
+        match self {
+            IconName::Extension(name) | IconName::MimeType(name) | IconName::Icon(name) => name,
+        }
+    }
+}
+
+/// An icon which has been resolved by a backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedIcon {
+    /// An icon file, like one of the icon theme
+    File(PathBuf),
+    /// An icon which only exists in memory, like the icons which Windows extracts
+    Data(Vec<u8>),
+}
+
+/// A source of icons
+pub trait IconBackend: Send + Sync {
+    /// A short name for traces, like `gtk`
+    fn name(&self) -> &str;
+
+    /// The MIME type of a file extension, `None` if it is unknown or the backend has no MIME types
+    fn resolve_mime_type(&self, _ext: &str) -> Option<String> {
+        None
+    }
+
+    /// Finds the icon in `size` pixels, fails with [`Error::NotFound`] if the backend has none,
+    /// so that the next backend is asked
+    fn resolve(&self, name: IconName, size: i32) -> Result<ResolvedIcon, Error>;
+
+    /// Loads an icon which has been resolved by this backend. The icon is formatted as png, unless the backend
+    /// has it in a different format
+    fn load(&self, icon: &ResolvedIcon) -> Result<Vec<u8>, Error> {
+        match icon {
+            ResolvedIcon::File(file) => fs::read(file).map_err(|err| Error::from(err).context(&file.to_string_lossy(), None)),
+            ResolvedIcon::Data(data) => Ok(data.clone()),
+        }
+    }
+}
+
+/// Backends ordered by priority. Backends with a higher priority are asked first, those with the same priority in
+/// the order they have been added. When a backend has no icon or is not available, the next one is asked
+#[derive(Default)]
+pub struct IconProvider {
+    backends: Vec<(i32, Box<dyn IconBackend>)>,
+}
+
+impl IconProvider {
+    /// A provider without backends
+    pub fn new() -> IconProvider {
+        IconProvider::default()
+    }
+
+    /// The backends of the platform which are enabled, on Linux GTK before the freedesktop backend
+    pub fn system() -> IconProvider {
+        let backends: Vec<(i32, Box<dyn IconBackend>)> = vec![
+            #[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+            (0, Box::new(GtkBackend)),
+            #[cfg(all(target_os = "linux", feature = "backend-freedesktop"))]
+            (-10, Box::new(FreedesktopBackend::new())),
+            #[cfg(all(target_os = "windows", feature = "backend-windows"))]
+            (0, Box::new(WindowsBackend)),
+            #[cfg(all(target_os = "macos", feature = "backend-macos"))]
+            (0, Box::new(MacosBackend)),
+        ];
+        IconProvider { backends }
+    }
+
+    /// Adds a backend with a priority
+    pub fn with_backend<B: IconBackend + 'static>(mut self, priority: i32, backend: B) -> IconProvider {
+        let position = self.backends.iter().position(|(other, _)| *other < priority).unwrap_or(self.backends.len());
+        self.backends.insert(position, (priority, Box::new(backend)));
+        self
+    }
+
+    /// The names of the backends in the order they are asked
+    pub fn backend_names(&self) -> Vec<&str> {
+        self.backends.iter().map(|(_, backend)| backend.name()).collect()
+    }
+
+    /// The MIME type of a file extension from the first backend which knows it
+    pub fn mime_type(&self, ext: &str) -> Option<String> {
+        self.backends.iter().find_map(|(_, backend)| backend.resolve_mime_type(ext))
+    }
+
+    /// Retrieving the icon of a file extension from the first backend which has it
+    pub fn get_icon(&self, ext: &str, size: i32) -> Result<Vec<u8>, Error> {
+        self.get(IconName::Extension(ext), size)
+    }
+
+    /// Retrieving the icon of a MIME type from the first backend which has it
+    pub fn get_icon_for_mime_type(&self, mime_type: &str, size: i32) -> Result<Vec<u8>, Error> {
+        self.get(IconName::MimeType(mime_type), size)
+    }
+
+    /// Retrieving an icon of the icon theme from the first backend which has it
+    pub fn get_icon_by_name(&self, name: &str, size: i32) -> Result<Vec<u8>, Error> {
+        self.get(IconName::Icon(name), size)
+    }
+
+    /// Retrieving an icon from the first backend which has it. If no backend has it, the error of the first
+    /// backend which is available is returned. Other errors like [`Error::Decode`] stop the lookup
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get(&self, name: IconName, size: i32) -> Result<Vec<u8>, Error> {
+        input::text(name.as_str(), "name")?;
+        input::size(size).map_err(|err| err.context(name.as_str(), Some(size)))?;
+        let mut error = None;
+        for (_, backend) in &self.backends {
+            match backend.resolve(name, size).and_then(|icon| backend.load(&icon)) {
+                Ok(icon) => {
+                    tracing::debug!(backend = backend.name(), "icon found");
+                    return Ok(icon);
+                }
+                Err(err @ (Error::NotFound { .. } | Error::BackendUnavailable { .. })) => {
+                    tracing::debug!(backend = backend.name(), error = %err, "trying the next backend");
+                    if matches!(error, None | Some(Error::BackendUnavailable { .. })) {
+                        error = Some(err);
+                    }
+                }
+                Err(err) => return Err(err.context(name.as_str(), Some(size))),
+            }
+        }
+        Err(error.unwrap_or_else(|| Error::not_found("", None, "The provider has no backends")).context(name.as_str(), Some(size)))
+    }
+}
+
+/// The icons of the current GTK icon theme, GTK has to be initialized with [`crate::init`]
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GtkBackend;
+
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+impl IconBackend for GtkBackend {
+    fn name(&self) -> &str {
+        "gtk"
+    }
+
+    fn resolve_mime_type(&self, ext: &str) -> Option<String> {
+        crate::linux::request::guess_mime_type(ext)
+    }
+
+    fn resolve(&self, name: IconName, size: i32) -> Result<ResolvedIcon, Error> {
+        let file = match name {
+            IconName::Extension(ext) => crate::linux::request::get_icon_as_file(ext, size)?,
+            IconName::MimeType(mime_type) => crate::linux::request::get_icon_for_mime_type_as_file(mime_type, size)?,
+            IconName::Icon(icon) => crate::linux::request::get_icon_by_names(&[icon], size)?,
+        };
+        Ok(ResolvedIcon::File(file.into()))
+    }
+}
+
+/// The icons of a freedesktop.org icon theme, looked up in Rust without GTK: the MIME type is detected with the
+/// shared MIME-info database and its icon names are searched in the theme and the themes it inherits from
+#[cfg(all(target_os = "linux", feature = "backend-freedesktop"))]
+pub struct FreedesktopBackend {
+    database: crate::linux::shared_mime::MimeDatabase,
+    base_dirs: Vec<PathBuf>,
+    theme: String,
+}
+
+#[cfg(all(target_os = "linux", feature = "backend-freedesktop"))]
+impl FreedesktopBackend {
+    /// The XDG data directories and the icon theme configured in `gtk-3.0/settings.ini`, or `hicolor`
+    pub fn new() -> FreedesktopBackend {
+        use crate::linux::{icon_theme, xdg};
+        let data_dirs: Vec<PathBuf> = std::iter::once(xdg::get_data_home()).chain(xdg::get_data_dirs()).collect();
+        let mut base_dirs = icon_theme::get_base_dirs();
+        base_dirs.extend(xdg::get_missing_data_dirs().iter().map(|dir| dir.join("icons")));
+        FreedesktopBackend {
+            database: crate::linux::shared_mime::MimeDatabase::load(&data_dirs),
+            base_dirs,
+            theme: icon_theme::get_configured_theme().unwrap_or_else(|| "hicolor".to_string()),
+        }
+    }
+
+    /// The `mime` and `icons` directories of the given data directories and the icon theme `theme`
+    pub fn in_dirs(data_dirs: &[PathBuf], theme: &str) -> FreedesktopBackend {
+        FreedesktopBackend {
+            database: crate::linux::shared_mime::MimeDatabase::load(data_dirs),
+            base_dirs: data_dirs.iter().map(|dir| dir.join("icons")).collect(),
+            theme: theme.to_string(),
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "backend-freedesktop"))]
+impl Default for FreedesktopBackend {
+    fn default() -> Self {
+        FreedesktopBackend::new()
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "backend-freedesktop"))]
+impl IconBackend for FreedesktopBackend {
+    fn name(&self) -> &str {
+        "freedesktop"
+    }
+
+    fn resolve_mime_type(&self, ext: &str) -> Option<String> {
+        let (mime_type, _) = self.database.detect_name(ext)?;
+        Some(self.database.unalias(&mime_type).map(|canonical| canonical.to_string()).unwrap_or(mime_type))
+    }
+
+    fn resolve(&self, name: IconName, size: i32) -> Result<ResolvedIcon, Error> {
+        use crate::linux::{explain, icon_theme};
+        let (icon, failure) = match name {
+            IconName::Extension(ext) => {
+                let explanation = explain::explain_with(&crate::IconRequest::extension(ext, size), &self.database, &self.base_dirs, &self.theme);
+                (explanation.icon, explanation.failure)
+            }
+            IconName::MimeType(mime_type) => {
+                let explanation = explain::explain_with(&crate::IconRequest::mime_type(mime_type, size), &self.database, &self.base_dirs, &self.theme);
+                (explanation.icon, explanation.failure)
+            }
+            IconName::Icon(icon) => (icon_theme::lookup_icon(&self.base_dirs, &self.theme, &[icon.to_string()], size, 1, explain::EXTENSIONS).icon, None),
+        };
+        icon.map(ResolvedIcon::File).ok_or_else(|| Error::not_found("", Some(size), failure.unwrap_or_else(|| "No icon found in the icon theme".to_string())))
+    }
+}
+
+/// The icons of the Windows shell
+#[cfg(all(target_os = "windows", feature = "backend-windows"))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WindowsBackend;
+
+#[cfg(all(target_os = "windows", feature = "backend-windows"))]
+impl IconBackend for WindowsBackend {
+    fn name(&self) -> &str {
+        "windows"
+    }
+
+    fn resolve(&self, name: IconName, size: i32) -> Result<ResolvedIcon, Error> {
+        match name {
+            IconName::Extension(ext) => Ok(ResolvedIcon::Data(crate::windows::request::get_icon(ext, size)?)),
+            _ => Err(Error::not_found(name.as_str(), Some(size), "Windows only has icons for file extensions")),
+        }
+    }
+}
+
+/// The icons of `NSWorkspace`
+#[cfg(all(target_os = "macos", feature = "backend-macos"))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MacosBackend;
+
+#[cfg(all(target_os = "macos", feature = "backend-macos"))]
+impl IconBackend for MacosBackend {
+    fn name(&self) -> &str {
+        "macos"
+    }
+
+    fn resolve(&self, name: IconName, size: i32) -> Result<ResolvedIcon, Error> {
+        match name {
+            IconName::Extension(ext) => Ok(ResolvedIcon::File(crate::macos::request::get_icon_as_file(ext, size.into())?.into())),
+            _ => Err(Error::not_found(name.as_str(), Some(size), "macOS only has icons for file extensions")),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use crate::Error;
+
+/// The icons of several requests. Requests resolving to the same icon share it, so a listing of 500 files
+/// with a handful of file types only contains a handful of icons
+pub struct IconBatch {
+    /// The distinct icons formatted as png
+    pub icons: Vec<Vec<u8>>,
+    /// For each request the index of its icon in `icons`
+    pub entries: Vec<Result<usize, Error>>,
+}
+
+impl IconBatch {
+    /// The icon of the request with the index
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        let icon = *self.entries.get(index)?.as_ref().ok()?;
+        self.icons.get(icon).map(|icon| icon.as_slice())
+    }
+}
+
+/// Retrieves the icons of file extensions with their sizes, every distinct extension and size is only looked up once
+#[cfg(any(not(target_os = "linux"), feature = "backend-gtk"))]
+pub fn get_icons(requests: &[(&str, i32)]) -> IconBatch {
+    get_icons_with(requests, crate::get_icon)
+}
+
+/// Retrieves the icons with `load`, which returns the icon of a file extension at a size formatted as png
+pub fn get_icons_with<F>(requests: &[(&str, i32)], load: F) -> IconBatch
+where
+    F: Fn(&str, i32) -> Result<Vec<u8>, Error>,
+{
+    let mut icons: Vec<Vec<u8>> = Vec::new();
+    let mut icon_indices: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut resolved: HashMap<(String, i32), Result<usize, Error>> = HashMap::new();
+    let entries = requests
+        .iter()
+        .map(|&(ext, size)| {
+            let key = (ext.trim().to_lowercase(), size);
+            if let Some(result) = resolved.get(&key) {
+                return result.as_ref().copied().map_err(Error::copy);
+            }
+            let result = load(&key.0, size).map_err(|err| err.context(ext, Some(size))).map(|icon| {
+                *icon_indices.entry(icon).or_insert_with_key(|icon| {
+                    icons.push(icon.clone());
+                    icons.len() - 1
+                })
+            });
+            let entry = result.as_ref().copied().map_err(Error::copy);
+            resolved.insert(key, result);
+            entry
+        })
+        .collect();
+    IconBatch { icons, entries }
+}
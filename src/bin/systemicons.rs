@@ -0,0 +1,562 @@
+//! Command line tool to look up system icons, for debugging which icon a file gets.
+//!
+//! Exit codes: 0 success, 1 I/O error, 2 invalid arguments, 3 icon not found, 4 invalid input,
+//! 5 icon backend like GTK not available, 6 decoding error, 7 encoding error
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use systemicons::{
+    export::{ExportInput, ExportOptions, ExportTarget},
+    Error,
+};
+
+#[derive(Parser)]
+#[command(name = "systemicons", version, about = "Look up the system icons of file extensions, files and MIME types")]
+struct Cli {
+    /// Print the results as JSON
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Get the icon of a file extension, a path or a MIME type
+    Get {
+        input: String,
+        #[command(flatten)]
+        options: IconOptions,
+        /// Print the icon as PNG or the path of the icon file, with `--output` the icon file is copied
+        #[arg(long, value_enum, default_value_t = Format::Png)]
+        format: Format,
+        /// Write the icon to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// List the installed icon themes
+    Themes,
+    /// Print the MIME type of a file
+    Mime { path: String },
+    /// Show how the icon of a file extension, a path or a MIME type is found
+    Explain {
+        input: String,
+        #[command(flatten)]
+        options: IconOptions,
+    },
+    /// Write an icon pack with `manifest.json` for clients without the icon theme
+    Export {
+        /// Extensions and MIME types, by default `type/subtype` is a MIME type, everything else an extension
+        inputs: Vec<String>,
+        /// Export all MIME types of the shared MIME-info database
+        #[arg(long)]
+        all_mime_types: bool,
+        /// The sizes in logical pixels, separated by commas
+        #[arg(long, value_delimiter = ',', default_values_t = [16, 32, 48])]
+        sizes: Vec<i32>,
+        /// The scale factors, separated by commas
+        #[arg(long, value_delimiter = ',', default_values_t = [1, 2])]
+        scales: Vec<i32>,
+        /// Leave out the scalable SVG icons
+        #[arg(long)]
+        no_svg: bool,
+        /// The directory, or the zip archive if it ends with `.zip`
+        #[arg(short, long)]
+        output: PathBuf,
+        /// How the inputs are interpreted
+        #[arg(long, value_enum, default_value_t = Kind::Auto)]
+        kind: Kind,
+    },
+}
+
+#[derive(clap::Args)]
+struct IconOptions {
+    /// The size in logical pixels
+    #[arg(long, default_value_t = 32)]
+    size: i32,
+    /// The scale factor, the icon has `size * scale` pixels
+    #[arg(long, default_value_t = 1)]
+    scale: i32,
+    /// How the input is interpreted, by default existing paths are paths, `type/subtype` is a MIME type,
+    /// everything else is an extension
+    #[arg(long, value_enum, default_value_t = Kind::Auto)]
+    kind: Kind,
+}
+
+impl IconOptions {
+    /// The size in physical pixels, fails if the scale is not positive or `size * scale` is too large
+    fn pixels(&self, input: &str) -> Result<i32, Error> {
+        if self.scale < 1 {
+            return Err(Error::InvalidInput { input: input.to_string(), reason: format!("The scale {} is not positive", self.scale) });
+        }
+        self.size.checked_mul(self.scale).ok_or_else(|| Error::InvalidInput {
+            input: input.to_string(),
+            reason: format!("The size {} with the scale {} is too large", self.size, self.scale),
+        })
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Png,
+    Path,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Auto,
+    Ext,
+    Path,
+    Mime,
+}
+
+#[derive(Serialize)]
+struct IconResult {
+    input: String,
+    kind: Kind,
+    size: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<PathBuf>,
+    bytes: usize,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Serialize)]
+struct Theme {
+    id: String,
+    name: String,
+    path: PathBuf,
+    inherits: Vec<String>,
+    hidden: bool,
+    current: bool,
+}
+
+#[derive(Serialize)]
+struct Explanation {
+    input: String,
+    kind: Kind,
+    query: String,
+    size: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[cfg(target_os = "linux")]
+    trace: Trace,
+}
+
+/// The lookup in the shared MIME-info database and the icon themes
+#[cfg(target_os = "linux")]
+#[derive(Serialize)]
+struct Trace {
+    mime_type: String,
+    reason: String,
+    steps: Vec<String>,
+    icon_names: Vec<String>,
+    theme: String,
+    visits: Vec<Visit>,
+    unthemed: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failure: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Serialize)]
+struct Visit {
+    theme: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<PathBuf>,
+    directories: usize,
+    candidates: Vec<Candidate>,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Serialize)]
+struct Candidate {
+    icon_name: String,
+    directory: String,
+    file: PathBuf,
+    matches_size: bool,
+    size_distance: i32,
+}
+
+#[derive(Serialize)]
+struct ErrorOutput<'a> {
+    error: &'a str,
+    kind: &'a str,
+    exit_code: u8,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    #[cfg(target_os = "linux")]
+    let result = if matches!(cli.command, Command::Mime { .. }) { run(&cli) } else { systemicons::init().and_then(|()| run(&cli)) };
+    #[cfg(not(target_os = "linux"))]
+    let result = run(&cli);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            let (kind, exit_code) = classify(&err);
+            if cli.json {
+                let output = ErrorOutput { error: &format!("{:#}", err), kind, exit_code };
+                eprintln!("{}", serde_json::to_string(&output).unwrap_or_default());
+            } else {
+                eprintln!("systemicons: {:#}", err);
+            }
+            ExitCode::from(exit_code)
+        }
+    }
+}
+
+/// Maps the error variants to the exit codes
+fn classify(error: &Error) -> (&'static str, u8) {
+    match error {
+        Error::Io { .. } => ("io", 1),
+        Error::NotFound { .. } => ("not_found", 3),
+        Error::InvalidInput { .. } => ("invalid_input", 4),
+        Error::BackendUnavailable { .. } => ("backend_unavailable", 5),
+        Error::Decode { .. } => ("decode", 6),
+        Error::Encode { .. } => ("encode", 7),
+        _ => ("error", 1),
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), Error> {
+    match &cli.command {
+        Command::Get { input, options, format, output } => get(cli.json, input, options, *format, output.as_deref()),
+        Command::Themes => themes(cli.json),
+        Command::Mime { path } => {
+            let mime_type = get_mime_type(path)?;
+            print(cli.json, &serde_json::json!({ "path": path, "mime_type": mime_type }), &mime_type)
+        }
+        Command::Explain { input, options } => explain(cli.json, input, options),
+        Command::Export { inputs, all_mime_types, sizes, scales, no_svg, output, kind } => {
+            let options = ExportOptions { sizes: sizes.clone(), scales: scales.clone(), svg: !no_svg };
+            export(cli.json, inputs, *all_mime_types, &options, output, *kind)
+        }
+    }
+}
+
+fn get(json: bool, input: &str, options: &IconOptions, format: Format, output: Option<&Path>) -> Result<(), Error> {
+    let kind = classify_input(input, options.kind);
+    let size = options.pixels(input)?;
+    let mut result = IconResult { input: input.to_string(), kind, size, file: None, output: output.map(Path::to_path_buf), bytes: 0 };
+    match format {
+        Format::Path => {
+            let file = get_icon_as_file(input, kind, size)?;
+            result.bytes = fs::metadata(&file).map(|metadata| metadata.len() as usize).unwrap_or(0);
+            result.file = Some(file.clone());
+            if let Some(output) = output {
+                fs::copy(&file, output)?;
+            }
+            print(json, &result, &file)
+        }
+        Format::Png => {
+            let icon = get_icon(input, kind, size)?;
+            result.bytes = icon.len();
+            match output {
+                Some(output) => {
+                    fs::write(output, &icon)?;
+                    print(json, &result, &output.to_string_lossy())
+                }
+                None if json => print(json, &result, ""),
+                None => Ok(io::stdout().write_all(&icon)?),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn themes(json: bool) -> Result<(), Error> {
+    let current = systemicons::get_current_icon_theme().ok();
+    let themes: Vec<Theme> = systemicons::get_icon_themes()
+        .into_iter()
+        .map(|theme| Theme {
+            current: current.as_deref() == Some(theme.id.as_str()),
+            id: theme.id,
+            name: theme.name,
+            path: theme.path,
+            inherits: theme.inherits,
+            hidden: theme.hidden,
+        })
+        .collect();
+    let text = themes
+        .iter()
+        .map(|theme| {
+            let marker = if theme.current { "*" } else { " " };
+            let hidden = if theme.hidden { " (hidden)" } else { "" };
+            format!("{} {:<24} {}{}  {}", marker, theme.id, theme.name, hidden, theme.path.to_string_lossy())
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    print(json, &themes, &text)
+}
+#[cfg(not(target_os = "linux"))]
+fn themes(_json: bool) -> Result<(), Error> {
+    Err(unsupported("Icon themes"))
+}
+
+fn explain(json: bool, input: &str, options: &IconOptions) -> Result<(), Error> {
+    let kind = classify_input(input, options.kind);
+    let size = options.pixels(input)?;
+    #[cfg(target_os = "linux")]
+    let trace = trace(input, kind, options);
+    #[cfg(target_os = "linux")]
+    let mime_type = Some(trace.mime_type.clone());
+    #[cfg(not(target_os = "linux"))]
+    let mime_type = match kind {
+        Kind::Mime => Some(input.to_string()),
+        Kind::Path => get_mime_type(input).ok(),
+        _ => None,
+    };
+    let file = get_icon_as_file(input, kind, size);
+    let explanation = Explanation {
+        input: input.to_string(),
+        kind,
+        query: query(input, kind),
+        size,
+        mime_type,
+        file: file.as_ref().ok().cloned(),
+        error: file.as_ref().err().map(|err| format!("{:#}", err)),
+        #[cfg(target_os = "linux")]
+        trace,
+    };
+    let mut lines = vec![
+        format!("input:     {}", explanation.input),
+        format!("kind:      {}", kind_name(kind)),
+        format!("query:     {}", explanation.query),
+        format!("size:      {}", size),
+    ];
+    if let Some(mime_type) = &explanation.mime_type {
+        lines.push(format!("mime type: {}", mime_type));
+    }
+    #[cfg(target_os = "linux")]
+    lines.extend(trace_lines(&explanation.trace));
+    match &file {
+        Ok(file) => lines.push(format!("icon:      {}", file)),
+        Err(err) => lines.push(format!("error:     {:#}", err)),
+    }
+    print(json, &explanation, &lines.join("\n"))
+}
+
+#[cfg(target_os = "linux")]
+fn trace(input: &str, kind: Kind, options: &IconOptions) -> Trace {
+    use systemicons::{IconRequest, MimeReason, MimeStep};
+
+    let request = match kind {
+        Kind::Path => IconRequest::path(input, options.size),
+        Kind::Mime => IconRequest::mime_type(input, options.size),
+        _ => IconRequest::extension(&query(input, kind), options.size),
+    }
+    .with_scale(options.scale);
+    let explanation = systemicons::explain(&request);
+    let reason = match explanation.reason {
+        MimeReason::Given => "given".to_string(),
+        MimeReason::Directory => "directory".to_string(),
+        MimeReason::Glob { pattern, weight } => format!("glob {} (weight {})", pattern, weight),
+        MimeReason::Magic { priority } => format!("magic (priority {})", priority),
+        MimeReason::EmptyFile => "empty file".to_string(),
+        MimeReason::Text => "text content".to_string(),
+        MimeReason::Fallback => "fallback".to_string(),
+    };
+    let steps = explanation
+        .steps
+        .iter()
+        .map(|step| match step {
+            MimeStep::Alias { alias, canonical } => format!("{} is an alias of {}", alias, canonical),
+            MimeStep::Subclass { child, parent } => format!("{} is a subclass of {}", child, parent),
+        })
+        .collect();
+    let visits = explanation
+        .visits
+        .into_iter()
+        .map(|visit| Visit {
+            theme: visit.theme,
+            index: visit.index,
+            directories: visit.directories,
+            candidates: visit
+                .candidates
+                .into_iter()
+                .map(|candidate| Candidate {
+                    icon_name: candidate.icon_name,
+                    directory: candidate.directory,
+                    file: candidate.file,
+                    matches_size: candidate.matches_size,
+                    size_distance: candidate.size_distance,
+                })
+                .collect(),
+        })
+        .collect();
+    Trace {
+        mime_type: explanation.mime_type,
+        reason,
+        steps,
+        icon_names: explanation.icon_names,
+        theme: explanation.theme,
+        visits,
+        unthemed: explanation.unthemed,
+        icon: explanation.icon,
+        failure: explanation.failure,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn trace_lines(trace: &Trace) -> Vec<String> {
+    let mut lines = vec![format!("detected:  {}", trace.reason)];
+    lines.extend(trace.steps.iter().map(|step| format!("           {}", step)));
+    lines.push(format!("names:     {}", trace.icon_names.join(", ")));
+    for visit in &trace.visits {
+        let index = visit.index.as_ref().map(|index| index.to_string_lossy().to_string()).unwrap_or_else(|| "not installed".to_string());
+        lines.push(format!("theme:     {} ({}, {} directories)", visit.theme, index, visit.directories));
+        for candidate in &visit.candidates {
+            let matches = if candidate.matches_size { "matches" } else { "distance" };
+            lines.push(format!("           {} {} {}  {}", matches, candidate.size_distance, candidate.directory, candidate.file.to_string_lossy()));
+        }
+    }
+    lines.extend(trace.unthemed.iter().map(|file| format!("unthemed:  {}", file.to_string_lossy())));
+    match (&trace.icon, &trace.failure) {
+        (Some(icon), _) => lines.push(format!("pick:      {}", icon.to_string_lossy())),
+        (None, Some(failure)) => lines.push(format!("failure:   {}", failure)),
+        (None, None) => {}
+    }
+    lines
+}
+
+fn export(json: bool, inputs: &[String], all_mime_types: bool, options: &ExportOptions, output: &Path, kind: Kind) -> Result<(), Error> {
+    let mut export_inputs = inputs
+        .iter()
+        .map(|input| match kind {
+            Kind::Auto if is_mime_type(input) => Ok(ExportInput::MimeType(input.clone())),
+            Kind::Auto | Kind::Ext => Ok(ExportInput::Extension(query(input, Kind::Ext))),
+            Kind::Mime => Ok(ExportInput::MimeType(input.clone())),
+            Kind::Path => Err(Error::InvalidInput { input: input.clone(), reason: "Paths can not be exported".to_string() }),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    if all_mime_types {
+        export_inputs.push(ExportInput::AllMimeTypes);
+    }
+    if export_inputs.is_empty() {
+        return Err(Error::InvalidInput { input: String::new(), reason: "Nothing to export".to_string() });
+    }
+    let is_zip = output.extension().map(|ext| ext.eq_ignore_ascii_case("zip")).unwrap_or(false);
+    let target = if is_zip { ExportTarget::Zip(output.to_path_buf()) } else { ExportTarget::Directory(output.to_path_buf()) };
+    let manifest = systemicons::export::export(&export_inputs, options, &target)?;
+    let text = format!(
+        "{} extensions and {} MIME types exported to {}",
+        manifest.extensions.len(),
+        manifest.mime_types.len(),
+        output.to_string_lossy()
+    );
+    print(json, &manifest, &text)
+}
+
+/// Existing paths are paths, `type/subtype` is a MIME type, everything else an extension
+fn classify_input(input: &str, kind: Kind) -> Kind {
+    if kind != Kind::Auto {
+        kind
+    } else if Path::new(input).exists() {
+        Kind::Path
+    } else if is_mime_type(input) {
+        Kind::Mime
+    } else {
+        Kind::Ext
+    }
+}
+
+fn is_mime_type(input: &str) -> bool {
+    let valid = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "-+._".contains(c));
+    matches!(input.split_once('/'), Some((media_type, subtype)) if valid(media_type) && valid(subtype) && !media_type.starts_with('.'))
+}
+
+/// Extensions are looked up as file names, so `txt` becomes `.txt`
+fn query(input: &str, kind: Kind) -> String {
+    if kind == Kind::Ext && !input.contains('.') {
+        format!(".{}", input)
+    } else {
+        input.to_string()
+    }
+}
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Auto => "auto",
+        Kind::Ext => "extension",
+        Kind::Path => "path",
+        Kind::Mime => "mime type",
+    }
+}
+
+fn get_icon(input: &str, kind: Kind, size: i32) -> Result<Vec<u8>, Error> {
+    match kind {
+        Kind::Path => systemicons::get_icon_for_path(input, size),
+        Kind::Mime => get_icon_for_mime_type(input, size),
+        _ => systemicons::get_icon(&query(input, kind), size),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn get_icon_as_file(input: &str, kind: Kind, size: i32) -> Result<String, Error> {
+    match kind {
+        Kind::Path => systemicons::get_icon_for_path_as_file(input, size),
+        Kind::Mime => get_icon_for_mime_type_as_file(input, size),
+        _ => systemicons::get_icon_as_file(&query(input, kind), size),
+    }
+}
+#[cfg(target_os = "windows")]
+fn get_icon_as_file(_input: &str, _kind: Kind, _size: i32) -> Result<String, Error> {
+    Err(unsupported("Icon files"))
+}
+
+#[cfg(target_os = "linux")]
+fn get_icon_for_mime_type(mime_type: &str, size: i32) -> Result<Vec<u8>, Error> {
+    systemicons::get_icon_for_mime_type(mime_type, size)
+}
+#[cfg(not(target_os = "linux"))]
+fn get_icon_for_mime_type(_mime_type: &str, _size: i32) -> Result<Vec<u8>, Error> {
+    Err(unsupported("MIME type icons"))
+}
+
+#[cfg(target_os = "linux")]
+fn get_icon_for_mime_type_as_file(mime_type: &str, size: i32) -> Result<String, Error> {
+    systemicons::get_icon_for_mime_type_as_file(mime_type, size)
+}
+#[cfg(target_os = "macos")]
+fn get_icon_for_mime_type_as_file(_mime_type: &str, _size: i32) -> Result<String, Error> {
+    Err(unsupported("MIME type icons"))
+}
+
+#[cfg(target_os = "linux")]
+fn get_mime_type(path: &str) -> Result<String, Error> {
+    systemicons::get_mime_type(path)
+}
+#[cfg(not(target_os = "linux"))]
+fn get_mime_type(_path: &str) -> Result<String, Error> {
+    Err(unsupported("MIME types"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported(what: &str) -> Error {
+    Error::InvalidInput { input: what.to_string(), reason: "Not supported on this platform".to_string() }
+}
+
+fn print<T: Serialize>(json: bool, value: &T, text: &str) -> Result<(), Error> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value).map_err(|err| Error::Encode { input: "json".to_string(), size: None, source: Box::new(err) })?);
+    } else if !text.is_empty() {
+        println!("{}", text);
+    }
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::{error::Error as StdError, fmt, io, str::Utf8Error};
+
+use image::ImageError;
+
+pub(crate) type Source = Box<dyn StdError + Send + Sync>;
+
+/// Possible Error. The input is the file extension, path, MIME type or whatever has been requested, it is empty
+/// when a function of a submodule like [`crate::ico`] fails which does not know it.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// There is no icon for the input
+    NotFound { input: String, size: Option<i32>, reason: String },
+    /// The input is not valid, like a path with a NUL character or a file which is not what it claims to be
+    InvalidInput { input: String, reason: String },
+    /// The backend of the platform can not be used, like GTK without display or a failing function of Windows
+    BackendUnavailable { backend: &'static str, reason: String, source: Option<Box<dyn StdError + Send + Sync>> },
+    /// An icon or a string delivered by the system could not be decoded
+    Decode { input: String, reason: String, source: Option<Box<dyn StdError + Send + Sync>> },
+    /// An icon or a manifest could not be encoded
+    Encode { input: String, size: Option<i32>, source: Box<dyn StdError + Send + Sync> },
+    /// A file could not be read or written
+    Io { input: String, source: io::Error },
+}
+
+impl Error {
+    pub(crate) fn not_found(input: &str, size: Option<i32>, reason: impl Into<String>) -> Error {
+        Error::NotFound { input: input.to_string(), size, reason: reason.into() }
+    }
+
+    pub(crate) fn invalid_input(input: &str, reason: impl Into<String>) -> Error {
+        Error::InvalidInput { input: input.to_string(), reason: reason.into() }
+    }
+
+    #[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+    pub(crate) fn backend_unavailable(backend: &'static str, reason: impl Into<String>) -> Error {
+        Error::BackendUnavailable { backend, reason: reason.into(), source: None }
+    }
+
+    pub(crate) fn decode(input: &str, reason: impl Into<String>, source: Option<Source>) -> Error {
+        Error::Decode { input: input.to_string(), reason: reason.into(), source }
+    }
+
+    pub(crate) fn encode(input: &str, size: Option<i32>, source: impl Into<Source>) -> Error {
+        Error::Encode { input: input.to_string(), size, source: source.into() }
+    }
+
+    /// The requested input, empty if it is not known
+    pub fn input(&self) -> &str {
+        match self {
+            Error::NotFound { input, .. } | Error::InvalidInput { input, .. } | Error::Decode { input, .. } | Error::Encode { input, .. } | Error::Io { input, .. } => input,
+            Error::BackendUnavailable { .. } => "",
+        }
+    }
+
+    /// Adds the input and the size of a request, where the error does not know them yet
+    pub(crate) fn context(mut self, request: &str, request_size: Option<i32>) -> Error {
+        match &mut self {
+            Error::NotFound { input, size, .. } | Error::Encode { input, size, .. } => {
+                if input.is_empty() {
+                    *input = request.to_string();
+                }
+                if size.is_none() {
+                    *size = request_size;
+                }
+            }
+            Error::InvalidInput { input, .. } | Error::Decode { input, .. } | Error::Io { input, .. } => {
+                if input.is_empty() {
+                    *input = request.to_string();
+                }
+            }
+            Error::BackendUnavailable { .. } => (),
+        }
+        self
+    }
+
+    /// Errors are not `Clone`, the sources besides I/O errors are kept as messages
+    pub(crate) fn copy(&self) -> Error {
+        let copy_source = |source: &Source| -> Source { Box::new(io::Error::other(source.to_string())) };
+        match self {
+            Error::NotFound { input, size, reason } => Error::NotFound { input: input.clone(), size: *size, reason: reason.clone() },
+            Error::InvalidInput { input, reason } => Error::InvalidInput { input: input.clone(), reason: reason.clone() },
+            Error::BackendUnavailable { backend, reason, source } => {
+                Error::BackendUnavailable { backend, reason: reason.clone(), source: source.as_ref().map(copy_source) }
+            }
+            Error::Decode { input, reason, source } => Error::Decode { input: input.clone(), reason: reason.clone(), source: source.as_ref().map(copy_source) },
+            Error::Encode { input, size, source } => Error::Encode { input: input.clone(), size: *size, source: copy_source(source) },
+            Error::Io { input, source } => Error::Io { input: input.clone(), source: io::Error::new(source.kind(), source.to_string()) },
+        }
+    }
+}
+
+/// The alternate format `{:#}` appends the sources of the error, like `I/O error (icon.png): Permission denied`
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound { reason, .. } => write!(f, "{}", if reason.is_empty() { "No icon found" } else { reason })?,
+            Error::InvalidInput { reason, .. } => write!(f, "Invalid input: {}", reason)?,
+            Error::BackendUnavailable { backend, reason, .. } => write!(f, "{} is not available: {}", backend, reason)?,
+            Error::Decode { reason, .. } => write!(f, "Decoding failed: {}", reason)?,
+            Error::Encode { .. } => write!(f, "Encoding failed")?,
+            Error::Io { .. } => write!(f, "I/O error")?,
+        }
+        let size = match self {
+            Error::NotFound { size, .. } | Error::Encode { size, .. } => *size,
+            _ => None,
+        };
+        match (self.input(), size) {
+            ("", None) => (),
+            ("", Some(size)) => write!(f, " ({} pixels)", size)?,
+            (input, None) => write!(f, " ({})", input)?,
+            (input, Some(size)) => write!(f, " ({}, {} pixels)", input, size)?,
+        }
+        if f.alternate() {
+            let mut source = self.source();
+            while let Some(error) = source {
+                write!(f, ": {}", error)?;
+                source = error.source();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::BackendUnavailable { source, .. } | Error::Decode { source, .. } => source.as_deref().map(|source| source as &(dyn StdError + 'static)),
+            Error::Encode { source, .. } => Some(source.as_ref()),
+            Error::Io { source, .. } => Some(source),
+            Error::NotFound { .. } | Error::InvalidInput { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io { input: String::new(), source: error }
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(error: Utf8Error) -> Self {
+        Error::decode("", "The string is not UTF-8", Some(Box::new(error)))
+    }
+}
+
+/// Image errors of reading, writing is mapped with [`Error::encode`]
+impl From<ImageError> for Error {
+    fn from(error: ImageError) -> Self {
+        match error {
+            ImageError::IoError(error) => error.into(),
+            error => Error::decode("", "The image could not be decoded", Some(Box::new(error))),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Exports icon packs for clients which do not have the icon theme of the server, like web clients.
+//!
+//! The icons are written content addressed as `icons/<hash>.png` or `icons/<hash>.svg` into a directory or a zip
+//! archive, so identical icons are stored once and may be cached forever. `manifest.json` maps the extensions to
+//! their MIME types and the MIME types to their icon files:
+//!
+//! ```json
+//! {
+//!   "sizes": [16, 32],
+//!   "scales": [1, 2],
+//!   "extensions": { ".pdf": { "mime_type": "application/pdf" } },
+//!   "mime_types": { "application/pdf": { "16": "icons/3f0c….png", "16@2x": "icons/9a41….png", "svg": "icons/51d2….svg" } }
+//! }
+//! ```
+//!
+//! Where MIME types are not known, on Windows and macOS, the extensions map to their icon files directly.
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashSet},
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use image::{ImageFormat, RgbaImage};
+use serde::Serialize;
+
+use crate::{atlas, hash::fnv1a, zip::ZipWriter, Error};
+
+const MANIFEST: &str = "manifest.json";
+const ICONS_DIR: &str = "icons";
+
+/// What is exported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportInput {
+    /// A file extension like `.pdf`
+    Extension(String),
+    MimeType(String),
+    /// All MIME types of the shared MIME-info database
+    AllMimeTypes,
+}
+
+/// Where the icon pack is written to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportTarget {
+    Directory(PathBuf),
+    Zip(PathBuf),
+}
+
+/// The sizes in logical pixels and the scale factors of the exported icons
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub sizes: Vec<i32>,
+    pub scales: Vec<i32>,
+    /// Exports the scalable icons, if the icon theme has them
+    pub svg: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions { sizes: vec![16, 32, 48], scales: vec![1, 2], svg: true }
+    }
+}
+
+/// The icon files of an extension or a MIME type by variant: `16` for 16 pixels, `16@2x` for 32 pixels at scale 2
+/// and `svg` for the scalable icon
+pub type IconFiles = BTreeMap<String, String>;
+
+/// The content of `manifest.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExportManifest {
+    pub sizes: Vec<i32>,
+    pub scales: Vec<i32>,
+    pub extensions: BTreeMap<String, ExtensionEntry>,
+    pub mime_types: BTreeMap<String, IconFiles>,
+}
+
+/// An extension refers to its MIME type, or has its own icons if the MIME type is not known
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExtensionEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub icons: IconFiles,
+}
+
+/// What an icon is looked up for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconKey<'a> {
+    Extension(&'a str),
+    MimeType(&'a str),
+}
+
+impl IconKey<'_> {
+    /// The extension or the MIME type
+    pub fn as_str(&self) -> &str {
+        match self {
+            IconKey::Extension(input) | IconKey::MimeType(input) => input,
+        }
+    }
+}
+
+/// Where the icons come from
+pub trait IconSource {
+    /// The MIME type of a file extension, `None` if it is unknown or the platform has no MIME types
+    fn mime_type(&self, ext: &str) -> Option<String>;
+    /// All known MIME types
+    fn mime_types(&self) -> Vec<String>;
+    /// The icon formatted as png, it may have another size than requested
+    fn png(&self, key: IconKey, size: i32) -> Result<Vec<u8>, Error>;
+    /// The scalable icon, fails with [`Error::NotFound`] if there is none
+    fn svg(&self, key: IconKey, size: i32) -> Result<Vec<u8>, Error>;
+}
+
+/// The icons of the system
+pub struct SystemIcons {
+    #[cfg(target_os = "linux")]
+    database: crate::linux::shared_mime::MimeDatabase,
+}
+
+impl SystemIcons {
+    pub fn new() -> SystemIcons {
+        SystemIcons {
+            #[cfg(target_os = "linux")]
+            database: crate::linux::shared_mime::MimeDatabase::load(
+                &std::iter::once(crate::linux::xdg::get_data_home()).chain(crate::linux::xdg::get_data_dirs()).collect::<Vec<_>>(),
+            ),
+        }
+    }
+}
+
+impl Default for SystemIcons {
+    fn default() -> Self {
+        SystemIcons::new()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl IconSource for SystemIcons {
+    fn mime_type(&self, ext: &str) -> Option<String> {
+        let (mime_type, _) = self.database.detect_name(ext)?;
+        Some(self.database.unalias(&mime_type).map(|canonical| canonical.to_string()).unwrap_or(mime_type))
+    }
+
+    fn mime_types(&self) -> Vec<String> {
+        self.database.mime_types().to_vec()
+    }
+
+    fn png(&self, key: IconKey, size: i32) -> Result<Vec<u8>, Error> {
+        match key {
+            IconKey::Extension(ext) => crate::get_icon(ext, size),
+            IconKey::MimeType(mime_type) => crate::get_icon_for_mime_type(mime_type, size),
+        }
+    }
+
+    fn svg(&self, key: IconKey, size: i32) -> Result<Vec<u8>, Error> {
+        let file = match key {
+            IconKey::Extension(ext) => crate::get_svg_icon_as_file(ext, size)?,
+            IconKey::MimeType(mime_type) => crate::get_svg_icon_for_mime_type_as_file(mime_type, size)?,
+        };
+        Ok(fs::read(file)?)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl IconSource for SystemIcons {
+    fn mime_type(&self, _ext: &str) -> Option<String> {
+        None
+    }
+
+    fn mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn png(&self, key: IconKey, size: i32) -> Result<Vec<u8>, Error> {
+        match key {
+            IconKey::Extension(ext) => crate::get_icon(ext, size),
+            IconKey::MimeType(mime_type) => Err(Error::invalid_input(mime_type, "No icons for MIME types on this platform")),
+        }
+    }
+
+    fn svg(&self, key: IconKey, size: i32) -> Result<Vec<u8>, Error> {
+        Err(Error::not_found(key.as_str(), Some(size), "No scalable icons on this platform"))
+    }
+}
+
+/// Exports the icons of the system
+pub fn export(inputs: &[ExportInput], options: &ExportOptions, target: &ExportTarget) -> Result<ExportManifest, Error> {
+    export_with(&SystemIcons::new(), inputs, options, target)
+}
+
+/// Exports the icons of `source`. Variants which are not found are left out, other errors abort the export
+pub fn export_with<S: IconSource>(source: &S, inputs: &[ExportInput], options: &ExportOptions, target: &ExportTarget) -> Result<ExportManifest, Error> {
+    if options.sizes.iter().chain(&options.scales).any(|&value| value < 1) {
+        return Err(Error::invalid_input("", "Sizes and scales have to be positive"));
+    }
+    if options.sizes.iter().any(|size| options.scales.iter().any(|&scale| size.checked_mul(scale).is_none())) {
+        return Err(Error::invalid_input("", "A size with a scale is too large"));
+    }
+    let mut writer = match target {
+        ExportTarget::Directory(dir) => {
+            fs::create_dir_all(dir.join(ICONS_DIR))?;
+            Writer::Directory(dir.clone())
+        }
+        ExportTarget::Zip(path) => Writer::Zip(ZipWriter::default(), path.clone()),
+    };
+    let mut manifest = ExportManifest { sizes: options.sizes.clone(), scales: options.scales.clone(), ..Default::default() };
+    let mut written = HashSet::new();
+    let mut export_icons = |key: IconKey, writer: &mut Writer| -> Result<IconFiles, Error> {
+        let mut files = IconFiles::new();
+        for &size in &options.sizes {
+            for &scale in &options.scales {
+                let pixels = size * scale;
+                let Some(png) = found(source.png(key, pixels).map_err(|err| err.context(key.as_str(), Some(pixels))))? else {
+                    continue;
+                };
+                let png = resize(png, pixels).map_err(|err| err.context(key.as_str(), Some(pixels)))?;
+                let variant = if scale == 1 { size.to_string() } else { format!("{}@{}x", size, scale) };
+                files.insert(variant, writer.write_once(&mut written, &png, "png")?);
+            }
+        }
+        if options.svg {
+            let size = options.sizes.iter().max().copied().unwrap_or(48);
+            if let Some(svg) = found(source.svg(key, size).map_err(|err| err.context(key.as_str(), Some(size))))? {
+                files.insert("svg".to_string(), writer.write_once(&mut written, &svg, "svg")?);
+            }
+        }
+        Ok(files)
+    };
+
+    let mut mime_types: Vec<String> = Vec::new();
+    for input in inputs {
+        match input {
+            ExportInput::Extension(ext) => {
+                let ext = if ext.starts_with('.') { ext.to_lowercase() } else { format!(".{}", ext.to_lowercase()) };
+                let entry = match source.mime_type(&ext) {
+                    Some(mime_type) => {
+                        mime_types.push(mime_type.clone());
+                        ExtensionEntry { mime_type: Some(mime_type), icons: IconFiles::new() }
+                    }
+                    None => ExtensionEntry { mime_type: None, icons: export_icons(IconKey::Extension(&ext), &mut writer)? },
+                };
+                manifest.extensions.insert(ext, entry);
+            }
+            ExportInput::MimeType(mime_type) => mime_types.push(mime_type.clone()),
+            ExportInput::AllMimeTypes => mime_types.extend(source.mime_types()),
+        }
+    }
+    for mime_type in mime_types {
+        if let Entry::Vacant(entry) = manifest.mime_types.entry(mime_type) {
+            let files = export_icons(IconKey::MimeType(entry.key()), &mut writer)?;
+            entry.insert(files);
+        }
+    }
+
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|err| Error::encode(MANIFEST, None, err))?;
+    match writer {
+        Writer::Directory(dir) => fs::write(dir.join(MANIFEST), json)?,
+        Writer::Zip(mut zip, path) => {
+            zip.add(MANIFEST, &json)?;
+            zip.write_to(&path)?;
+        }
+    }
+    Ok(manifest)
+}
+
+enum Writer {
+    Directory(PathBuf),
+    Zip(ZipWriter, PathBuf),
+}
+
+impl Writer {
+    /// Writes the file named after its content, if it has not been written yet, and returns its path in the pack
+    fn write_once(&mut self, written: &mut HashSet<String>, data: &[u8], extension: &str) -> Result<String, Error> {
+        let name = format!("{}/{:016x}.{}", ICONS_DIR, fnv1a(data), extension);
+        if written.insert(name.clone()) {
+            match self {
+                Writer::Directory(dir) => fs::write(dir.join(Path::new(&name)), data)?,
+                Writer::Zip(zip, _) => zip.add(&name, data)?,
+            }
+        }
+        Ok(name)
+    }
+}
+
+/// [`Error::NotFound`] becomes `None`
+fn found(result: Result<Vec<u8>, Error>) -> Result<Option<Vec<u8>>, Error> {
+    match result {
+        Ok(data) => Ok(Some(data)),
+        Err(Error::NotFound { .. }) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Icon themes may lack a size, then the nearest size is scaled
+fn resize(png: Vec<u8>, pixels: i32) -> Result<Vec<u8>, Error> {
+    let image: RgbaImage = image::load_from_memory_with_format(&png, ImageFormat::Png)?.to_rgba8();
+    if image.width().max(image.height()) == pixels as u32 {
+        return Ok(png);
+    }
+    let mut resized = Cursor::new(Vec::new());
+    atlas::fit(image, pixels).write_to(&mut resized, ImageFormat::Png).map_err(|err| Error::encode("", Some(pixels), err))?;
+    Ok(resized.into_inner())
+}
@@ -0,0 +1,50 @@
+//! Entry points for the fuzz targets in `fuzz`, which reach the parsers of private modules.
+//! They are no stable API.
+#[cfg(target_os = "linux")]
+use std::{fs::File, io::Write};
+
+#[cfg(target_os = "linux")]
+use crate::linux::{
+    appimage,
+    gvfs_metadata,
+    iso9660::Iso9660,
+    mountinfo,
+    squashfs::SquashFs,
+};
+
+/// Parses a property list in the XML or in the binary format
+pub fn plist(data: &[u8]) -> bool {
+    crate::plist::parse(data).is_ok()
+}
+
+/// Reads the icon of a SquashFS image, like the one of a type 2 AppImage
+#[cfg(target_os = "linux")]
+pub fn squashfs(data: &[u8]) -> bool {
+    image_file(data, "squashfs").and_then(|file| appimage::find_icon(&SquashFs::open(file, 0)?, 32)).is_ok()
+}
+
+/// Reads the icon of an ISO 9660 image, like the one of a type 1 AppImage
+#[cfg(target_os = "linux")]
+pub fn iso9660(data: &[u8]) -> bool {
+    image_file(data, "iso9660").and_then(|file| appimage::find_icon(&Iso9660::open(file)?, 32)).is_ok()
+}
+
+/// Looks up the metadata attribute `key` of a path in a GVfs metadata tree, which is overridden by the journal
+#[cfg(target_os = "linux")]
+pub fn gvfs_metadata(tree: &[u8], journal: &[u8], path: &str, key: &str) -> Option<String> {
+    gvfs_metadata::parse(tree.to_vec(), journal, path, key)
+}
+
+/// Parses the mounts of `/proc/self/mountinfo` and returns their number
+#[cfg(target_os = "linux")]
+pub fn mountinfo(text: &str) -> usize {
+    mountinfo::parse(text).len()
+}
+
+/// The images are read with positioned reads, so the data is written to a temporary file per process and image type
+#[cfg(target_os = "linux")]
+fn image_file(data: &[u8], kind: &str) -> std::io::Result<File> {
+    let path = std::env::temp_dir().join(format!("systemicons-fuzz-{}-{}", std::process::id(), kind));
+    File::create(&path)?.write_all(data)?;
+    File::open(path)
+}
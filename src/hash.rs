@@ -0,0 +1,8 @@
+//! 64 bit FNV-1a, unlike `DefaultHasher` its values are stable across Rust versions, so they can name files
+//! and entity tags
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub fn fnv1a<'a, I: IntoIterator<Item = &'a u8>>(data: I) -> u64 {
+    data.into_iter().fold(FNV_OFFSET_BASIS, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
@@ -0,0 +1,297 @@
+//! A handler serving icons over HTTP, built on the types of the `http` crate, so that any server like warp,
+//! axum, hyper or the custom protocols of Tauri can mount it.
+//!
+//! Icons are requested like `/icon?ext=.pdf&size=16&scale=2&format=png`. `size` defaults to 32, `scale` to 1.
+//! Without `format` the format is negotiated from the `Accept` header: scalable icons are served as SVG, if the
+//! icon theme has them, otherwise WebP and PNG are served. Responses carry a strong `ETag` and may be cached
+//! forever, `If-None-Match` is answered with `304 Not Modified`.
+use std::io::Cursor;
+
+use ::http::{
+    header::{ACCEPT, ALLOW, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH, VARY},
+    HeaderMap, HeaderValue, Method, Request, Response, StatusCode,
+};
+use image::ImageFormat;
+
+use crate::{hash::fnv1a, Error};
+
+const DEFAULT_SIZE: i32 = 32;
+const MAX_SIZE: i32 = 1024;
+const MAX_SCALE: i32 = 4;
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// The formats icons are served in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Svg,
+    WebP,
+    Png,
+}
+
+impl Format {
+    /// The server's preference, if the client accepts several formats equally
+    const PREFERENCE: [Format; 3] = [Format::Svg, Format::WebP, Format::Png];
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Svg => "image/svg+xml",
+            Format::WebP => "image/webp",
+            Format::Png => "image/png",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Format> {
+        match value.to_ascii_lowercase().as_str() {
+            "svg" => Some(Format::Svg),
+            "webp" => Some(Format::WebP),
+            "png" => Some(Format::Png),
+            _ => None,
+        }
+    }
+}
+
+/// The parameters of an icon request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconParams {
+    /// The file extension like `.pdf`
+    pub ext: String,
+    pub size: i32,
+    pub scale: i32,
+    /// The requested format, `None` negotiates the format from the `Accept` header
+    pub format: Option<Format>,
+}
+
+impl IconParams {
+    /// Parses a query string like `ext=.pdf&size=16&scale=2&format=png`, unknown parameters are ignored
+    pub fn parse(query: &str) -> Result<IconParams, Error> {
+        let mut params = IconParams { ext: String::new(), size: DEFAULT_SIZE, scale: 1, format: None };
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value)?;
+            let number = |max: i32| match value.parse::<i32>() {
+                Ok(number) if (1..=max).contains(&number) => Ok(number),
+                _ => Err(invalid(&value, format!("{} must be a number from 1 to {}", key, max))),
+            };
+            match key {
+                "ext" => params.ext = value.clone(),
+                "size" => params.size = number(MAX_SIZE)?,
+                "scale" => params.scale = number(MAX_SCALE)?,
+                "format" => params.format = Some(Format::parse(&value).ok_or_else(|| invalid(&value, "Unknown format".to_string()))?),
+                _ => {}
+            }
+        }
+        if params.ext.is_empty() {
+            return Err(invalid(query, "ext is missing".to_string()));
+        }
+        if !params.ext.starts_with('.') {
+            params.ext.insert(0, '.');
+        }
+        Ok(params)
+    }
+
+    /// The size of the icon in physical pixels
+    pub fn pixels(&self) -> i32 {
+        self.size * self.scale
+    }
+}
+
+/// Serves the icon of the request with the icons of the system
+pub fn handle<B>(request: &Request<B>) -> Response<Vec<u8>> {
+    handle_with(request, load_icon)
+}
+
+/// Serves the icon of the request, `load` returns the icon of an extension at a size in pixels either as SVG or
+/// as PNG. WebP icons are converted from PNG
+pub fn handle_with<B, F>(request: &Request<B>, load: F) -> Response<Vec<u8>>
+where
+    F: Fn(&str, i32, Format) -> Result<Vec<u8>, Error>,
+{
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        let mut response = error_response(StatusCode::METHOD_NOT_ALLOWED, "Only GET and HEAD are allowed");
+        response.headers_mut().insert(ALLOW, HeaderValue::from_static("GET, HEAD"));
+        return response;
+    }
+    let params = match IconParams::parse(request.uri().query().unwrap_or_default()) {
+        Ok(params) => params,
+        Err(err) => return error_response(status(&err), &format!("{:#}", err)),
+    };
+    let formats = match params.format {
+        Some(format) => vec![format],
+        None => negotiate(request.headers()),
+    };
+    if formats.is_empty() {
+        return error_response(StatusCode::NOT_ACCEPTABLE, "Only SVG, WebP and PNG icons are available");
+    }
+    let (format, body) = match load_first(&params, &formats, &load) {
+        Ok(icon) => icon,
+        Err(err) => return error_response(status(&err), &format!("{:#}", err)),
+    };
+
+    let etag = etag(format, &body);
+    let not_modified = request.headers().get_all(IF_NONE_MATCH).iter().any(|value| matches_etag(value, &etag));
+    let mut response = Response::new(Vec::new());
+    let headers = response.headers_mut();
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static(IMMUTABLE));
+    if params.format.is_none() {
+        headers.insert(VARY, HeaderValue::from_static("Accept"));
+    }
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(ETAG, etag);
+    }
+    if not_modified {
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        return response;
+    }
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(format.content_type()));
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    if request.method() == Method::GET {
+        *response.body_mut() = body;
+    }
+    response
+}
+
+/// Loads the icon in the first acceptable format, formats which are not available are skipped
+fn load_first<F>(params: &IconParams, formats: &[Format], load: &F) -> Result<(Format, Vec<u8>), Error>
+where
+    F: Fn(&str, i32, Format) -> Result<Vec<u8>, Error>,
+{
+    let mut last_error = None;
+    for &format in formats {
+        let icon = match format {
+            Format::Svg => load(&params.ext, params.pixels(), Format::Svg),
+            Format::Png => load(&params.ext, params.pixels(), Format::Png),
+            Format::WebP => load(&params.ext, params.pixels(), Format::Png).and_then(|png| to_webp(&png)),
+        };
+        match icon.map_err(|err| err.context(&params.ext, Some(params.pixels()))) {
+            Ok(icon) => return Ok((format, icon)),
+            Err(err @ Error::NotFound { .. }) => last_error = Some(err),
+            Err(err) => return Err(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| Error::not_found(&params.ext, Some(params.pixels()), "No icon found")))
+}
+
+/// Returns the acceptable formats, the best first. A format gets the quality of the most specific media range
+/// matching it. Without `Accept` header PNG is served
+pub fn negotiate(headers: &HeaderMap) -> Vec<Format> {
+    let ranges: Vec<(String, f32)> = headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(|part| part.trim());
+            let media_range = parts.next().filter(|media_range| !media_range.is_empty())?.to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q=").or_else(|| param.strip_prefix("Q=")))
+                .find_map(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media_range, quality))
+        })
+        .collect();
+    if ranges.is_empty() {
+        return vec![Format::Png];
+    }
+    let mut formats: Vec<(Format, f32, u8, usize)> = Format::PREFERENCE
+        .iter()
+        .enumerate()
+        .filter_map(|(preference, &format)| {
+            let content_type = format.content_type();
+            let (specificity, quality) = ranges
+                .iter()
+                .filter_map(|(media_range, quality)| {
+                    let specificity = if media_range == content_type {
+                        2
+                    } else if media_range == "image/*" {
+                        1
+                    } else if media_range == "*/*" {
+                        0
+                    } else {
+                        return None;
+                    };
+                    Some((specificity, *quality))
+                })
+                .max_by(|a, b| a.0.cmp(&b.0))?;
+            (quality > 0.0).then_some((format, quality, specificity, preference))
+        })
+        .collect();
+    formats.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.2.cmp(&a.2)).then(a.3.cmp(&b.3)));
+    formats.into_iter().map(|(format, ..)| format).collect()
+}
+
+/// A strong entity tag of the format and the content, hashed with 64 bit FNV-1a
+pub fn etag(format: Format, data: &[u8]) -> String {
+    format!("\"{:016x}\"", fnv1a(format.content_type().as_bytes().iter().chain(data)))
+}
+
+/// `If-None-Match` uses the weak comparison, so `W/` prefixes are ignored
+fn matches_etag(value: &HeaderValue, etag: &str) -> bool {
+    value.to_str().map(|value| value.split(',').map(|tag| tag.trim()).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)).unwrap_or(false)
+}
+
+fn to_webp(png: &[u8]) -> Result<Vec<u8>, Error> {
+    let image = image::load_from_memory_with_format(png, ImageFormat::Png)?;
+    let mut webp = Cursor::new(Vec::new());
+    image.write_to(&mut webp, ImageFormat::WebP).map_err(|err| Error::encode("", None, err))?;
+    Ok(webp.into_inner())
+}
+
+/// Maps the errors to client errors for bad input and missing icons, and to server errors otherwise
+pub fn status(error: &Error) -> StatusCode {
+    match error {
+        Error::NotFound { .. } => StatusCode::NOT_FOUND,
+        Error::InvalidInput { .. } => StatusCode::BAD_REQUEST,
+        Error::BackendUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    let mut response = Response::new(message.as_bytes().to_vec());
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+fn load_icon(ext: &str, size: i32, format: Format) -> Result<Vec<u8>, Error> {
+    match format {
+        Format::Svg => load_svg_icon(ext, size),
+        _ => crate::get_icon(ext, size),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn load_svg_icon(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
+    let filename = crate::get_svg_icon_as_file(ext, size)?;
+    Ok(std::fs::read(filename)?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn load_svg_icon(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
+    Err(Error::not_found(ext, Some(size), "No scalable icon found"))
+}
+
+fn percent_decode(value: &str) -> Result<String, Error> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let byte = value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()).ok_or_else(|| invalid(value, "Invalid escape".to_string()))?;
+                decoded.push(byte);
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| invalid(value, "The value is not UTF-8".to_string()))
+}
+
+fn invalid(input: &str, message: String) -> Error {
+    Error::invalid_input(input, message)
+}
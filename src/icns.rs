@@ -0,0 +1,300 @@
+//! Reading and writing the ICNS files of macOS.
+//!
+//! Newer icons (`icp4`–`icp6`, `ic04`–`ic14`) are stored as PNG or JPEG 2000, the `@2x` variants for Retina displays
+//! have their own types. Older icons (`is32`, `il32`, `ih32`, `it32`) are run length encoded RGB images with a separate
+//! 8 bit alpha mask (`s8mk`, `l8mk`, `h8mk`, `t8mk`). JPEG 2000 entries are listed, but can not be decoded.
+use crate::{ico::IconImage, Error};
+
+const MAGIC: &[u8; 4] = b"icns";
+const HEADER_SIZE: usize = 8;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JP2_SIGNATURE: &[u8] = b"\0\0\0\x0cjP  ";
+const J2K_SIGNATURE: &[u8] = b"\xff\x4f\xff\x51";
+
+/// The type of an icon element with its size in points, the scale factor and the type of the mask
+type IconType = (&'static [u8; 4], u32, u32, Option<&'static [u8; 4]>);
+
+const ICON_TYPES: &[IconType] = &[
+    (b"is32", 16, 1, Some(b"s8mk")),
+    (b"il32", 32, 1, Some(b"l8mk")),
+    (b"ih32", 48, 1, Some(b"h8mk")),
+    (b"it32", 128, 1, Some(b"t8mk")),
+    (b"icp4", 16, 1, None),
+    (b"icp5", 32, 1, None),
+    (b"icp6", 64, 1, None),
+    (b"ic04", 16, 1, None),
+    (b"ic05", 32, 1, None),
+    (b"ic07", 128, 1, None),
+    (b"ic08", 256, 1, None),
+    (b"ic09", 512, 1, None),
+    (b"ic11", 16, 2, None),
+    (b"ic12", 32, 2, None),
+    (b"ic13", 128, 2, None),
+    (b"ic14", 256, 2, None),
+    (b"ic10", 512, 2, None),
+];
+
+/// How the image of an element is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Jpeg2000,
+    /// Run length encoded ARGB channels (`ic04`, `ic05`)
+    Argb,
+    /// Run length encoded RGB channels with a separate mask
+    Rgb,
+}
+
+/// An available image of an icon family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IconSize {
+    pub os_type: [u8; 4],
+    /// The size in points
+    pub size: u32,
+    /// 2 for the `@2x` variants
+    pub scale: u32,
+    pub format: Format,
+}
+
+impl IconSize {
+    /// The width and height in pixels
+    pub fn pixels(&self) -> u32 {
+        self.size * self.scale
+    }
+}
+
+/// An element of an ICNS file
+#[derive(Debug, Clone)]
+pub struct IcnsElement {
+    pub os_type: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+/// The content of an ICNS file
+#[derive(Debug, Clone, Default)]
+pub struct IconFamily {
+    pub elements: Vec<IcnsElement>,
+}
+
+impl IconFamily {
+    pub fn new() -> IconFamily {
+        IconFamily::default()
+    }
+
+    pub fn read(data: &[u8]) -> Result<IconFamily, Error> {
+        if data.get(..4) != Some(MAGIC) {
+            return Err(invalid_data("No ICNS file"));
+        }
+        let len = (read_u32_be(data, 4).unwrap_or(0) as usize).min(data.len());
+        let mut elements = Vec::new();
+        let mut position = HEADER_SIZE;
+        while position + HEADER_SIZE <= len {
+            let element_len = read_u32_be(data, position + 4).unwrap_or(0) as usize;
+            if element_len < HEADER_SIZE || position + element_len > len {
+                return Err(invalid_data("Invalid ICNS element"));
+            }
+            let mut os_type = [0; 4];
+            os_type.copy_from_slice(&data[position..position + 4]);
+            elements.push(IcnsElement { os_type, data: data[position + HEADER_SIZE..position + element_len].to_vec() });
+            position += element_len;
+        }
+        Ok(IconFamily { elements })
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let len = HEADER_SIZE + self.elements.iter().map(|element| HEADER_SIZE + element.data.len()).sum::<usize>();
+        let mut data = Vec::with_capacity(len);
+        data.extend_from_slice(MAGIC);
+        data.extend((len as u32).to_be_bytes());
+        for element in &self.elements {
+            data.extend_from_slice(&element.os_type);
+            data.extend(((HEADER_SIZE + element.data.len()) as u32).to_be_bytes());
+            data.extend_from_slice(&element.data);
+        }
+        data
+    }
+
+    /// Lists the images of the icon family, masks and other elements like the table of contents are skipped
+    pub fn sizes(&self) -> Vec<IconSize> {
+        self.elements
+            .iter()
+            .filter_map(|element| {
+                let (os_type, size, scale, mask) = ICON_TYPES.iter().find(|(os_type, ..)| **os_type == element.os_type)?;
+                let format = if mask.is_some() {
+                    Format::Rgb
+                } else if element.data.starts_with(PNG_SIGNATURE) {
+                    Format::Png
+                } else if element.data.starts_with(JP2_SIGNATURE) || element.data.starts_with(J2K_SIGNATURE) {
+                    Format::Jpeg2000
+                } else if element.data.starts_with(b"ARGB") {
+                    Format::Argb
+                } else {
+                    return None;
+                };
+                Some(IconSize { os_type: **os_type, size: *size, scale: *scale, format })
+            })
+            .collect()
+    }
+
+    /// Decodes the image of an element, RGB images are combined with their mask
+    pub fn decode(&self, os_type: &[u8; 4]) -> Result<IconImage, Error> {
+        let icon_size = self.sizes().into_iter().find(|size| size.os_type == *os_type).ok_or_else(|| Error::not_found("", None, format!("No icon of type {}", String::from_utf8_lossy(os_type))))?;
+        let data = &self.element(os_type).map(|element| element.data.as_slice()).unwrap_or_default();
+        let pixels = icon_size.pixels();
+        let count = pixels as usize * pixels as usize;
+        match icon_size.format {
+            Format::Png => IconImage::from_png(data),
+            Format::Jpeg2000 => Err(invalid_data("JPEG 2000 icons are not supported")),
+            Format::Argb => {
+                let channels = unpack_channels(&data[4..], count, 4)?;
+                let rgba = (0..count).flat_map(|i| [channels[count + i], channels[2 * count + i], channels[3 * count + i], channels[i]]).collect();
+                IconImage::from_rgba(pixels, pixels, rgba)
+            }
+            Format::Rgb => {
+                let rgb = if data.len() == count * 4 {
+                    // uncompressed 32 bit pixels with an unused first byte
+                    (0..3).flat_map(|channel| (0..count).map(move |i| data[i * 4 + 1 + channel])).collect()
+                } else if os_type == b"it32" {
+                    unpack_channels(data.get(4..).unwrap_or_default(), count, 3)?
+                } else {
+                    unpack_channels(data, count, 3)?
+                };
+                let mask = ICON_TYPES
+                    .iter()
+                    .find(|(icon_type, ..)| *icon_type == os_type)
+                    .and_then(|(.., mask)| self.element(mask.as_ref()?))
+                    .map(|mask| mask.data.as_slice())
+                    .filter(|mask| mask.len() == count);
+                let rgba = (0..count)
+                    .flat_map(|i| [rgb[i], rgb[count + i], rgb[2 * count + i], mask.map(|mask| mask[i]).unwrap_or(255)])
+                    .collect();
+                IconImage::from_rgba(pixels, pixels, rgba)
+            }
+        }
+    }
+
+    /// Returns the image which fits `size` (in pixels) best as PNG. This is the smallest one which is at least
+    /// as large as `size`, or the largest one. PNG images are preferred and returned as they are
+    pub fn get_png(&self, size: i32) -> Result<Vec<u8>, Error> {
+        let min_size = size.max(0) as u32;
+        let sizes: Vec<_> = self.sizes().into_iter().filter(|icon_size| icon_size.format != Format::Jpeg2000).collect();
+        let rank = |icon_size: &&IconSize| (icon_size.pixels(), icon_size.format == Format::Png);
+        let icon_size = sizes
+            .iter()
+            .filter(|icon_size| icon_size.pixels() >= min_size)
+            .min_by_key(|icon_size| (icon_size.pixels(), icon_size.format != Format::Png))
+            .or_else(|| sizes.iter().max_by_key(rank))
+            .ok_or_else(|| Error::not_found("", Some(size), "No decodable icon found"))?;
+        if icon_size.format == Format::Png {
+            Ok(self.element(&icon_size.os_type).map(|element| element.data.clone()).unwrap_or_default())
+        } else {
+            self.decode(&icon_size.os_type)?.to_png()
+        }
+    }
+
+    /// Adds an image, which must have the size of the type. The types of the RGB images get a mask,
+    /// `ic04` and `ic05` are stored as ARGB, all others as PNG
+    pub fn add_image(&mut self, os_type: &[u8; 4], image: &IconImage) -> Result<(), Error> {
+        let (_, size, scale, mask) = ICON_TYPES
+            .iter()
+            .find(|(icon_type, ..)| *icon_type == os_type)
+            .ok_or_else(|| invalid_input(&format!("Unknown icon type {}", String::from_utf8_lossy(os_type))))?;
+        if image.width != size * scale || image.height != size * scale {
+            return Err(invalid_input(&format!("{} icons must have {} pixels", String::from_utf8_lossy(os_type), size * scale)));
+        }
+        let channel = |channel: usize| image.rgba.iter().skip(channel).step_by(4).copied().collect::<Vec<_>>();
+        let data = if let Some(mask) = mask {
+            let mut data = if os_type == b"it32" { vec![0; 4] } else { Vec::new() };
+            for c in 0..3 {
+                data.extend(pack_channel(&channel(c)));
+            }
+            self.set_element(**mask, channel(3));
+            data
+        } else if os_type == b"ic04" || os_type == b"ic05" {
+            let mut data = b"ARGB".to_vec();
+            for c in [3, 0, 1, 2] {
+                data.extend(pack_channel(&channel(c)));
+            }
+            data
+        } else {
+            image.to_png()?
+        };
+        self.set_element(*os_type, data);
+        Ok(())
+    }
+
+    fn element(&self, os_type: &[u8; 4]) -> Option<&IcnsElement> {
+        self.elements.iter().find(|element| element.os_type == *os_type)
+    }
+
+    fn set_element(&mut self, os_type: [u8; 4], data: Vec<u8>) {
+        self.elements.retain(|element| element.os_type != os_type);
+        self.elements.push(IcnsElement { os_type, data });
+    }
+}
+
+/// Decodes the run length encoded channels, which follow each other.
+/// A byte below 0x80 is followed by that number plus one literal bytes, otherwise the next byte is repeated
+/// the number minus 125 times
+fn unpack_channels(data: &[u8], count: usize, channels: usize) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::with_capacity(count * channels);
+    let mut position = 0;
+    while result.len() < count * channels {
+        let invalid = || invalid_data("Invalid run length encoding");
+        let n = *data.get(position).ok_or_else(invalid)? as usize;
+        if n < 0x80 {
+            result.extend_from_slice(data.get(position + 1..position + 2 + n).ok_or_else(invalid)?);
+            position += 2 + n;
+        } else {
+            let value = *data.get(position + 1).ok_or_else(invalid)?;
+            result.resize(result.len() + n - 125, value);
+            position += 2;
+        }
+    }
+    // a run must not cross the end of a channel, but is tolerated
+    result.truncate(count * channels);
+    Ok(result)
+}
+
+fn pack_channel(channel: &[u8]) -> Vec<u8> {
+    let mut packed = Vec::new();
+    let mut literals: Vec<u8> = Vec::new();
+    let mut position = 0;
+    while position < channel.len() {
+        let value = channel[position];
+        let run = channel[position..].iter().take(130).take_while(|&&byte| byte == value).count();
+        if run >= 3 {
+            flush_literals(&mut packed, &mut literals);
+            packed.push((run + 125) as u8);
+            packed.push(value);
+            position += run;
+        } else {
+            literals.push(value);
+            if literals.len() == 128 {
+                flush_literals(&mut packed, &mut literals);
+            }
+            position += 1;
+        }
+    }
+    flush_literals(&mut packed, &mut literals);
+    packed
+}
+
+fn flush_literals(packed: &mut Vec<u8>, literals: &mut Vec<u8>) {
+    if !literals.is_empty() {
+        packed.push((literals.len() - 1) as u8);
+        packed.append(literals);
+    }
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn invalid_input(message: &str) -> Error {
+    Error::invalid_input("", message)
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::decode("", message, None)
+}
@@ -0,0 +1,452 @@
+//! Reading and writing ICO and CUR files.
+//!
+//! The images of an icon are either PNG compressed or stored as device independent bitmaps (DIB)
+//! with 1, 4, 8, 16, 24 or 32 bits per pixel and an AND mask for the transparency.
+use std::{fs, io::Cursor, path::Path};
+
+use image::{ImageFormat, RgbaImage};
+
+use crate::Error;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const DIR_HEADER_SIZE: usize = 6;
+const DIR_ENTRY_SIZE: usize = 16;
+const BITMAPINFOHEADER_SIZE: usize = 40;
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+/// Larger bitmaps are rejected, they are no icons
+const MAX_SIZE: u32 = 4096;
+
+/// Reads an ICO or CUR file and returns the image which fits `size` best as PNG
+pub fn get_icon(path: &Path, size: i32) -> Result<Vec<u8>, Error> {
+    let dir = IconDir::read(&fs::read(path)?)?;
+    let entry = dir.best_fit(size).ok_or_else(|| Error::not_found(&path.to_string_lossy(), Some(size), "The file contains no image"))?;
+    entry.to_png()
+}
+
+/// ICO files contain icons, CUR files contain cursors with a hotspot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceType {
+    Icon,
+    Cursor,
+}
+
+/// A decoded image with 4 bytes RGBA per pixel, row by row beginning at the top
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// How an image is stored in an ICO file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Png,
+    /// A DIB with 1, 4, 8, 24 or 32 bits per pixel. With up to 8 bits a palette is used,
+    /// which fails if the image has too many colors
+    Bmp(u16),
+}
+
+/// An image of an ICO or CUR file
+#[derive(Debug, Clone)]
+pub struct IconEntry {
+    pub width: u32,
+    pub height: u32,
+    pub bit_count: u16,
+    /// The hotspot of a cursor
+    pub hotspot: Option<(u16, u16)>,
+    /// A PNG file or a DIB without file header
+    pub data: Vec<u8>,
+}
+
+/// The content of an ICO or CUR file
+#[derive(Debug, Clone)]
+pub struct IconDir {
+    pub resource_type: ResourceType,
+    pub entries: Vec<IconEntry>,
+}
+
+impl IconImage {
+    pub fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Result<IconImage, Error> {
+        if rgba.len() != width as usize * height as usize * 4 {
+            return Err(invalid_input("The pixel data does not match the size of the image"));
+        }
+        Ok(IconImage { width, height, rgba })
+    }
+
+    pub fn from_png(png: &[u8]) -> Result<IconImage, Error> {
+        let image = image::load_from_memory_with_format(png, ImageFormat::Png)?.to_rgba8();
+        Ok(IconImage { width: image.width(), height: image.height(), rgba: image.into_raw() })
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, Error> {
+        let image = RgbaImage::from_raw(self.width, self.height, self.rgba.clone())
+            .ok_or_else(|| invalid_input("The pixel data does not match the size of the image"))?;
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).map_err(|err| Error::encode("", None, err))?;
+        Ok(png)
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = (y as usize * self.width as usize + x as usize) * 4;
+        [self.rgba[offset], self.rgba[offset + 1], self.rgba[offset + 2], self.rgba[offset + 3]]
+    }
+}
+
+impl IconEntry {
+    /// Creates an entry from the raw data of an image, which is either a PNG file or a DIB without file header
+    /// like in the `RT_ICON` resources of PE files
+    pub fn from_data(data: Vec<u8>) -> Result<IconEntry, Error> {
+        let (width, height, bit_count) = if data.starts_with(PNG_SIGNATURE) {
+            let width = read_u32_be(&data, 16).ok_or_else(|| invalid_data("Invalid PNG header"))?;
+            let height = read_u32_be(&data, 20).ok_or_else(|| invalid_data("Invalid PNG header"))?;
+            let channels = match data.get(25) {
+                Some(2) => 3,
+                Some(4) => 2,
+                Some(6) => 4,
+                _ => 1,
+            };
+            (width, height, *data.get(24).unwrap_or(&8) as u16 * channels)
+        } else {
+            let header = DibHeader::parse(&data)?;
+            (header.width, header.height, header.bit_count)
+        };
+        Ok(IconEntry { width, height, bit_count, hotspot: None, data })
+    }
+
+    /// Encodes an image as PNG or as DIB with the given color depth
+    pub fn encode(image: &IconImage, encoding: Encoding) -> Result<IconEntry, Error> {
+        let data = match encoding {
+            Encoding::Png => image.to_png()?,
+            Encoding::Bmp(bit_count) => encode_dib(image, bit_count)?,
+        };
+        IconEntry::from_data(data)
+    }
+
+    pub fn with_hotspot(self, x: u16, y: u16) -> IconEntry {
+        IconEntry { hotspot: Some((x, y)), ..self }
+    }
+
+    pub fn is_png(&self) -> bool {
+        self.data.starts_with(PNG_SIGNATURE)
+    }
+
+    pub fn decode(&self) -> Result<IconImage, Error> {
+        if self.is_png() {
+            IconImage::from_png(&self.data)
+        } else {
+            decode_dib(&self.data)
+        }
+    }
+
+    /// Returns the image as PNG, PNG compressed entries are returned as they are
+    pub fn to_png(&self) -> Result<Vec<u8>, Error> {
+        if self.is_png() {
+            Ok(self.data.clone())
+        } else {
+            self.decode()?.to_png()
+        }
+    }
+}
+
+impl IconDir {
+    pub fn new(resource_type: ResourceType) -> IconDir {
+        IconDir { resource_type, entries: Vec::new() }
+    }
+
+    pub fn read(data: &[u8]) -> Result<IconDir, Error> {
+        let resource_type = match (read_u16(data, 0), read_u16(data, 2)) {
+            (Some(0), Some(1)) => ResourceType::Icon,
+            (Some(0), Some(2)) => ResourceType::Cursor,
+            _ => return Err(invalid_data("No ICO or CUR file")),
+        };
+        let count = read_u16(data, 4).unwrap_or(0) as usize;
+        let entries = (0..count)
+            .map(|i| {
+                let entry = DIR_HEADER_SIZE + i * DIR_ENTRY_SIZE;
+                let (Some(x), Some(y), Some(size), Some(offset)) =
+                    (read_u16(data, entry + 4), read_u16(data, entry + 6), read_u32(data, entry + 8), read_u32(data, entry + 12))
+                else {
+                    return Err(invalid_data("Invalid icon directory"));
+                };
+                let image = data
+                    .get(offset as usize..offset as usize + size as usize)
+                    .ok_or_else(|| invalid_data("Icon image exceeds the file"))?;
+                let entry = IconEntry::from_data(image.to_vec())?;
+                Ok(if resource_type == ResourceType::Cursor { entry.with_hotspot(x, y) } else { entry })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(IconDir { resource_type, entries })
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(0u16.to_le_bytes());
+        data.extend(match self.resource_type {
+            ResourceType::Icon => 1u16,
+            ResourceType::Cursor => 2u16,
+        }
+        .to_le_bytes());
+        data.extend((self.entries.len() as u16).to_le_bytes());
+        let mut offset = DIR_HEADER_SIZE + self.entries.len() * DIR_ENTRY_SIZE;
+        for entry in &self.entries {
+            // 0 means 256 or more pixels
+            data.push(if entry.width >= 256 { 0 } else { entry.width as u8 });
+            data.push(if entry.height >= 256 { 0 } else { entry.height as u8 });
+            data.push(if entry.bit_count < 8 && !entry.is_png() { 1 << entry.bit_count } else { 0 });
+            data.push(0);
+            let (planes, bit_count) = match (self.resource_type, entry.hotspot) {
+                (ResourceType::Cursor, Some(hotspot)) => hotspot,
+                (ResourceType::Cursor, None) => (0, 0),
+                (ResourceType::Icon, _) => (1, entry.bit_count),
+            };
+            data.extend(planes.to_le_bytes());
+            data.extend(bit_count.to_le_bytes());
+            data.extend((entry.data.len() as u32).to_le_bytes());
+            data.extend((offset as u32).to_le_bytes());
+            offset += entry.data.len();
+        }
+        for entry in &self.entries {
+            data.extend_from_slice(&entry.data);
+        }
+        data
+    }
+
+    /// Returns the entry which fits `size` best. This is the smallest one which is at least as large as `size`
+    /// with the highest color depth, or the largest one
+    pub fn best_fit(&self, size: i32) -> Option<&IconEntry> {
+        let size = size.max(0) as u32;
+        self.entries
+            .iter()
+            .filter(|entry| entry.width >= size)
+            .min_by_key(|entry| (entry.width, u16::MAX - entry.bit_count))
+            .or_else(|| self.entries.iter().max_by_key(|entry| (entry.width, entry.bit_count)))
+    }
+}
+
+/// The `BITMAPINFOHEADER` of a DIB in an icon, its height covers the color bitmap and the AND mask
+struct DibHeader {
+    header_size: usize,
+    width: u32,
+    height: u32,
+    top_down: bool,
+    bit_count: u16,
+    compression: u32,
+    colors_used: u32,
+}
+
+impl DibHeader {
+    fn parse(data: &[u8]) -> Result<DibHeader, Error> {
+        let invalid = || invalid_data("Invalid bitmap header");
+        let header_size = read_u32(data, 0).ok_or_else(invalid)? as usize;
+        if header_size < BITMAPINFOHEADER_SIZE {
+            return Err(invalid());
+        }
+        let width = read_u32(data, 4).ok_or_else(invalid)? as i32;
+        let height = read_u32(data, 8).ok_or_else(invalid)? as i32;
+        let header = DibHeader {
+            header_size,
+            width: width.unsigned_abs(),
+            height: height.unsigned_abs() / 2,
+            top_down: height < 0,
+            bit_count: read_u16(data, 14).ok_or_else(invalid)?,
+            compression: read_u32(data, 16).ok_or_else(invalid)?,
+            colors_used: read_u32(data, 32).ok_or_else(invalid)?,
+        };
+        if header.width == 0 || header.height == 0 || header.width > MAX_SIZE || header.height > MAX_SIZE {
+            return Err(invalid_data("Invalid bitmap size"));
+        }
+        Ok(header)
+    }
+}
+
+fn decode_dib(data: &[u8]) -> Result<IconImage, Error> {
+    let header = DibHeader::parse(data)?;
+    let (width, height) = (header.width as usize, header.height as usize);
+    let mut position = header.header_size;
+    let masks = match (header.compression, header.bit_count) {
+        (BI_RGB, 16) => [0x7c00, 0x03e0, 0x001f, 0],
+        (BI_RGB, 24) | (BI_RGB, 32) => [0xff0000, 0xff00, 0xff, 0xff000000],
+        (BI_BITFIELDS, 16) | (BI_BITFIELDS, 32) => {
+            let invalid = || invalid_data("Invalid bit fields");
+            // with a BITMAPINFOHEADER the masks follow the header, larger headers contain them
+            let masks_position = if header.header_size == BITMAPINFOHEADER_SIZE { position } else { BITMAPINFOHEADER_SIZE };
+            let masks = [
+                read_u32(data, masks_position).ok_or_else(invalid)?,
+                read_u32(data, masks_position + 4).ok_or_else(invalid)?,
+                read_u32(data, masks_position + 8).ok_or_else(invalid)?,
+                if header.bit_count == 32 { 0xff000000 } else { 0 },
+            ];
+            if header.header_size == BITMAPINFOHEADER_SIZE {
+                position += 12;
+            }
+            masks
+        }
+        (BI_RGB, 1) | (BI_RGB, 4) | (BI_RGB, 8) => [0; 4],
+        _ => return Err(invalid_data(&format!("Unsupported bitmap format {} with {} bits", header.compression, header.bit_count))),
+    };
+    let palette_size = if header.bit_count <= 8 {
+        if header.colors_used == 0 { 1 << header.bit_count } else { header.colors_used as usize }
+    } else {
+        header.colors_used as usize
+    };
+    let palette = data.get(position..position + palette_size * 4).ok_or_else(|| invalid_data("Invalid color table"))?;
+    position += palette_size * 4;
+
+    let stride = (width * header.bit_count as usize).div_ceil(32) * 4;
+    let mask_stride = width.div_ceil(32) * 4;
+    let pixels = data.get(position..position + stride * height).ok_or_else(|| invalid_data("Bitmap data is truncated"))?;
+    // some icons omit the AND mask, they are opaque
+    let mask = data.get(position + stride * height..position + stride * height + mask_stride * height);
+
+    let mut rgba = vec![0; width * height * 4];
+    for y in 0..height {
+        let row = if header.top_down { y } else { height - 1 - y };
+        let line = &pixels[row * stride..(row + 1) * stride];
+        for x in 0..width {
+            let pixel = match header.bit_count {
+                1 | 4 | 8 => {
+                    let bits = header.bit_count as usize;
+                    let index = (line[x * bits / 8] >> (8 - bits - x * bits % 8)) & ((1 << bits) - 1) as u8;
+                    let color = palette.get(index as usize * 4..index as usize * 4 + 3).unwrap_or(&[0, 0, 0]);
+                    [color[2], color[1], color[0], 255]
+                }
+                _ => {
+                    let bytes = header.bit_count as usize / 8;
+                    let value = line[x * bytes..(x + 1) * bytes].iter().rev().fold(0u32, |value, &byte| value << 8 | byte as u32);
+                    let [red, green, blue, alpha] = masks.map(|mask| scale_channel(value, mask));
+                    [red, green, blue, if masks[3] == 0 { 255 } else { alpha }]
+                }
+            };
+            rgba[(y * width + x) * 4..(y * width + x + 1) * 4].copy_from_slice(&pixel);
+        }
+    }
+
+    // a 32 bit bitmap has an alpha channel, unless all pixels are transparent, then the AND mask is used
+    let has_alpha = header.bit_count == 32 && rgba.chunks(4).any(|pixel| pixel[3] != 0);
+    if !has_alpha {
+        for y in 0..height {
+            let row = if header.top_down { y } else { height - 1 - y };
+            for x in 0..width {
+                let transparent = mask
+                    .map(|mask| mask[row * mask_stride + x / 8] & (0x80 >> (x % 8)) != 0)
+                    .unwrap_or(false);
+                let pixel = &mut rgba[(y * width + x) * 4..(y * width + x + 1) * 4];
+                // the color of masked pixels would invert the screen, which can not be represented
+                if transparent {
+                    pixel.copy_from_slice(&[0, 0, 0, 0]);
+                } else {
+                    pixel[3] = 255;
+                }
+            }
+        }
+    }
+    Ok(IconImage { width: header.width, height: header.height, rgba })
+}
+
+/// Extracts a color channel defined by a bit mask and scales it to 8 bits
+fn scale_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let channel = ((value & mask) >> mask.trailing_zeros()) as u64;
+    let max = (mask >> mask.trailing_zeros()) as u64;
+    (channel * 255 / max) as u8
+}
+
+fn encode_dib(image: &IconImage, bit_count: u16) -> Result<Vec<u8>, Error> {
+    if ![1, 4, 8, 24, 32].contains(&bit_count) {
+        return Err(invalid_input(&format!("Bitmaps with {} bits per pixel can not be written", bit_count)));
+    }
+    if image.width == 0 || image.height == 0 || image.width > MAX_SIZE || image.height > MAX_SIZE {
+        return Err(invalid_input("Invalid bitmap size"));
+    }
+    let (width, height) = (image.width as usize, image.height as usize);
+    // without alpha channel pixels are either opaque or transparent
+    let is_transparent = |pixel: [u8; 4]| if bit_count == 32 { pixel[3] == 0 } else { pixel[3] < 128 };
+
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    if bit_count <= 8 {
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let pixel = image.pixel(x, y);
+                let color = [pixel[0], pixel[1], pixel[2]];
+                if !is_transparent(pixel) && !palette.contains(&color) {
+                    palette.push(color);
+                }
+            }
+        }
+        if palette.len() > 1 << bit_count {
+            return Err(invalid_input(&format!("The image has too many colors for {} bits per pixel", bit_count)));
+        }
+        palette.resize(1 << bit_count, [0, 0, 0]);
+    }
+
+    let stride = (width * bit_count as usize).div_ceil(32) * 4;
+    let mask_stride = width.div_ceil(32) * 4;
+    let mut pixels = vec![0; stride * height];
+    let mut mask = vec![0; mask_stride * height];
+    for y in 0..height {
+        // bitmaps are stored bottom up
+        let row = height - 1 - y;
+        for x in 0..width {
+            let pixel = image.pixel(x as u32, y as u32);
+            let transparent = is_transparent(pixel);
+            if transparent {
+                mask[row * mask_stride + x / 8] |= 0x80 >> (x % 8);
+            }
+            let line = &mut pixels[row * stride..(row + 1) * stride];
+            match bit_count {
+                32 => line[x * 4..x * 4 + 4].copy_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]),
+                24 if !transparent => line[x * 3..x * 3 + 3].copy_from_slice(&[pixel[2], pixel[1], pixel[0]]),
+                24 => {}
+                _ => {
+                    let bits = bit_count as usize;
+                    let index = if transparent {
+                        0
+                    } else {
+                        palette.iter().position(|color| *color == [pixel[0], pixel[1], pixel[2]]).unwrap_or(0) as u8
+                    };
+                    line[x * bits / 8] |= index << (8 - bits - x * bits % 8);
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::with_capacity(BITMAPINFOHEADER_SIZE + palette.len() * 4 + pixels.len() + mask.len());
+    data.extend((BITMAPINFOHEADER_SIZE as u32).to_le_bytes());
+    data.extend(image.width.to_le_bytes());
+    data.extend((image.height * 2).to_le_bytes());
+    data.extend(1u16.to_le_bytes());
+    data.extend(bit_count.to_le_bytes());
+    data.extend(BI_RGB.to_le_bytes());
+    data.extend(((pixels.len() + mask.len()) as u32).to_le_bytes());
+    data.extend([0; 16]);
+    for color in &palette {
+        data.extend([color[2], color[1], color[0], 0]);
+    }
+    data.extend(pixels);
+    data.extend(mask);
+    Ok(data)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn invalid_input(message: &str) -> Error {
+    Error::invalid_input("", message)
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::decode("", message, None)
+}
@@ -0,0 +1,45 @@
+//! Validation of the inputs of the public functions. The lookups check their inputs with these functions before they
+//! reach GTK, Windows or Cocoa, so untrusted input like the query of an HTTP request fails with
+//! [`Error::InvalidInput`] instead of a panic. Servers may use them to reject requests early.
+use std::path::{Component, Path};
+
+use crate::Error;
+
+/// The largest icon size in pixels, larger icons would only be scaled up
+pub const MAX_SIZE: i32 = 4096;
+/// The maximum length of a file extension, path, MIME type or id in bytes, which is `PATH_MAX` on Linux
+pub const MAX_LENGTH: usize = 4096;
+
+/// Checks an icon size, which is between 1 and [`MAX_SIZE`]
+pub fn size(size: i32) -> Result<i32, Error> {
+    if (1..=MAX_SIZE).contains(&size) {
+        Ok(size)
+    } else {
+        Err(Error::invalid_input("", format!("The size {} is not between 1 and {}", size, MAX_SIZE)))
+    }
+}
+
+/// Checks a file extension, path, MIME type or id, which is not empty, has no NUL character and is at most
+/// [`MAX_LENGTH`] bytes long. `what` names the input in the error
+pub fn text<'a>(input: &'a str, what: &str) -> Result<&'a str, Error> {
+    let reason = if input.is_empty() {
+        format!("The {} is empty", what)
+    } else if input.contains('\0') {
+        format!("The {} contains a NUL character", what)
+    } else if input.len() > MAX_LENGTH {
+        format!("The {} is longer than {} bytes", what, MAX_LENGTH)
+    } else {
+        return Ok(input);
+    };
+    Err(Error::invalid_input(input, reason))
+}
+
+/// The last component of a path as file name, like `file.pdf` of `/tmp/file.pdf` or `.pdf` of `.pdf`.
+/// Fails for paths ending in `..`, a root or nothing
+pub fn file_name(path: &str) -> Result<&str, Error> {
+    let path = text(path, "path")?;
+    match Path::new(path).components().next_back() {
+        Some(Component::Normal(name)) => name.to_str().ok_or_else(|| Error::invalid_input(path, "The file name is not UTF-8")),
+        _ => Err(Error::invalid_input(path, "The path has no file name")),
+    }
+}
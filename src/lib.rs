@@ -5,40 +5,305 @@
 //! Windows and Linux (GTK) are supported.
 //!
 //! When you specify an absolute path to a .exe file, then the icon is loaded from resource, if the exe contains an icon resource.
-#[cfg(target_os = "windows")]
-use image::ImageError;
-#[cfg(target_os = "windows")]
-use ::windows::core::Error as WinError;
-use std::{fmt, str::Utf8Error};
-
 #[cfg(target_os = "linux")]
+// without GTK only the parts used by the freedesktop backend are reachable
+#[cfg_attr(not(feature = "backend-gtk"), allow(dead_code))]
 mod linux;
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "windows")]
 mod windows;
+mod app_bundle;
+mod error;
+pub mod atlas;
+pub mod backend;
+pub mod batch;
+#[cfg(feature = "export")]
+pub mod export;
+#[doc(hidden)]
+pub mod fuzzing;
+#[cfg(any(all(target_os = "linux", feature = "backend-gtk"), feature = "http", feature = "export"))]
+mod hash;
+mod plist;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod icns;
+pub mod ico;
+pub mod input;
+pub mod pe;
+pub mod shortcut;
+pub mod windows_root;
+#[cfg(feature = "export")]
+mod zip;
+
+pub use batch::IconBatch;
+pub use error::Error;
+#[cfg(target_os = "linux")]
+pub use linux::{
+    app_id::{AppIdMatch, AppIdRule},
+    explain::{Explanation, IconQuery, IconRequest},
+    icon_theme::{IconCandidate, IconTheme, ThemeVisit},
+    mime_apps::{AppInfo, MimeApps},
+    shared_mime::{MimeReason, MimeStep},
+    trash::TrashWatcher,
+};
 
 /// Retrieving system icon. You have to specify the file extension and desired icon size (like 16, 32 or 64).
 /// Returns the icon formatted as png as byte buffer.
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
 pub fn get_icon(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
-    linux::request::get_icon(ext, size)
+    check(ext, "file extension", size).and_then(|()| linux::request::get_icon(ext, size)).map_err(|err| err.context(ext, Some(size)))
 }
 #[cfg(target_os = "windows")]
 pub fn get_icon(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
-    windows::request::get_icon(ext, size)
+    check(ext, "file extension", size).and_then(|()| windows::request::get_icon(ext, size)).map_err(|err| err.context(ext, Some(size)))
 }
 
 /// Retrieving system icon. You have to specify the file extension and desired icon size (like 16, 32 or 64).
 /// Returns the path to the system icon.
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
 pub fn get_icon_as_file(ext: &str, size: i32) -> Result<String, Error> {
-    linux::request::get_icon_as_file(ext, size)
+    check(ext, "file extension", size).and_then(|()| linux::request::get_icon_as_file(ext, size)).map_err(|err| err.context(ext, Some(size)))
+}
+
+/// Retrieving the path of the scalable SVG icon of a file extension. Fails with [`Error::NotFound`], if the icon theme
+/// only has bitmaps.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_svg_icon_as_file(ext: &str, size: i32) -> Result<String, Error> {
+    check(ext, "file extension", size).and_then(|()| linux::request::get_svg_icon_as_file(ext, size)).map_err(|err| err.context(ext, Some(size)))
+}
+
+/// Retrieving the icon of a MIME type like `text/plain`. You have to specify the MIME type and desired icon size (like 16, 32 or 64).
+/// Returns the icon as byte buffer, which is formatted as png, unless the icon theme contains a different format.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_icon_for_mime_type(mime_type: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(mime_type, "MIME type", size).and_then(|()| linux::request::get_icon_for_mime_type(mime_type, size)).map_err(|err| err.context(mime_type, Some(size)))
+}
+
+/// Retrieving the icon of a MIME type like `text/plain`. You have to specify the MIME type and desired icon size (like 16, 32 or 64).
+/// Returns the path to the icon.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_icon_for_mime_type_as_file(mime_type: &str, size: i32) -> Result<String, Error> {
+    check(mime_type, "MIME type", size).and_then(|()| linux::request::get_icon_for_mime_type_as_file(mime_type, size)).map_err(|err| err.context(mime_type, Some(size)))
+}
+
+/// Retrieving the path of the scalable SVG icon of a MIME type. Fails with [`Error::NotFound`], if the icon theme
+/// only has bitmaps.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_svg_icon_for_mime_type_as_file(mime_type: &str, size: i32) -> Result<String, Error> {
+    check(mime_type, "MIME type", size).and_then(|()| linux::request::get_svg_icon_for_mime_type_as_file(mime_type, size)).map_err(|err| err.context(mime_type, Some(size)))
+}
+
+/// Guessing the MIME type of a file from its name and content, directories are `inode/directory`.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_mime_type(path: &str) -> Result<String, Error> {
+    input::text(path, "path").and_then(linux::request::get_mime_type).map_err(|err| err.context(path, None))
+}
+
+/// Listing all MIME types of the shared MIME-info database.
+#[cfg(target_os = "linux")]
+pub fn get_mime_types() -> Vec<String> {
+    let data_dirs: Vec<_> = std::iter::once(linux::xdg::get_data_home()).chain(linux::xdg::get_data_dirs()).collect();
+    linux::shared_mime::MimeDatabase::load(&data_dirs).mime_types().to_vec()
+}
+
+/// Listing the installed icon themes.
+#[cfg(target_os = "linux")]
+pub fn get_icon_themes() -> Vec<IconTheme> {
+    linux::icon_theme::get_icon_themes()
+}
+
+/// Retrieving the name of the icon theme which is used by GTK.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_current_icon_theme() -> Result<String, Error> {
+    linux::request::get_current_icon_theme()
+}
+
+/// Explaining how the icon of a request is found: the detected MIME type and why, the alias and subclass steps,
+/// the icon names, the themes and directories which have been searched and the icon which has been picked.
+#[cfg(target_os = "linux")]
+pub fn explain(request: &IconRequest) -> Explanation {
+    linux::explain::explain(request)
+}
+
+/// Explaining the lookup in the `mime` and `icons` directories of the given data directories, starting with `theme`.
+#[cfg(target_os = "linux")]
+pub fn explain_in(request: &IconRequest, data_dirs: &[std::path::PathBuf], theme: &str) -> Explanation {
+    linux::explain::explain_in(request, data_dirs, theme)
+}
+
+/// Retrieving the icon of a file or directory on disk. You have to specify the path and desired icon size (like 16, 32 or 64).
+/// Custom folder icons set in Dolphin (`.directory` file) or in Nautilus (GVfs metadata) are honoured,
+/// the icons of AppImages are read from the embedded file system and those of Windows executables from their resources.
+/// Windows shortcuts (`.lnk`, `.url`) show their explicit icon or the icon of their target.
+/// Returns the icon as byte buffer, which is formatted as png, unless the custom folder icon is in a different format.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_icon_for_path(path: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(path, "path", size).and_then(|()| linux::request::get_icon_for_path(path, size)).map_err(|err| err.context(path, Some(size)))
+}
+#[cfg(target_os = "windows")]
+pub fn get_icon_for_path(path: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(path, "path", size).and_then(|()| windows::request::get_icon(path, size)).map_err(|err| err.context(path, Some(size)))
+}
+
+/// Retrieving the icon of a file or directory on disk. You have to specify the path and desired icon size (like 16, 32 or 64).
+/// Custom folder icons set in Dolphin (`.directory` file) or in Nautilus (GVfs metadata) are honoured,
+/// the icons of AppImages, Windows executables and Windows shortcuts are extracted to `$XDG_CACHE_HOME/systemicons`.
+/// Returns the path to the icon.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_icon_for_path_as_file(path: &str, size: i32) -> Result<String, Error> {
+    check(path, "path", size).and_then(|()| linux::request::get_icon_for_path_as_file(path, size)).map_err(|err| err.context(path, Some(size)))
+}
+
+/// Retrieving the trash icon, which shows whether the trash is full or empty. You have to specify the desired icon size (like 16, 32 or 64).
+/// Returns the icon formatted as png as byte buffer.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_trash_icon(size: i32) -> Result<Vec<u8>, Error> {
+    input::size(size).and_then(linux::request::get_trash_icon).map_err(|err| err.context("trash", Some(size)))
+}
+
+/// Retrieving the trash icon, which shows whether the trash is full or empty. You have to specify the desired icon size (like 16, 32 or 64).
+/// Returns the path to the icon.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_trash_icon_as_file(size: i32) -> Result<String, Error> {
+    input::size(size).and_then(linux::request::get_trash_icon_as_file).map_err(|err| err.context("trash", Some(size)))
 }
 
-/// In a non GTK program you have to initialize GTK when getting system icons (Linux)-
+/// Checks whether the home trash or the trash directory of a mounted volume contains files.
 #[cfg(target_os = "linux")]
-pub fn init() {
+pub fn is_trash_full() -> bool {
+    linux::trash::is_trash_full()
+}
+
+/// Watches the trash directories with inotify. `on_change` is called from a background thread with the new state
+/// (`true` when full) whenever the trash becomes full or empty, so that the trash icon can be retrieved again.
+/// Watching stops when the returned [`TrashWatcher`] is dropped.
+#[cfg(target_os = "linux")]
+pub fn watch_trash<F>(on_change: F) -> Result<TrashWatcher, Error>
+where
+    F: Fn(bool) + Send + 'static,
+{
+    linux::trash::watch_trash(on_change)
+}
+
+/// Retrieving the icon of a volume, like a hard disk, an usb stick, an optical disc or a network share.
+/// You have to specify the mount point and desired icon size (like 16, 32 or 64).
+/// Returns the icon formatted as png as byte buffer.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_volume_icon(mount_path: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(mount_path, "mount path", size).and_then(|()| linux::request::get_volume_icon(mount_path, size)).map_err(|err| err.context(mount_path, Some(size)))
+}
+
+/// Retrieving the icon of a volume, like a hard disk, an usb stick, an optical disc or a network share.
+/// You have to specify the mount point and desired icon size (like 16, 32 or 64).
+/// Returns the path to the icon.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_volume_icon_as_file(mount_path: &str, size: i32) -> Result<String, Error> {
+    check(mount_path, "mount path", size).and_then(|()| linux::request::get_volume_icon_as_file(mount_path, size)).map_err(|err| err.context(mount_path, Some(size)))
+}
+
+/// Determines the icon names of the volume mounted at `mount_path`, the most specific one first.
+/// The file system type and device are taken from `/proc/self/mountinfo`, the drive properties from `/run/udev/data`.
+#[cfg(target_os = "linux")]
+pub fn get_volume_icon_names(mount_path: &str) -> Result<Vec<String>, Error> {
+    get_volume_icon_names_from(mount_path, linux::mountinfo::MOUNTINFO, linux::volume::UDEV_DATA)
+}
+
+/// Like [`get_volume_icon_names`], but reads the mounts from the file `mountinfo` and the udev database from the directory `udev_data`.
+#[cfg(target_os = "linux")]
+pub fn get_volume_icon_names_from(mount_path: &str, mountinfo: &str, udev_data: &str) -> Result<Vec<String>, Error> {
+    input::text(mount_path, "mount path")?;
+    linux::volume::get_volume_icon_names(std::path::Path::new(mount_path), std::path::Path::new(mountinfo), std::path::Path::new(udev_data)).map_err(|err| err.context(mount_path, None))
+}
+
+/// Retrieving the icon of an application. You have to specify the desktop ID (like `firefox.desktop` or `org.gnome.Nautilus.desktop`)
+/// and desired icon size (like 16, 32 or 64). Returns the icon as byte buffer, which is formatted as png, unless the application's
+/// desktop file points to an icon file in a different format.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_app_icon(desktop_id: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(desktop_id, "desktop id", size).and_then(|()| linux::request::get_app_icon(desktop_id, size)).map_err(|err| err.context(desktop_id, Some(size)))
+}
+
+/// Retrieving the icon of an application. You have to specify the desktop ID (like `firefox.desktop` or `org.gnome.Nautilus.desktop`)
+/// and desired icon size (like 16, 32 or 64). Returns the path to the icon.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_app_icon_as_file(desktop_id: &str, size: i32) -> Result<String, Error> {
+    check(desktop_id, "desktop id", size).and_then(|()| linux::request::get_app_icon_as_file(desktop_id, size)).map_err(|err| err.context(desktop_id, Some(size)))
+}
+
+/// Retrieving the default application and all registered applications for a MIME type (like `text/plain`),
+/// each with its display name and the path to its icon in the desired icon size (like 16, 32 or 64).
+/// The `mimeapps.list` files and `mimeinfo.cache` files are evaluated as specified by freedesktop.org.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_apps_for_mime_type(mime_type: &str, size: i32) -> MimeApps {
+    linux::request::get_apps_for_mime_type(mime_type, size)
+}
+
+/// Retrieving the icon for an URI. You have to specify the URI and desired icon size (like 16, 32 or 64).
+/// `file://` URIs get the icon of the local file, for other schemes like `https:` or `mailto:` the icon of the
+/// application handling `x-scheme-handler/<scheme>` is returned. Returns the icon as byte buffer, which is formatted as png,
+/// unless the icon is taken from a file in a different format.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_icon_for_uri(uri: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(uri, "URI", size).and_then(|()| linux::request::get_icon_for_uri(uri, size)).map_err(|err| err.context(uri, Some(size)))
+}
+
+/// Retrieving the icon for an URI. You have to specify the URI and desired icon size (like 16, 32 or 64).
+/// `file://` URIs get the icon of the local file, for other schemes like `https:` or `mailto:` the icon of the
+/// application handling `x-scheme-handler/<scheme>` is returned. Returns the path to the icon.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_icon_for_uri_as_file(uri: &str, size: i32) -> Result<String, Error> {
+    check(uri, "URI", size).and_then(|()| linux::request::get_icon_for_uri_as_file(uri, size)).map_err(|err| err.context(uri, Some(size)))
+}
+
+/// Retrieving the icon of the application a running process belongs to. You have to specify the process id and desired icon size (like 16, 32 or 64).
+/// The application is identified by the environment (`GIO_LAUNCHED_DESKTOP_FILE` when `GIO_LAUNCHED_DESKTOP_FILE_PID` is the process id, `FLATPAK_ID`, `SNAP_NAME`), the systemd scope
+/// and the executable. Processes without a matching application get the generic executable icon.
+/// Returns the icon as byte buffer, which is formatted as png, unless the application's icon is a file in a different format.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_process_icon(pid: u32, size: i32) -> Result<Vec<u8>, Error> {
+    input::size(size).and_then(|size| linux::request::get_process_icon(pid, size)).map_err(|err| err.context(&pid.to_string(), Some(size)))
+}
+
+/// Retrieving the icon of the application a running process belongs to. You have to specify the process id and desired icon size (like 16, 32 or 64).
+/// Processes without a matching application get the generic executable icon. Returns the path to the icon.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_process_icon_as_file(pid: u32, size: i32) -> Result<String, Error> {
+    input::size(size).and_then(|size| linux::request::get_process_icon_as_file(pid, size)).map_err(|err| err.context(&pid.to_string(), Some(size)))
+}
+
+/// Retrieving the icon for a Wayland app id or an X11 window class (`WM_CLASS`), like task bars need it.
+/// You have to specify the id and desired icon size (like 16, 32 or 64). Returns the icon as byte buffer,
+/// which is formatted as png, unless the application's icon is a file in a different format.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_icon_for_app_id(id: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(id, "app id", size).and_then(|()| linux::request::get_icon_for_app_id(id, size)).map_err(|err| err.context(id, Some(size)))
+}
+
+/// Retrieving the icon for a Wayland app id or an X11 window class (`WM_CLASS`), like task bars need it.
+/// You have to specify the id and desired icon size (like 16, 32 or 64). Returns the path to the icon.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn get_icon_for_app_id_as_file(id: &str, size: i32) -> Result<String, Error> {
+    check(id, "app id", size).and_then(|()| linux::request::get_icon_for_app_id_as_file(id, size)).map_err(|err| err.context(id, Some(size)))
+}
+
+/// Finds the application a Wayland app id or an X11 window class belongs to, and reports which rule has matched.
+#[cfg(target_os = "linux")]
+pub fn find_app_for_app_id(id: &str) -> Option<AppIdMatch> {
+    find_app_for_app_id_in(id, &linux::xdg::get_applications_dirs())
+}
+
+/// Like [`find_app_for_app_id`], but searches the desktop files in the specified `applications` directories.
+#[cfg(target_os = "linux")]
+pub fn find_app_for_app_id_in<P: AsRef<std::path::Path>>(id: &str, applications_dirs: &[P]) -> Option<AppIdMatch> {
+    let dirs: Vec<_> = applications_dirs.iter().map(|dir| dir.as_ref().to_path_buf()).collect();
+    linux::app_id::find_app_for_app_id(id, &dirs).map(|(entry, rule)| AppIdMatch { desktop_id: entry.desktop_id().to_string(), rule })
+}
+
+/// In a non GTK program you have to initialize GTK when getting system icons (Linux). Fails with
+/// [`Error::BackendUnavailable`], if GTK cannot be initialized, like without display.
+#[cfg(all(target_os = "linux", feature = "backend-gtk"))]
+pub fn init() -> Result<(), Error> {
     linux::request::init()
 }
 
@@ -46,68 +311,47 @@ pub fn init() {
 /// Returns the icon formatted as png as byte buffer.
 #[cfg(target_os = "macos")]
 pub fn get_icon(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
-    macos::request::get_icon(ext, size.into())
+    check(ext, "file extension", size).and_then(|()| macos::request::get_icon(ext, size.into())).map_err(|err| err.context(ext, Some(size)))
 }
 
 /// Retrieving system icon. You have to specify the file extension and desired icon size (like 16, 32 or 64).
 /// Returns the path to the system icon.
 #[cfg(target_os = "macos")]
 pub fn get_icon_as_file(ext: &str, size: i32) -> Result<String, Error> {
-    macos::request::get_icon_as_file(ext, size.into())
-}
-
-/// Possible Error
-pub struct Error {
-    pub message: String,
-    pub inner_error: InnerError,
-}
-
-/// Inner Error type of possible Error
-pub enum InnerError {
-    IoError(std::io::Error),
-    Utf8Error(Utf8Error),
-    GtkInitError,
-    #[cfg(target_os = "windows")]
-    ImageError(ImageError),
-    #[cfg(target_os = "windows")]
-    WinError(WinError),
-}
-
-impl From<std::io::Error> for Error {
-    fn from(error: std::io::Error) -> Self {
-        Error {
-            message: error.to_string(),
-            inner_error: InnerError::IoError(error),
-        }
-    }
-}
-
-impl From<Utf8Error> for Error {
-    fn from(error: Utf8Error) -> Self {
-        Error {
-            message: error.to_string(),
-            inner_error: InnerError::Utf8Error(error),
-        }
-    }
-}
-
-impl fmt::Debug for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}, {:?})", self.message, self.inner_error)
-    }
-}
-
-impl fmt::Debug for InnerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let res = match self {
-            &InnerError::GtkInitError => "GtkInitError".to_string(),
-            &InnerError::Utf8Error(_) => "Utf8Error".to_string(),
-            &InnerError::IoError(_) => "IoError".to_string(),
-            #[cfg(target_os = "windows")]
-            &InnerError::ImageError(_) => "ImageError".to_string(),
-            #[cfg(target_os = "windows")]
-            &InnerError::WinError(_) => "WinError".to_string(),
-        };
-        write!(f, "(Error type: {}", res)
-    }
+    check(ext, "file extension", size).and_then(|()| macos::request::get_icon_as_file(ext, size.into())).map_err(|err| err.context(ext, Some(size)))
+}
+
+/// Retrieving the icon of a file or directory on disk. You have to specify the path and desired icon size (like 16, 32 or 64).
+/// Returns the icon formatted as png as byte buffer.
+#[cfg(target_os = "macos")]
+pub fn get_icon_for_path(path: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(path, "path", size).and_then(|()| macos::request::get_icon_for_path(path, size.into())).map_err(|err| err.context(path, Some(size)))
+}
+
+/// Retrieving the icon of a file or directory on disk. You have to specify the path and desired icon size (like 16, 32 or 64).
+/// Returns the path to the icon.
+#[cfg(target_os = "macos")]
+pub fn get_icon_for_path_as_file(path: &str, size: i32) -> Result<String, Error> {
+    check(path, "path", size).and_then(|()| macos::request::get_icon_as_file(path, size.into())).map_err(|err| err.context(path, Some(size)))
+}
+
+/// Retrieving the icons of several file extensions with their sizes at once, like the rows of a file listing.
+/// Every distinct request is only looked up once and requests with the same icon share it.
+#[cfg(any(not(target_os = "linux"), feature = "backend-gtk"))]
+pub fn get_icons(requests: &[(&str, i32)]) -> IconBatch {
+    batch::get_icons(requests)
+}
+
+/// Retrieving the icon of a macOS application bundle (`.app` directory) without Cocoa, so it works on every platform.
+/// The icon named in `Contents/Info.plist` is read from `Contents/Resources`. You have to specify the path
+/// and desired icon size (like 16, 32 or 64). Returns the icon formatted as png as byte buffer.
+pub fn get_app_bundle_icon(path: &str, size: i32) -> Result<Vec<u8>, Error> {
+    check(path, "path", size).and_then(|()| app_bundle::get_icon(std::path::Path::new(path), size)).map_err(|err| err.context(path, Some(size)))
+}
+
+/// Checks the input and the size of a request before it reaches the backend
+fn check(input: &str, what: &str, size: i32) -> Result<(), Error> {
+    input::text(input, what)?;
+    input::size(size)?;
+    Ok(())
 }
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use super::desktop_entry::DesktopEntry;
+
+/// The rule by which a Wayland app id or X11 window class has been matched to a desktop entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppIdRule {
+    /// The id is the desktop ID, like `org.gnome.Nautilus` for `org.gnome.Nautilus.desktop`
+    DesktopId,
+    /// The id equals the `StartupWMClass` key of the desktop entry
+    StartupWmClass,
+    /// The lowercased id is the desktop ID, like `Firefox` for `firefox.desktop`
+    Lowercase,
+    /// The id with dashes and underscores swapped is the desktop ID, like `gnome_terminal` for `gnome-terminal.desktop`
+    DashUnderscore,
+    /// The id is the Flatpak application ID (`X-Flatpak` key), or its last component
+    Flatpak,
+    /// The id is the snap name of a `<snap>_<app>.desktop` file
+    Snap,
+    /// The id is the last component of a reverse DNS desktop ID, like `nautilus` for `org.gnome.Nautilus.desktop`
+    ReverseDns,
+}
+
+/// The desktop entry a Wayland app id or X11 window class belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppIdMatch {
+    pub desktop_id: String,
+    pub rule: AppIdRule,
+}
+
+/// Matches a Wayland app id or X11 `WM_CLASS` against the desktop entries in the `applications` directories
+pub fn find_app_for_app_id(id: &str, dirs: &[PathBuf]) -> Option<(DesktopEntry, AppIdRule)> {
+    let id = id.trim().trim_end_matches(".desktop");
+    if id.is_empty() {
+        return None;
+    }
+    let lowercase = id.to_lowercase();
+    if let Some(entry) = DesktopEntry::find(id, dirs) {
+        return Some((entry, AppIdRule::DesktopId));
+    }
+
+    let entries = DesktopEntry::all(dirs);
+    let find = |matches: &dyn Fn(&DesktopEntry) -> bool| entries.iter().position(matches);
+    let desktop_id_stem = |entry: &DesktopEntry| entry.desktop_id().trim_end_matches(".desktop").to_lowercase();
+
+    let found = find(&|entry| entry.startup_wm_class().as_deref() == Some(id))
+        .or_else(|| find(&|entry| entry.startup_wm_class().map(|class| class.to_lowercase() == lowercase).unwrap_or(false)))
+        .map(|i| (i, AppIdRule::StartupWmClass))
+        .or_else(|| find(&|entry| entry.desktop_id() == format!("{}.desktop", lowercase)).map(|i| (i, AppIdRule::Lowercase)))
+        .or_else(|| {
+            let variants = [lowercase.replace('_', "-"), lowercase.replace('-', "_"), lowercase.replace(' ', "-")];
+            find(&|entry| variants.contains(&desktop_id_stem(entry))).map(|i| (i, AppIdRule::DashUnderscore))
+        })
+        .or_else(|| {
+            find(&|entry| {
+                entry
+                    .flatpak_id()
+                    .map(|flatpak_id| {
+                        let flatpak_id = flatpak_id.to_lowercase();
+                        flatpak_id == lowercase || flatpak_id.rsplit('.').next() == Some(lowercase.as_str())
+                    })
+                    .unwrap_or(false)
+            })
+            .map(|i| (i, AppIdRule::Flatpak))
+        })
+        .or_else(|| {
+            let snap_prefix = format!("{}_", lowercase);
+            find(&|entry| desktop_id_stem(entry) == format!("{}_{}", lowercase, lowercase))
+                .or_else(|| find(&|entry| entry.snap_instance_name().map(|name| name.to_lowercase() == lowercase).unwrap_or(false)))
+                .or_else(|| find(&|entry| desktop_id_stem(entry).starts_with(&snap_prefix)))
+                .map(|i| (i, AppIdRule::Snap))
+        })
+        .or_else(|| {
+            let last_component = lowercase.rsplit('.').next().unwrap_or_default().to_string();
+            find(&|entry| {
+                let stem = desktop_id_stem(entry);
+                stem.contains('.') && stem.rsplit('.').next() == Some(lowercase.as_str())
+                    || lowercase.contains('.') && stem == last_component
+            })
+            .map(|i| (i, AppIdRule::ReverseDns))
+        });
+
+    let (index, rule) = found?;
+    entries.get(index).map(|entry| (entry.clone(), rule))
+}
@@ -0,0 +1,191 @@
+use std::{collections::VecDeque, fs::File, io, os::unix::fs::FileExt, path::Path};
+
+use crate::Error;
+
+use super::{iso9660::Iso9660, key_file::KeyFile, squashfs::SquashFs};
+
+const MAX_SYMLINKS: usize = 16;
+const HICOLOR: &str = "usr/share/icons/hicolor";
+
+#[derive(Clone)]
+pub enum NodeKind {
+    Dir,
+    File,
+    Symlink(String),
+    Other,
+}
+
+/// A read only file system embedded in an AppImage
+pub trait Image {
+    type Node: Clone;
+
+    fn root(&self) -> io::Result<Self::Node>;
+    fn kind(node: &Self::Node) -> &NodeKind;
+    fn read_dir(&self, dir: &Self::Node) -> io::Result<Vec<(String, Self::Node)>>;
+    fn read_file(&self, file: &Self::Node) -> io::Result<Vec<u8>>;
+}
+
+/// AppImages are ELF files with the magic bytes `AI` followed by the type 1 (ISO 9660) or 2 (SquashFS) at offset 8
+pub fn is_appimage(path: &Path) -> bool {
+    File::open(path).ok().and_then(|file| get_type(&file)).is_some()
+}
+
+/// Reads the icon file of an AppImage without mounting or running it. It is either `.DirIcon`
+/// or the icon named in the desktop file, an icon of the hicolor theme which matches the size is preferred.
+/// The icon is returned as it is stored, usually as PNG, sometimes as SVG
+pub fn get_icon(path: &Path, size: i32) -> Result<Vec<u8>, Error> {
+    let file = File::open(path)?;
+    let icon = match get_type(&file) {
+        Some(1) => Iso9660::open(file).and_then(|image| find_icon(&image, size)),
+        Some(_) => get_squashfs_offset(&file).and_then(|offset| find_icon(&SquashFs::open(file, offset)?, size)),
+        None => return Err(Error::invalid_input(&path.to_string_lossy(), "The file is no AppImage")),
+    };
+    // corrupt images, like blocks which decompress to more than the block size
+    let icon = icon.map_err(|err| match err.kind() {
+        io::ErrorKind::InvalidData => Error::decode(&path.to_string_lossy(), "The image of the AppImage is corrupt", Some(Box::new(err))),
+        _ => err.into(),
+    })?;
+    icon.ok_or_else(|| Error::not_found(&path.to_string_lossy(), Some(size), "No icon found in the AppImage"))
+}
+
+fn get_type(file: &File) -> Option<u8> {
+    let mut ident = [0; 11];
+    file.read_exact_at(&mut ident, 0).ok()?;
+    (&ident[..4] == b"\x7fELF" && &ident[8..10] == b"AI" && (ident[10] == 1 || ident[10] == 2)).then_some(ident[10])
+}
+
+/// The SquashFS image of a type 2 AppImage is appended to the ELF runtime, which ends with the section header table
+fn get_squashfs_offset(file: &File) -> io::Result<u64> {
+    let mut header = [0; 64];
+    file.read_exact_at(&mut header, 0)?;
+    let big_endian = header[5] == 2;
+    let read = |position: usize, len: usize| {
+        let bytes = &header[position..position + len];
+        if big_endian {
+            bytes.iter().fold(0u64, |value, &byte| value << 8 | byte as u64)
+        } else {
+            bytes.iter().rev().fold(0u64, |value, &byte| value << 8 | byte as u64)
+        }
+    };
+    // ELFCLASS64 or ELFCLASS32
+    let (section_headers, entry_size, entries) =
+        if header[4] == 2 { (read(0x28, 8), read(0x3a, 2), read(0x3c, 2)) } else { (read(0x20, 4), read(0x2e, 2), read(0x30, 2)) };
+    entry_size
+        .checked_mul(entries)
+        .and_then(|len| section_headers.checked_add(len))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid ELF section header table"))
+}
+
+/// The icon of an AppImage like [`get_icon`], in an opened file system
+pub fn find_icon<I: Image>(image: &I, size: i32) -> io::Result<Option<Vec<u8>>> {
+    let root = image.root()?;
+    let icon_name = image
+        .read_dir(&root)?
+        .into_iter()
+        .filter(|(name, _)| name.ends_with(".desktop"))
+        .find_map(|(name, _)| {
+            let desktop_file = read_path(image, &name).ok()??;
+            KeyFile::parse(&String::from_utf8_lossy(&desktop_file)).get_string("Desktop Entry", "Icon")
+        })
+        .filter(|icon| !icon.is_empty() && !icon.contains('/'));
+
+    let mut candidates = Vec::new();
+    if let Some(icon) = &icon_name {
+        let icon = icon.strip_suffix(".png").or_else(|| icon.strip_suffix(".svg")).unwrap_or(icon);
+        if let Some(dir) = find_hicolor_size(image, icon, size)? {
+            candidates.push(format!("{}/{}/apps/{}.png", HICOLOR, dir, icon));
+        }
+        candidates.push(".DirIcon".to_string());
+        candidates.push(format!("{}.png", icon));
+        candidates.push(format!("{}.svg", icon));
+        candidates.push(format!("{}/scalable/apps/{}.svg", HICOLOR, icon));
+    } else {
+        candidates.push(".DirIcon".to_string());
+    }
+    for candidate in candidates {
+        if let Some(icon) = read_path(image, &candidate)?.filter(|icon| !icon.is_empty()) {
+            return Ok(Some(icon));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the hicolor directory like `64x64` with the smallest icon which is at least as large as `size`,
+/// or the largest one
+fn find_hicolor_size<I: Image>(image: &I, icon: &str, size: i32) -> io::Result<Option<String>> {
+    let Some(hicolor) = resolve(image, HICOLOR)? else {
+        return Ok(None);
+    };
+    if !matches!(I::kind(&hicolor), NodeKind::Dir) {
+        return Ok(None);
+    }
+    let mut sizes: Vec<(i32, String)> = image
+        .read_dir(&hicolor)?
+        .into_iter()
+        .filter_map(|(name, _)| {
+            let (width, height) = name.split_once('x')?;
+            let width: i32 = width.parse().ok()?;
+            (height.parse::<i32>().ok()? == width).then_some((width, name))
+        })
+        .collect();
+    sizes.sort();
+    let mut found = None;
+    for (icon_size, dir) in sizes {
+        if resolve(image, &format!("{}/{}/apps/{}.png", HICOLOR, dir, icon))?.is_some() {
+            found = Some(dir);
+            if icon_size >= size {
+                break;
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Reads a regular file, returns `None` if there is none at the path
+fn read_path<I: Image>(image: &I, path: &str) -> io::Result<Option<Vec<u8>>> {
+    match resolve(image, path)? {
+        Some(node) if matches!(I::kind(&node), NodeKind::File) => image.read_file(&node).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Looks up a path relative to the root of the image, symbolic links are followed.
+/// Absolute links are resolved inside of the image, since they are meant for the mounted AppImage
+fn resolve<I: Image>(image: &I, path: &str) -> io::Result<Option<I::Node>> {
+    let mut components: VecDeque<String> = split_path(path).collect();
+    let mut ancestors = vec![image.root()?];
+    let mut symlinks = 0;
+    while let Some(component) = components.pop_front() {
+        if component == ".." {
+            if ancestors.len() > 1 {
+                ancestors.pop();
+            }
+            continue;
+        }
+        let Some(dir) = ancestors.last().filter(|dir| matches!(I::kind(dir), NodeKind::Dir)) else {
+            return Ok(None);
+        };
+        let Some((_, node)) = image.read_dir(dir)?.into_iter().find(|(name, _)| *name == component) else {
+            return Ok(None);
+        };
+        if let NodeKind::Symlink(target) = I::kind(&node) {
+            symlinks += 1;
+            if symlinks > MAX_SYMLINKS {
+                return Ok(None);
+            }
+            if target.starts_with('/') {
+                ancestors.truncate(1);
+            }
+            for component in split_path(target).collect::<Vec<_>>().into_iter().rev() {
+                components.push_front(component);
+            }
+        } else {
+            ancestors.push(node);
+        }
+    }
+    Ok(ancestors.pop())
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = String> + '_ {
+    path.split('/').filter(|component| !component.is_empty() && *component != ".").map(|component| component.to_string())
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use super::key_file::KeyFile;
+
+const GROUP: &str = "Desktop Entry";
+const ENV_OPTIONS_WITH_VALUE: &[&str] = &["-u", "--unset", "-C", "--chdir"];
+
+/// The result of the last [`DesktopEntry::all`], task managers and docks look up many processes and windows in a row
+static SCAN: Mutex<Option<Scan>> = Mutex::new(None);
+
+struct Scan {
+    dirs: Vec<PathBuf>,
+    /// The modification times of the scanned directories, adding, removing or renaming a desktop file changes them
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    entries: Arc<Vec<DesktopEntry>>,
+}
+
+impl Scan {
+    fn is_valid(&self, dirs: &[PathBuf]) -> bool {
+        self.dirs == dirs && self.modified.iter().all(|(dir, modified)| get_modified(dir) == *modified)
+    }
+}
+
+/// An application's `.desktop` file
+#[derive(Clone)]
+pub struct DesktopEntry {
+    desktop_id: String,
+    key_file: KeyFile,
+}
+
+impl DesktopEntry {
+    /// Finds the desktop file of a desktop ID like `org.gnome.Nautilus.desktop` in the `applications` directories.
+    /// The first file found wins, if it is hidden, the application is regarded as deleted
+    pub fn find(desktop_id: &str, dirs: &[PathBuf]) -> Option<DesktopEntry> {
+        let desktop_id = if desktop_id.ends_with(".desktop") {
+            desktop_id.to_string()
+        } else {
+            format!("{}.desktop", desktop_id)
+        };
+        let file = dirs.iter().find_map(|dir| find_file(dir, &desktop_id))?;
+        DesktopEntry::load(&file, desktop_id)
+    }
+
+    /// Loads a desktop file outside of the `applications` directories, its desktop ID is the file name
+    pub fn load_file(file: &Path) -> Option<DesktopEntry> {
+        let desktop_id = file.file_name()?.to_string_lossy().to_string();
+        DesktopEntry::load(file, desktop_id)
+    }
+
+    /// Returns all applications installed in the `applications` directories.
+    /// Desktop files in directories with lower precedence are shadowed by those with the same desktop ID.
+    /// The directories are only scanned again, when one of them has been modified since the last call
+    pub fn all(dirs: &[PathBuf]) -> Arc<Vec<DesktopEntry>> {
+        let mut scan = SCAN.lock().unwrap_or_else(|err| err.into_inner());
+        match scan.as_ref() {
+            Some(scan) if scan.is_valid(dirs) => scan.entries.clone(),
+            _ => {
+                let mut desktop_ids = HashSet::new();
+                let mut files = Vec::new();
+                let mut modified = Vec::new();
+                for dir in dirs {
+                    collect_files(dir, "", &mut files, &mut modified);
+                }
+                let entries: Vec<_> = files
+                    .into_iter()
+                    .filter(|(desktop_id, _)| desktop_ids.insert(desktop_id.clone()))
+                    .filter_map(|(desktop_id, file)| DesktopEntry::load(&file, desktop_id))
+                    .collect();
+                let entries = Arc::new(entries);
+                *scan = Some(Scan { dirs: dirs.to_vec(), modified, entries: entries.clone() });
+                entries
+            }
+        }
+    }
+
+    fn load(file: &Path, desktop_id: String) -> Option<DesktopEntry> {
+        let key_file = KeyFile::load(file).ok()?;
+        (key_file.get(GROUP, "Hidden") != Some("true")).then_some(DesktopEntry { desktop_id, key_file })
+    }
+
+    pub fn desktop_id(&self) -> &str {
+        &self.desktop_id
+    }
+
+    pub fn name(&self) -> Option<String> {
+        self.key_file.get_locale_string(GROUP, "Name")
+    }
+
+    pub fn icon(&self) -> Option<String> {
+        self.key_file.get_string(GROUP, "Icon").filter(|icon| !icon.is_empty())
+    }
+
+    pub fn try_exec(&self) -> Option<String> {
+        self.key_file.get_string(GROUP, "TryExec").filter(|try_exec| !try_exec.is_empty())
+    }
+
+    pub fn startup_wm_class(&self) -> Option<String> {
+        self.key_file.get_string(GROUP, "StartupWMClass").filter(|class| !class.is_empty())
+    }
+
+    /// The Flatpak application ID of desktop files exported by Flatpak
+    pub fn flatpak_id(&self) -> Option<String> {
+        self.key_file.get_string(GROUP, "X-Flatpak").filter(|id| !id.is_empty())
+    }
+
+    /// The snap name of desktop files exported by snapd
+    pub fn snap_instance_name(&self) -> Option<String> {
+        self.key_file.get_string(GROUP, "X-SnapInstanceName").filter(|name| !name.is_empty())
+    }
+
+    /// Returns the program of the `Exec` key, leading `env VAR=value` arguments are skipped
+    pub fn exec_program(&self) -> Option<String> {
+        let exec = self.key_file.get_string(GROUP, "Exec")?;
+        let mut args = split_exec(&exec).into_iter().peekable();
+        if args.peek().map(|arg| arg == "env" || arg.ends_with("/env")).unwrap_or(false) {
+            args.next();
+            while let Some(arg) = args.next_if(|arg| arg.contains('=') || arg.starts_with('-')) {
+                // options like `-u VAR` have a separate value
+                if ENV_OPTIONS_WITH_VALUE.contains(&arg.as_str()) {
+                    args.next();
+                }
+            }
+        }
+        args.next()
+    }
+}
+
+/// Desktop files in subdirectories have the vendor prefix `<subdir>-` in their desktop ID,
+/// `kde-kate.desktop` may be found at `kde/kate.desktop`
+fn find_file(dir: &Path, desktop_id: &str) -> Option<PathBuf> {
+    let file = dir.join(desktop_id);
+    if file.is_file() {
+        return Some(file);
+    }
+    desktop_id
+        .match_indices('-')
+        .map(|(i, _)| (&desktop_id[..i], &desktop_id[i + 1..]))
+        .filter(|(prefix, _)| dir.join(prefix).is_dir())
+        .find_map(|(prefix, rest)| find_file(&dir.join(prefix), rest))
+}
+
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>, modified: &mut Vec<(PathBuf, Option<SystemTime>)>) {
+    // a missing directory is remembered too, so that its creation is noticed
+    modified.push((dir.to_path_buf(), get_modified(dir)));
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, &format!("{}{}-", prefix, name), files, modified);
+        } else if name.ends_with(".desktop") {
+            files.push((format!("{}{}", prefix, name), path));
+        }
+    }
+}
+
+fn get_modified(dir: &Path) -> Option<SystemTime> {
+    fs::metadata(dir).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Splits the `Exec` value into arguments, which may be quoted with double quotes
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => arg.extend(chars.next()),
+            ' ' | '\t' if !in_quotes => {
+                if !arg.is_empty() {
+                    args.push(std::mem::take(&mut arg));
+                }
+            }
+            _ => arg.push(c),
+        }
+    }
+    if !arg.is_empty() {
+        args.push(arg);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESKTOP_ENTRY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/desktop_entry");
+
+    fn dirs() -> Vec<PathBuf> {
+        ["high", "low"].iter().map(|dir| PathBuf::from(DESKTOP_ENTRY).join(dir)).collect()
+    }
+
+    fn find(desktop_id: &str) -> Option<DesktopEntry> {
+        DesktopEntry::find(desktop_id, &dirs())
+    }
+
+    #[test]
+    fn keys() {
+        let entry = find("org.example.Editor").unwrap();
+        assert_eq!(entry.desktop_id(), "org.example.Editor.desktop");
+        assert_eq!(entry.icon().unwrap(), "org.example.Editor");
+        assert_eq!(entry.try_exec().unwrap(), "/opt/My Editor/bin/editor");
+        assert_eq!(entry.startup_wm_class().unwrap(), "example-editor");
+        assert_eq!(entry.flatpak_id().unwrap(), "org.example.Editor");
+        assert_eq!(entry.snap_instance_name(), None);
+    }
+
+    #[test]
+    fn empty_keys_are_missing() {
+        let entry = find("vendor-app.desktop").unwrap();
+        assert_eq!(entry.icon(), None);
+        assert_eq!(entry.try_exec(), None);
+        assert_eq!(entry.snap_instance_name().unwrap(), "vendor");
+    }
+
+    #[test]
+    fn exec_program() {
+        // quoted program with field codes and quoted arguments
+        assert_eq!(find("org.example.Editor").unwrap().exec_program().unwrap(), "/opt/My Editor/bin/editor");
+        // env with options and variables
+        assert_eq!(find("vendor-app").unwrap().exec_program().unwrap(), "/usr/bin/vendor-app");
+        // \s is a space
+        assert_eq!(find("org.example.Viewer").unwrap().exec_program().unwrap(), "viewer");
+    }
+
+    #[test]
+    fn precedence() {
+        // the first directory shadows the second one
+        assert_eq!(find("org.example.Editor").unwrap().exec_program().unwrap(), "/opt/My Editor/bin/editor");
+        // a hidden desktop file deletes the application
+        assert!(find("deleted").is_none());
+        assert!(find("missing").is_none());
+    }
+
+    #[test]
+    fn vendor_prefix() {
+        assert_eq!(find("vendor-app").unwrap().desktop_id(), "vendor-app.desktop");
+        assert!(find("vendor").is_none());
+    }
+
+    #[test]
+    fn all() {
+        let desktop_ids: Vec<_> = DesktopEntry::all(&dirs()).iter().map(|entry| entry.desktop_id().to_string()).collect();
+        // hidden and invalid desktop files are skipped
+        assert_eq!(desktop_ids, ["org.example.Editor.desktop", "vendor-app.desktop", "org.example.Viewer.desktop"]);
+    }
+
+    #[test]
+    fn all_is_scanned_again_when_modified() {
+        let dir = std::env::temp_dir().join(format!("systemicons-test-{}-desktop-entry-all", std::process::id()));
+        let desktop_ids = || DesktopEntry::all(std::slice::from_ref(&dir)).iter().map(|entry| entry.desktop_id().to_string()).collect::<Vec<_>>();
+        assert!(desktop_ids().is_empty());
+        fs::create_dir_all(dir.join("vendor")).unwrap();
+        fs::write(dir.join("viewer.desktop"), "[Desktop Entry]\nExec=viewer\n").unwrap();
+        assert_eq!(desktop_ids(), ["viewer.desktop"]);
+        fs::write(dir.join("vendor/editor.desktop"), "[Desktop Entry]\nExec=editor\n").unwrap();
+        assert_eq!(desktop_ids(), ["vendor-editor.desktop", "viewer.desktop"]);
+        fs::remove_file(dir.join("viewer.desktop")).unwrap();
+        let after_removal = desktop_ids();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(after_removal, ["vendor-editor.desktop"]);
+    }
+
+    #[test]
+    fn load_file() {
+        let entry = DesktopEntry::load_file(&PathBuf::from(DESKTOP_ENTRY).join("low/org.example.Viewer.desktop")).unwrap();
+        assert_eq!(entry.desktop_id(), "org.example.Viewer.desktop");
+        assert!(DesktopEntry::load_file(&PathBuf::from(DESKTOP_ENTRY).join("low/broken.desktop")).is_none());
+        assert!(DesktopEntry::load_file(&PathBuf::from(DESKTOP_ENTRY).join("high/deleted.desktop")).is_none());
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+use super::{gvfs_metadata, key_file::KeyFile};
+
+/// A custom icon the user has chosen for a directory
+pub enum CustomIcon {
+    /// An image file
+    File(PathBuf),
+    /// An icon name to be looked up in the icon theme
+    Name(String),
+}
+
+/// Returns the custom icon set in Dolphin (`.directory` file) or in Nautilus (GVfs metadata)
+pub fn get_custom_icon(path: &Path) -> Option<CustomIcon> {
+    get_dolphin_icon(path).or_else(|| get_nautilus_icon(path))
+}
+
+fn get_dolphin_icon(path: &Path) -> Option<CustomIcon> {
+    let key_file = KeyFile::load(&path.join(".directory")).ok()?;
+    let icon = key_file.get_string("Desktop Entry", "Icon")?;
+    if icon.is_empty() {
+        None
+    } else if icon.contains('/') {
+        let file = path.join(icon);
+        file.is_file().then_some(CustomIcon::File(file))
+    } else {
+        Some(CustomIcon::Name(icon))
+    }
+}
+
+fn get_nautilus_icon(path: &Path) -> Option<CustomIcon> {
+    gvfs_metadata::get_metadata(path, "custom-icon")
+        .and_then(|uri| glib::filename_from_uri(&uri).ok())
+        .map(|(file, _)| file)
+        .filter(|file| file.is_file())
+        .map(CustomIcon::File)
+        .or_else(|| gvfs_metadata::get_metadata(path, "custom-icon-name").map(CustomIcon::Name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// The custom icon of a directory, the path of an image file or an icon name
+    fn directory_icon(path: &Path) -> Option<String> {
+        match get_custom_icon(path)? {
+            CustomIcon::File(file) => Some(file.to_string_lossy().to_string()),
+            CustomIcon::Name(name) => Some(name),
+        }
+    }
+
+    fn dir(name: &str, directory: Option<&[u8]>) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("systemicons-test-{}-directory-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        if let Some(directory) = directory {
+            fs::write(dir.join(".directory"), directory).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn icon_name() {
+        let dir = dir("name", Some(b"[Desktop Entry]\nIcon=folder-music\n"));
+        let icon = directory_icon(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(icon.unwrap(), "folder-music");
+    }
+
+    #[test]
+    fn icon_file() {
+        let dir = dir("file", Some(b"[Desktop Entry]\nIcon=./icons/cover.png\n"));
+        fs::create_dir_all(dir.join("icons")).unwrap();
+        fs::write(dir.join("icons/cover.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+        let icon = directory_icon(&dir);
+        let path = crate::get_icon_for_path_as_file(dir.to_str().unwrap(), 32);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(icon.unwrap(), dir.join("./icons/cover.png").to_str().unwrap());
+        assert_eq!(path.unwrap(), dir.join("./icons/cover.png").to_str().unwrap());
+    }
+
+    #[test]
+    fn missing_icon_file() {
+        let dir = dir("missing", Some(b"[Desktop Entry]\nIcon=./icons/cover.png\n"));
+        let icon = directory_icon(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(icon, None);
+    }
+
+    #[test]
+    fn corrupt_directory_file() {
+        for (name, directory) in [
+            ("empty", &b""[..]),
+            ("no-group", b"Icon=folder-music\n"),
+            ("other-group", b"[Dolphin]\nIcon=folder-music\n"),
+            ("empty-icon", b"[Desktop Entry]\nIcon=\n"),
+            ("truncated", b"[Desktop Ent"),
+            ("binary", b"\xff\xfe\0[Desktop Entry]\0Icon"),
+        ] {
+            let dir = dir(name, Some(directory));
+            let icon = directory_icon(&dir);
+            fs::remove_dir_all(&dir).unwrap();
+            assert_eq!(icon, None, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Explains how the icon of a request is found: the MIME type is detected with the shared MIME-info database,
+//! its icon names are looked up in the icon theme and its parents like GTK does.
+//!
+//! Only the MIME type lookup is traced. Custom folder icons, AppImages, executables and shortcuts, which have their
+//! own icons, are not part of the explanation.
+use std::path::{Path, PathBuf};
+
+use super::{
+    icon_theme::{self, ThemeVisit},
+    shared_mime::{MimeDatabase, MimeReason, MimeStep},
+    xdg,
+};
+
+/// GTK is asked without `GTK_ICON_LOOKUP_FORCE_SVG`, but with `GTK_ICON_LOOKUP_NO_SVG`
+pub(crate) const EXTENSIONS: &[&str] = &["png", "xpm"];
+
+/// What an icon is requested for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IconQuery {
+    /// A file extension like `.pdf`
+    Extension(String),
+    Path(PathBuf),
+    MimeType(String),
+}
+
+/// An icon request to be explained
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconRequest {
+    pub query: IconQuery,
+    pub size: i32,
+    pub scale: i32,
+}
+
+impl IconRequest {
+    pub fn extension(ext: &str, size: i32) -> IconRequest {
+        IconRequest { query: IconQuery::Extension(ext.to_string()), size, scale: 1 }
+    }
+
+    pub fn path<P: Into<PathBuf>>(path: P, size: i32) -> IconRequest {
+        IconRequest { query: IconQuery::Path(path.into()), size, scale: 1 }
+    }
+
+    pub fn mime_type(mime_type: &str, size: i32) -> IconRequest {
+        IconRequest { query: IconQuery::MimeType(mime_type.to_string()), size, scale: 1 }
+    }
+
+    pub fn with_scale(self, scale: i32) -> IconRequest {
+        IconRequest { scale, ..self }
+    }
+}
+
+/// The trace of an icon lookup
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub request: IconRequest,
+    /// The detected MIME type, which may be an alias
+    pub mime_type: String,
+    pub reason: MimeReason,
+    /// The alias resolution and the subclass relations of the canonical type
+    pub steps: Vec<MimeStep>,
+    /// The icon names of the canonical type in the order they are looked up
+    pub icon_names: Vec<String>,
+    /// The icon theme where the lookup starts
+    pub theme: String,
+    /// The themes of the inheritance chain which have been searched
+    pub visits: Vec<ThemeVisit>,
+    /// Matching icons directly in the base directories, which are used if no theme has one
+    pub unthemed: Vec<PathBuf>,
+    pub icon: Option<PathBuf>,
+    /// Why no icon has been found
+    pub failure: Option<String>,
+}
+
+/// Explains the lookup with the icon theme configured in GTK or in `gtk-3.0/settings.ini`
+pub fn explain(request: &IconRequest) -> Explanation {
+    #[cfg(feature = "backend-gtk")]
+    let theme = super::request::get_current_icon_theme().ok();
+    #[cfg(not(feature = "backend-gtk"))]
+    let theme = None;
+    let theme = theme
+        .or_else(icon_theme::get_configured_theme)
+        .unwrap_or_else(|| "hicolor".to_string());
+    let mime_dirs: Vec<PathBuf> = std::iter::once(xdg::get_data_home()).chain(xdg::get_data_dirs()).collect();
+    // like the GTK backend, the icons of Flatpak and Snap applications are searched, too
+    let mut base_dirs = icon_theme::get_base_dirs();
+    base_dirs.extend(xdg::get_missing_data_dirs().iter().map(|dir| dir.join("icons")));
+    explain_with(request, &MimeDatabase::load(&mime_dirs), &base_dirs, &theme)
+}
+
+/// Explains the lookup in the `mime` and `icons` directories of the given data directories
+pub fn explain_in(request: &IconRequest, data_dirs: &[PathBuf], theme: &str) -> Explanation {
+    let base_dirs: Vec<PathBuf> = data_dirs.iter().map(|dir| dir.join("icons")).collect();
+    explain_with(request, &MimeDatabase::load(data_dirs), &base_dirs, theme)
+}
+
+/// Explains the lookup with a loaded database in the icon theme directories `base_dirs`
+#[tracing::instrument(level = "debug", skip(database, base_dirs))]
+pub fn explain_with(request: &IconRequest, database: &MimeDatabase, base_dirs: &[PathBuf], theme: &str) -> Explanation {
+    let (mime_type, reason) = match &request.query {
+        IconQuery::Extension(ext) => database
+            .detect_name(ext)
+            .unwrap_or_else(|| ("application/octet-stream".to_string(), MimeReason::Fallback)),
+        IconQuery::Path(path) => database.detect_file(Path::new(path)),
+        IconQuery::MimeType(mime_type) => (mime_type.clone(), MimeReason::Given),
+    };
+    tracing::debug!(mime_type, ?reason, "detected MIME type");
+    let (canonical, steps) = database.ancestors(&mime_type);
+    let icon_names = database.icon_names(&canonical);
+    let lookup = icon_theme::lookup_icon(base_dirs, theme, &icon_names, request.size, request.scale, EXTENSIONS);
+    let failure = lookup.icon.is_none().then(|| {
+        let themes: Vec<&str> = lookup.visits.iter().map(|visit| visit.theme.as_str()).collect();
+        format!("None of the icons {} found in {}", icon_names.join(", "), themes.join(", "))
+    });
+    tracing::debug!(icon = ?lookup.icon, "icon lookup finished");
+    Explanation {
+        request: request.clone(),
+        mime_type,
+        reason,
+        steps,
+        icon_names,
+        theme: theme.to_string(),
+        visits: lookup.visits,
+        unthemed: lookup.unthemed,
+        icon: lookup.icon,
+        failure,
+    }
+}
@@ -0,0 +1,320 @@
+//! Reader for the binary metadata database of GVfs (`~/.local/share/gvfs-metadata`), where
+//! Nautilus stores attributes like `metadata::custom-icon`.
+//!
+//! A database consists of a tree file per mount (`home`, `root`, `uuid-...`) and a journal
+//! (`<tree>-<tag>.log`) holding the changes which have not yet been rotated into the tree.
+//! All numbers are stored big endian.
+use std::{
+    env, fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use super::xdg;
+
+const TREE_MAGIC: &[u8] = b"\xda\x1ameta";
+const JOURNAL_MAGIC: &[u8] = b"\xda\x1ajour";
+const KEY_IS_LIST_MASK: u32 = 1 << 31;
+/// How often a tree is read again, when it has been rotated while reading it
+const MAX_REOPEN: usize = 3;
+
+const JOURNAL_OP_SET_KEY: u8 = 0;
+const JOURNAL_OP_SETV_KEY: u8 = 1;
+const JOURNAL_OP_UNSET_KEY: u8 = 2;
+const JOURNAL_OP_REMOVE_PATH: u8 = 4;
+
+/// Returns the value of the metadata attribute `key` (without the `metadata::` prefix) of `path`
+pub fn get_metadata(path: &Path, key: &str) -> Option<String> {
+    let (tree_name, tree_path) = get_tree(path)?;
+    let filename = xdg::get_data_home().join("gvfs-metadata").join(tree_name);
+    // GVfs replaces a rotated tree by a new file, which already contains the changes of the journal
+    let tree = (0..MAX_REOPEN).find_map(|_| MetaTree::new(fs::read(&filename).ok()?))?;
+    let journal_filename = PathBuf::from(format!("{}-{:08x}.log", filename.to_string_lossy(), tree.random_tag()?));
+    let journal = fs::read(journal_filename).unwrap_or_default();
+    lookup(&tree, &journal, &tree_path, key)
+}
+
+/// Returns the value of `key` of the path inside of a tree file, the journal overrides the tree
+pub fn parse(tree: Vec<u8>, journal: &[u8], path: &str, key: &str) -> Option<String> {
+    lookup(&MetaTree::new(tree)?, journal, path, key)
+}
+
+fn lookup(tree: &MetaTree, journal: &[u8], path: &str, key: &str) -> Option<String> {
+    match lookup_journal(journal, tree.random_tag()?, path, key) {
+        Some(value) => value,
+        None => tree.lookup(path, key),
+    }
+}
+
+/// Determines the metadata tree `path` belongs to, and the path inside this tree
+fn get_tree(path: &Path) -> Option<(String, String)> {
+    let path = path.canonicalize().ok()?;
+    let device = fs::metadata(&path).ok()?.dev();
+    if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+        if path.starts_with(&home) && fs::metadata(&home).map(|m| m.dev() == device).unwrap_or(false) {
+            return Some(("home".to_string(), get_tree_path(&path, &home)));
+        }
+    }
+    if fs::metadata("/").ok()?.dev() == device {
+        return Some(("root".to_string(), get_tree_path(&path, Path::new("/"))));
+    }
+    let mut mount_point = path.as_path();
+    while let Some(parent) = mount_point.parent().filter(|p| fs::metadata(p).map(|m| m.dev() == device).unwrap_or(false)) {
+        mount_point = parent;
+    }
+    let uuid = fs::read_dir("/dev/disk/by-uuid")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| fs::metadata(entry.path()).map(|m| m.rdev() == device).unwrap_or(false))?
+        .file_name();
+    Some((format!("uuid-{}", uuid.to_string_lossy()), get_tree_path(&path, mount_point)))
+}
+
+fn get_tree_path(path: &Path, prefix: &Path) -> String {
+    format!("/{}", path.strip_prefix(prefix).unwrap_or(path).to_string_lossy())
+}
+
+struct MetaTree {
+    data: Vec<u8>,
+}
+
+impl MetaTree {
+    /// Fails for rotated trees, which have been replaced by a new file
+    fn new(data: Vec<u8>) -> Option<MetaTree> {
+        // magic, major version 1, minor version 0, rotated flag
+        (data.len() >= 32 && data.starts_with(TREE_MAGIC) && data[6] == 1 && read_u32(&data, 8)? == 0).then_some(MetaTree { data })
+    }
+
+    fn random_tag(&self) -> Option<u32> {
+        read_u32(&self.data, 12)
+    }
+
+    fn lookup(&self, path: &str, key: &str) -> Option<String> {
+        let mut dir_ent = read_u32(&self.data, 16)? as usize;
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            dir_ent = self.find_child(dir_ent, segment)?;
+        }
+        let key_index = self.find_attribute(key)?;
+        let metadata = read_u32(&self.data, dir_ent + 8)? as usize;
+        if metadata == 0 {
+            return None;
+        }
+        let num_keys = self.count(metadata, 8)?;
+        let (ent, ent_key) = (0..num_keys)
+            .map(|i| metadata + 4 + i * 8)
+            .filter_map(|ent| Some((ent, read_u32(&self.data, ent)?)))
+            .find(|(_, ent_key)| ent_key & !KEY_IS_LIST_MASK == key_index)?;
+        // string lists are not needed for icons
+        if ent_key & KEY_IS_LIST_MASK != 0 {
+            return None;
+        }
+        read_string(&self.data, read_u32(&self.data, ent + 4)? as usize)
+    }
+
+    fn find_child(&self, dir_ent: usize, name: &str) -> Option<usize> {
+        let children = read_u32(&self.data, dir_ent + 4)? as usize;
+        if children == 0 {
+            return None;
+        }
+        let num_children = self.count(children, 16)?;
+        (0..num_children)
+            .map(|i| children + 4 + i * 16)
+            .find(|&child| {
+                read_u32(&self.data, child)
+                    .and_then(|name_offset| read_string(&self.data, name_offset as usize))
+                    .map(|child_name| child_name == name)
+                    .unwrap_or(false)
+            })
+    }
+
+    /// Returns the index of the attribute name, which is the key used in the metadata entries
+    fn find_attribute(&self, key: &str) -> Option<u32> {
+        let attributes = read_u32(&self.data, 20)? as usize;
+        let num_strings = self.count(attributes, 4)?;
+        (0..num_strings as u32)
+            .find(|&i| {
+                read_u32(&self.data, attributes + 4 + i as usize * 4)
+                    .and_then(|offset| read_string(&self.data, offset as usize))
+                    .map(|name| name == key)
+                    .unwrap_or(false)
+            })
+    }
+
+    /// The number of entries of a table, which cannot be larger than the tree
+    fn count(&self, table: usize, entry_size: usize) -> Option<usize> {
+        Some((read_u32(&self.data, table)? as usize).min(self.data.len() / entry_size))
+    }
+}
+
+/// Replays the journal. Returns `Some(None)` when the journal unsets the key or removes the path
+fn lookup_journal(journal: &[u8], random_tag: u32, path: &str, key: &str) -> Option<Option<String>> {
+    if journal.len() < 20 || !journal.starts_with(JOURNAL_MAGIC) || read_u32(journal, 8)? != random_tag {
+        return None;
+    }
+    let num_entries = read_u32(journal, 16)?;
+    let mut result = None;
+    let mut pos = 20;
+    for _ in 0..num_entries {
+        let entry_size = read_u32(journal, pos)? as usize;
+        if entry_size < 21 || pos + entry_size > journal.len() {
+            break;
+        }
+        let entry = &journal[pos..pos + entry_size];
+        pos += entry_size;
+        let entry_type = entry[16];
+        let entry_path = read_string(entry, 17)?;
+        let key_offset = 17 + entry_path.len() + 1;
+        match entry_type {
+            JOURNAL_OP_SET_KEY | JOURNAL_OP_SETV_KEY | JOURNAL_OP_UNSET_KEY if entry_path == path => {
+                if read_string(entry, key_offset)? != key {
+                    continue;
+                }
+                result = Some(match entry_type {
+                    JOURNAL_OP_SET_KEY => read_string(entry, key_offset + key.len() + 1),
+                    _ => None,
+                });
+            }
+            JOURNAL_OP_REMOVE_PATH if path == entry_path || path.starts_with(&format!("{}/", entry_path.trim_end_matches('/'))) => {
+                result = Some(None);
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_string(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let len = bytes.iter().position(|&b| b == 0)?;
+    String::from_utf8(bytes[..len].to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAG: u32 = 0x1234_abcd;
+    const ICON: &str = "file:///home/user/Pictures/projects.png";
+
+    /// Looks up `key` in a copy of the tree
+    fn metadata(tree: &[u8], journal: &[u8], path: &str, key: &str) -> Option<String> {
+        parse(tree.to_vec(), journal, path, key)
+    }
+
+    /// A tree with the directories `/Documents` and `/Documents/Projects`, the latter has a custom icon
+    fn tree(rotated: bool) -> Vec<u8> {
+        let mut data = b"\xda\x1ameta\x01\x00".to_vec();
+        data.extend((rotated as u32).to_be_bytes());
+        data.extend(TAG.to_be_bytes());
+        // root directory, attributes, time base
+        data.extend([44u32, 32].iter().flat_map(|value| value.to_be_bytes()));
+        data.extend(0u64.to_be_bytes());
+        // the offsets of the strings are patched, when the strings are appended
+        let strings = ["custom-icon", "custom-icon-name", "", "Documents", "Projects", ICON];
+        let mut patches = Vec::new();
+        let mut table = |data: &mut Vec<u8>, values: &[u32], string: Option<usize>| {
+            if let Some(string) = string {
+                patches.push((data.len(), string));
+            }
+            data.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+        };
+        // attributes at 32
+        table(&mut data, &[2], None);
+        table(&mut data, &[0], Some(0));
+        table(&mut data, &[0], Some(1));
+        // root at 44 with the children at 60
+        table(&mut data, &[0, 60, 0, 0], Some(2));
+        table(&mut data, &[1], None);
+        table(&mut data, &[0, 80, 0, 0], Some(3));
+        table(&mut data, &[1], None);
+        // Projects at 84 with the metadata at 100
+        table(&mut data, &[0, 0, 100, 0], Some(4));
+        table(&mut data, &[1, 0], None);
+        table(&mut data, &[0], Some(5));
+        let mut offsets = Vec::new();
+        for string in strings {
+            offsets.push(data.len() as u32);
+            data.extend(string.as_bytes());
+            data.push(0);
+        }
+        for (position, string) in patches {
+            data[position..position + 4].copy_from_slice(&offsets[string].to_be_bytes());
+        }
+        data
+    }
+
+    /// A journal with entries of the operation, path, key and value
+    fn journal(tag: u32, entries: &[(u8, &str, &str, &str)]) -> Vec<u8> {
+        let mut data = b"\xda\x1ajour\x01\x00".to_vec();
+        data.extend(tag.to_be_bytes());
+        data.extend(0u32.to_be_bytes());
+        data.extend((entries.len() as u32).to_be_bytes());
+        for (operation, path, key, value) in entries {
+            let mut entry = vec![*operation];
+            for string in [path, key, value] {
+                entry.extend(string.as_bytes());
+                entry.push(0);
+            }
+            let size = (4 + 4 + 8 + entry.len() + 4) as u32;
+            data.extend(size.to_be_bytes());
+            data.extend([0; 12]);
+            data.extend(entry);
+            data.extend(size.to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn tree_lookup() {
+        let tree = tree(false);
+        assert_eq!(metadata(&tree, &[], "/Documents/Projects", "custom-icon").unwrap(), ICON);
+        assert_eq!(metadata(&tree, &[], "/Documents/Projects", "custom-icon-name"), None);
+        assert_eq!(metadata(&tree, &[], "/Documents", "custom-icon"), None);
+        assert_eq!(metadata(&tree, &[], "/Music", "custom-icon"), None);
+    }
+
+    #[test]
+    fn journal_overrides_tree() {
+        let tree = tree(false);
+        let set = journal(TAG, &[(0, "/Documents/Projects", "custom-icon", "file:///tmp/other.png")]);
+        assert_eq!(metadata(&tree, &set, "/Documents/Projects", "custom-icon").unwrap(), "file:///tmp/other.png");
+        let set = journal(TAG, &[(0, "/Documents", "custom-icon-name", "folder-documents")]);
+        assert_eq!(metadata(&tree, &set, "/Documents", "custom-icon-name").unwrap(), "folder-documents");
+        let unset = journal(TAG, &[(0, "/Documents/Projects", "custom-icon", "file:///tmp/other.png"), (2, "/Documents/Projects", "custom-icon", "")]);
+        assert_eq!(metadata(&tree, &unset, "/Documents/Projects", "custom-icon"), None);
+        let remove = journal(TAG, &[(4, "/Documents", "", "")]);
+        assert_eq!(metadata(&tree, &remove, "/Documents/Projects", "custom-icon"), None);
+        // a journal of another tree is ignored
+        let other = journal(TAG + 1, &[(2, "/Documents/Projects", "custom-icon", "")]);
+        assert_eq!(metadata(&tree, &other, "/Documents/Projects", "custom-icon").unwrap(), ICON);
+    }
+
+    #[test]
+    fn rotated_tree() {
+        assert_eq!(metadata(&tree(true), &[], "/Documents/Projects", "custom-icon"), None);
+    }
+
+    #[test]
+    fn truncated_tree() {
+        let tree = tree(false);
+        for len in 0..tree.len() {
+            assert_eq!(metadata(&tree[..len], &[], "/Documents/Projects", "custom-icon"), None, "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn corrupt_tree() {
+        // a huge number of children of the root
+        let mut tree = tree(false);
+        tree[60..64].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(metadata(&tree, &[], "/Music", "custom-icon"), None);
+        let mut journal = journal(TAG, &[(0, "/Documents/Projects", "custom-icon", "file:///tmp/other.png")]);
+        // a truncated journal entry is ignored
+        journal.truncate(journal.len() - 8);
+        assert_eq!(metadata(&tree, &journal, "/Documents/Projects", "custom-icon").unwrap(), ICON);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use super::{key_file::KeyFile, xdg};
+
+/// An installed icon theme
+#[derive(Debug, Clone)]
+pub struct IconTheme {
+    /// The name of the theme's directory, like `Adwaita`
+    pub id: String,
+    /// The display name from `index.theme`
+    pub name: String,
+    /// The directory containing `index.theme`, a theme may be spread over several base directories
+    pub path: PathBuf,
+    /// The themes which are searched, if an icon is missing
+    pub inherits: Vec<String>,
+    /// Hidden themes like `hicolor` are not shown in theme selectors
+    pub hidden: bool,
+}
+
+/// The base directories of icon themes in order of precedence: `~/.icons`, `$XDG_DATA_HOME/icons`,
+/// the `icons` directories in `$XDG_DATA_DIRS` and `/usr/share/pixmaps`
+pub fn get_base_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".icons")];
+    dirs.extend(std::iter::once(xdg::get_data_home()).chain(xdg::get_data_dirs()).map(|dir| dir.join("icons")));
+    dirs.push(PathBuf::from("/usr/share/pixmaps"));
+    dirs
+}
+
+/// Lists the installed icon themes sorted by id. If a theme is installed in several base directories,
+/// the first `index.theme` wins
+pub fn get_icon_themes() -> Vec<IconTheme> {
+    let mut themes: Vec<IconTheme> = Vec::new();
+    for base_dir in get_base_dirs() {
+        let Ok(entries) = fs::read_dir(&base_dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let id = entry.file_name().to_string_lossy().to_string();
+            if themes.iter().any(|theme| theme.id == id) {
+                continue;
+            }
+            if let Some(theme) = load(&id, &entry.path()) {
+                themes.push(theme);
+            }
+        }
+    }
+    themes.sort_by(|a, b| a.id.cmp(&b.id));
+    themes
+}
+
+fn load(id: &str, path: &Path) -> Option<IconTheme> {
+    let index = KeyFile::load(&path.join("index.theme")).ok()?;
+    Some(IconTheme {
+        id: id.to_string(),
+        name: index.get_locale_string("Icon Theme", "Name").unwrap_or_else(|| id.to_string()),
+        path: path.to_path_buf(),
+        inherits: index
+            .get_string("Icon Theme", "Inherits")
+            .map(|inherits| inherits.split(',').map(|theme| theme.trim().to_string()).filter(|theme| !theme.is_empty()).collect())
+            .unwrap_or_default(),
+        hidden: index.get("Icon Theme", "Hidden") == Some("true"),
+    })
+}
+
+/// How the size of the icons in a theme directory is interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectoryType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// A directory of an icon theme as described in `index.theme`
+#[derive(Debug, Clone)]
+pub struct ThemeDirectory {
+    pub name: String,
+    pub size: i32,
+    pub scale: i32,
+    pub directory_type: DirectoryType,
+    pub min_size: i32,
+    pub max_size: i32,
+    pub threshold: i32,
+}
+
+impl ThemeDirectory {
+    /// `DirectoryMatchesSize` of the icon theme specification
+    pub fn matches_size(&self, size: i32, scale: i32) -> bool {
+        self.scale == scale
+            && match self.directory_type {
+                DirectoryType::Fixed => self.size == size,
+                DirectoryType::Scalable => self.min_size <= size && size <= self.max_size,
+                DirectoryType::Threshold => self.size - self.threshold <= size && size <= self.size + self.threshold,
+            }
+    }
+
+    /// `DirectorySizeDistance` of the icon theme specification
+    pub fn size_distance(&self, size: i32, scale: i32) -> i32 {
+        let pixels = size * scale;
+        let (min, max) = match self.directory_type {
+            DirectoryType::Fixed => (self.size, self.size),
+            DirectoryType::Scalable => (self.min_size, self.max_size),
+            DirectoryType::Threshold => (self.size - self.threshold, self.size + self.threshold),
+        };
+        if pixels < min * self.scale {
+            min * self.scale - pixels
+        } else if pixels > max * self.scale {
+            pixels - max * self.scale
+        } else {
+            0
+        }
+    }
+}
+
+/// An icon file found in a theme directory
+#[derive(Debug, Clone)]
+pub struct IconCandidate {
+    pub icon_name: String,
+    pub directory: String,
+    pub file: PathBuf,
+    pub matches_size: bool,
+    pub size_distance: i32,
+}
+
+/// The icons found in a theme of the inheritance chain. Themes which are not installed have no `index.theme`
+#[derive(Debug, Clone)]
+pub struct ThemeVisit {
+    pub theme: String,
+    pub index: Option<PathBuf>,
+    pub directories: usize,
+    pub candidates: Vec<IconCandidate>,
+}
+
+/// The trace of an icon lookup
+#[derive(Debug, Clone)]
+pub struct ThemeLookup {
+    pub visits: Vec<ThemeVisit>,
+    /// Icon files directly in the base directories like `/usr/share/pixmaps`, which are used if no theme has the icon
+    pub unthemed: Vec<PathBuf>,
+    pub icon: Option<PathBuf>,
+}
+
+/// Returns the icon theme configured in `gtk-3.0/settings.ini`
+pub fn get_configured_theme() -> Option<String> {
+    std::iter::once(xdg::get_config_home()).chain(xdg::get_config_dirs()).find_map(|dir| {
+        KeyFile::load(&dir.join("gtk-3.0/settings.ini")).ok()?.get_string("Settings", "gtk-icon-theme-name")
+    })
+}
+
+/// The theme followed by the themes it inherits from, depth first, and `hicolor`
+pub fn get_theme_chain(base_dirs: &[PathBuf], theme: &str) -> Vec<String> {
+    fn add(base_dirs: &[PathBuf], theme: &str, chain: &mut Vec<String>) {
+        if chain.iter().any(|known| known == theme) {
+            return;
+        }
+        chain.push(theme.to_string());
+        let inherits = find_index(base_dirs, theme)
+            .and_then(|index| KeyFile::load(&index).ok())
+            .and_then(|index| index.get_string("Icon Theme", "Inherits"))
+            .unwrap_or_default();
+        for parent in inherits.split(',').map(|parent| parent.trim()).filter(|parent| !parent.is_empty()) {
+            add(base_dirs, parent, chain);
+        }
+    }
+    let mut chain = Vec::new();
+    add(base_dirs, theme, &mut chain);
+    add(base_dirs, "hicolor", &mut chain);
+    chain
+}
+
+/// Looks up the first icon name which is found, theme by theme like GTK does: each theme of the chain is searched
+/// for all names before the next theme. In a theme a directory matching the size wins, otherwise the one
+/// with the smallest size distance
+pub fn lookup_icon(base_dirs: &[PathBuf], theme: &str, names: &[String], size: i32, scale: i32, extensions: &[&str]) -> ThemeLookup {
+    let mut visits = Vec::new();
+    for theme in get_theme_chain(base_dirs, theme) {
+        let index = find_index(base_dirs, &theme);
+        let directories = index.as_deref().and_then(|index| KeyFile::load(index).ok()).map(|index| load_directories(&index)).unwrap_or_default();
+        let theme_dirs: Vec<PathBuf> = base_dirs.iter().map(|dir| dir.join(&theme)).filter(|dir| dir.is_dir()).collect();
+        let mut visit = ThemeVisit { theme, index, directories: directories.len(), candidates: Vec::new() };
+        for name in names {
+            let candidates: Vec<IconCandidate> = directories
+                .iter()
+                .flat_map(|directory| theme_dirs.iter().map(move |theme_dir| (directory, theme_dir)))
+                .flat_map(|(directory, theme_dir)| extensions.iter().map(move |ext| (directory, theme_dir.join(&directory.name).join(format!("{}.{}", name, ext)))))
+                .filter(|(_, file)| file.is_file())
+                .map(|(directory, file)| IconCandidate {
+                    icon_name: name.clone(),
+                    directory: directory.name.clone(),
+                    file,
+                    matches_size: directory.matches_size(size, scale),
+                    size_distance: directory.size_distance(size, scale),
+                })
+                .collect();
+            let icon = candidates
+                .iter()
+                .find(|candidate| candidate.matches_size)
+                .or_else(|| candidates.iter().min_by_key(|candidate| candidate.size_distance))
+                .map(|candidate| candidate.file.clone());
+            visit.candidates.extend(candidates);
+            if icon.is_some() {
+                visits.push(visit);
+                return ThemeLookup { visits, unthemed: Vec::new(), icon };
+            }
+        }
+        visits.push(visit);
+    }
+    let unthemed: Vec<PathBuf> = names
+        .iter()
+        .flat_map(|name| base_dirs.iter().flat_map(move |dir| extensions.iter().map(move |ext| dir.join(format!("{}.{}", name, ext)))))
+        .filter(|file| file.is_file())
+        .collect();
+    let icon = unthemed.first().cloned();
+    ThemeLookup { visits, unthemed, icon }
+}
+
+fn find_index(base_dirs: &[PathBuf], theme: &str) -> Option<PathBuf> {
+    base_dirs.iter().map(|dir| dir.join(theme).join("index.theme")).find(|index| index.is_file())
+}
+
+fn load_directories(index: &KeyFile) -> Vec<ThemeDirectory> {
+    let mut names: Vec<String> = Vec::new();
+    for key in ["Directories", "ScaledDirectories"] {
+        let list = index.get_string("Icon Theme", key).unwrap_or_default();
+        for name in list.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+            if !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let number = |key: &str| index.get(&name, key).and_then(|value| value.trim().parse::<i32>().ok());
+            let size = number("Size")?;
+            let directory_type = match index.get(&name, "Type") {
+                Some("Fixed") => DirectoryType::Fixed,
+                Some("Scalable") => DirectoryType::Scalable,
+                _ => DirectoryType::Threshold,
+            };
+            Some(ThemeDirectory {
+                size,
+                scale: number("Scale").unwrap_or(1),
+                directory_type,
+                min_size: number("MinSize").unwrap_or(size),
+                max_size: number("MaxSize").unwrap_or(size),
+                threshold: number("Threshold").unwrap_or(2),
+                name,
+            })
+        })
+        .collect()
+}
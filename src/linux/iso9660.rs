@@ -0,0 +1,186 @@
+use std::{fs::File, io, os::unix::fs::FileExt};
+
+use super::appimage::{Image, NodeKind};
+
+const SECTOR_SIZE: u64 = 2048;
+const PRIMARY_VOLUME_DESCRIPTOR: u64 = 16;
+/// Icons are small, larger files are not read
+const MAX_FILE_SIZE: u32 = 16 * 1024 * 1024;
+const FLAG_DIRECTORY: u8 = 0x02;
+const MAX_CONTINUATIONS: usize = 16;
+const SL_CONTINUE: u8 = 0x01;
+const SL_CURRENT: u8 = 0x02;
+const SL_PARENT: u8 = 0x04;
+const SL_ROOT: u8 = 0x08;
+
+/// A read only ISO 9660 file system, the Rock Ridge extensions are used for file names and symbolic links
+pub struct Iso9660 {
+    file: File,
+    root: Node,
+}
+
+#[derive(Clone)]
+pub struct Node {
+    extent: u32,
+    size: u32,
+    kind: NodeKind,
+}
+
+impl Iso9660 {
+    pub fn open(file: File) -> io::Result<Iso9660> {
+        let mut descriptor = [0; SECTOR_SIZE as usize];
+        file.read_exact_at(&mut descriptor, PRIMARY_VOLUME_DESCRIPTOR * SECTOR_SIZE)?;
+        if descriptor[0] != 1 || &descriptor[1..6] != b"CD001" {
+            return Err(invalid_data("No ISO 9660 file system"));
+        }
+        let (_, root) = parse_record(&descriptor[156..190]).ok_or_else(|| invalid_data("Invalid root directory"))?;
+        Ok(Iso9660 { file, root })
+    }
+
+    fn read_extent(&self, extent: u32, size: u32) -> io::Result<Vec<u8>> {
+        if size > MAX_FILE_SIZE {
+            return Err(invalid_data("File too large"));
+        }
+        let mut data = vec![0; size as usize];
+        self.file.read_exact_at(&mut data, extent as u64 * SECTOR_SIZE)?;
+        Ok(data)
+    }
+
+    /// Collects the System Use entries of a record, following continuation areas (`CE`)
+    fn read_system_use(&self, system_use: &[u8]) -> io::Result<Vec<([u8; 2], Vec<u8>)>> {
+        let mut entries = Vec::new();
+        let mut area = system_use.to_vec();
+        for _ in 0..MAX_CONTINUATIONS {
+            let mut continuation = None;
+            let mut position = 0;
+            while position + 4 <= area.len() {
+                let len = area[position + 2] as usize;
+                if len < 4 || position + len > area.len() {
+                    break;
+                }
+                let signature = [area[position], area[position + 1]];
+                let data = &area[position + 4..position + len];
+                match &signature {
+                    b"ST" => break,
+                    b"CE" if data.len() >= 20 => continuation = Some((le_u32(&data[0..]), le_u32(&data[8..]), le_u32(&data[16..]))),
+                    _ => entries.push((signature, data.to_vec())),
+                }
+                position += len;
+            }
+            let Some((block, offset, len)) = continuation else {
+                break;
+            };
+            area = vec![0; len.min(SECTOR_SIZE as u32) as usize];
+            self.file.read_exact_at(&mut area, block as u64 * SECTOR_SIZE + offset as u64)?;
+        }
+        Ok(entries)
+    }
+}
+
+impl Image for Iso9660 {
+    type Node = Node;
+
+    fn root(&self) -> io::Result<Node> {
+        Ok(self.root.clone())
+    }
+
+    fn kind(node: &Node) -> &NodeKind {
+        &node.kind
+    }
+
+    fn read_dir(&self, dir: &Node) -> io::Result<Vec<(String, Node)>> {
+        let data = self.read_extent(dir.extent, dir.size)?;
+        let mut entries = Vec::new();
+        let mut position = 0;
+        while position < data.len() {
+            let len = data[position] as usize;
+            if len == 0 {
+                // records do not span sectors, the rest of the sector is padding
+                position = (position / SECTOR_SIZE as usize + 1) * SECTOR_SIZE as usize;
+                continue;
+            }
+            let Some(record) = data.get(position..position + len) else {
+                break;
+            };
+            position += len;
+            let Some((identifier, mut node)) = parse_record(record) else {
+                continue;
+            };
+            // the entries for . and ..
+            if identifier == [0] || identifier == [1] {
+                continue;
+            }
+            let identifier_len = identifier.len();
+            let system_use_start = 33 + identifier_len + (identifier_len + 1) % 2;
+            let system_use = self.read_system_use(record.get(system_use_start..).unwrap_or_default())?;
+
+            let mut name = Vec::new();
+            let mut link_target = None;
+            let mut continued = false;
+            for (signature, data) in system_use {
+                match &signature {
+                    b"NM" if !data.is_empty() => name.extend_from_slice(&data[1..]),
+                    b"SL" if !data.is_empty() => {
+                        append_symlink_components(link_target.get_or_insert_with(String::new), &mut continued, &data[1..]);
+                    }
+                    _ => {}
+                }
+            }
+            let name = if name.is_empty() {
+                // plain ISO 9660 names like FILE.PNG;1
+                let name = String::from_utf8_lossy(identifier).to_string();
+                let name = name.split(';').next().unwrap_or_default();
+                name.strip_suffix('.').unwrap_or(name).to_string()
+            } else {
+                String::from_utf8_lossy(&name).to_string()
+            };
+            if let Some(target) = link_target {
+                node.kind = NodeKind::Symlink(target);
+            }
+            entries.push((name, node));
+        }
+        Ok(entries)
+    }
+
+    fn read_file(&self, file: &Node) -> io::Result<Vec<u8>> {
+        self.read_extent(file.extent, file.size)
+    }
+}
+
+/// Parses a directory record, returns the file identifier and the node
+fn parse_record(record: &[u8]) -> Option<(&[u8], Node)> {
+    let identifier_len = *record.get(32)? as usize;
+    let identifier = record.get(33..33 + identifier_len)?;
+    let kind = if record[25] & FLAG_DIRECTORY != 0 { NodeKind::Dir } else { NodeKind::File };
+    Some((identifier, Node { extent: le_u32(&record[2..]), size: le_u32(&record[10..]), kind }))
+}
+
+/// Appends the component records of a Rock Ridge `SL` entry to the link target,
+/// a component may be continued in the next record
+fn append_symlink_components(target: &mut String, continued: &mut bool, mut components: &[u8]) {
+    while components.len() >= 2 {
+        let flags = components[0];
+        let len = components[1] as usize;
+        let Some(content) = components.get(2..2 + len) else {
+            return;
+        };
+        if !*continued && (flags & SL_ROOT != 0 || !target.is_empty() && !target.ends_with('/')) {
+            target.push('/');
+        }
+        match flags & (SL_CURRENT | SL_PARENT) {
+            SL_CURRENT => target.push('.'),
+            SL_PARENT => target.push_str(".."),
+            _ => target.push_str(&String::from_utf8_lossy(content)),
+        }
+        *continued = flags & SL_CONTINUE != 0;
+        components = &components[2 + len..];
+    }
+}
+
+fn le_u32(data: &[u8]) -> u32 {
+    u32::from_le_bytes(data[..4].try_into().unwrap_or_default())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -0,0 +1,207 @@
+use std::{env, fs, path::Path};
+
+use crate::Error;
+
+/// A file in the freedesktop key file format, like `.desktop` or `.directory` files
+#[derive(Clone)]
+pub struct KeyFile {
+    groups: Vec<Group>,
+}
+
+#[derive(Clone)]
+struct Group {
+    name: String,
+    entries: Vec<(String, String)>,
+}
+
+impl KeyFile {
+    pub fn load(path: &Path) -> Result<KeyFile, Error> {
+        Ok(KeyFile::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(text: &str) -> KeyFile {
+        let mut groups: Vec<Group> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                groups.push(Group { name: line[1..line.len() - 1].to_string(), entries: Vec::new() });
+            } else if let (Some(group), Some((key, value))) = (groups.last_mut(), line.split_once('=')) {
+                group.entries.push((key.trim_end().to_string(), value.trim_start().to_string()));
+            }
+        }
+        KeyFile { groups }
+    }
+
+    /// Returns the raw value of a key, the first group with that name wins
+    pub fn get(&self, group: &str, key: &str) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|g| g.name == group)?
+            .entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the value of a key with escape sequences like `\s` or `\n` resolved
+    pub fn get_string(&self, group: &str, key: &str) -> Option<String> {
+        self.get(group, key).map(unescape)
+    }
+
+    /// Returns the value of a key translated to the language of the current locale, like `Name[de]`
+    pub fn get_locale_string(&self, group: &str, key: &str) -> Option<String> {
+        self.get_locale_string_for(group, key, &get_locale())
+    }
+
+    /// Returns the value of a key translated to the language of `locale`, like `de_DE.UTF-8`
+    pub fn get_locale_string_for(&self, group: &str, key: &str, locale: &str) -> Option<String> {
+        get_locale_variants(locale)
+            .iter()
+            .find_map(|locale| self.get(group, &format!("{}[{}]", key, locale)))
+            .or_else(|| self.get(group, key))
+            .map(unescape)
+    }
+
+    /// Returns the items of a list separated by `;`
+    pub fn get_list(&self, group: &str, key: &str) -> Vec<String> {
+        let Some(value) = self.get(group, key) else {
+            return Vec::new();
+        };
+        let mut items = Vec::new();
+        let mut item = String::new();
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                ';' => items.push(std::mem::take(&mut item)),
+                '\\' => {
+                    item.push(c);
+                    item.extend(chars.next());
+                }
+                _ => item.push(c),
+            }
+        }
+        items.push(item);
+        items.iter().filter(|item| !item.is_empty()).map(|item| unescape(item)).collect()
+    }
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn get_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_default()
+}
+
+/// The locale `lang_COUNTRY.ENCODING@MODIFIER` is matched as `lang_COUNTRY@MODIFIER`, `lang_COUNTRY`, `lang@MODIFIER` and `lang`
+fn get_locale_variants(locale: &str) -> Vec<String> {
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or_default();
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return Vec::new();
+    }
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+    let mut variants = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        variants.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        variants.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        variants.push(format!("{}@{}", lang, modifier));
+    }
+    variants.push(lang.to_string());
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESKTOP_FILE: &str = r"# comment
+[Desktop Entry]
+Name=Files
+Name[de]=Dateien
+Name[de_CH]=Dateie
+Name[sr@latin]=Datoteke
+Comment = Access\sand organize\tfiles\nquickly\\
+Keywords=folder;manager\;explorer;C:\\;;disk;
+Empty=
+
+[Desktop Entry]
+Icon=shadowed
+
+[Desktop Action new-window]
+Name=New Window
+";
+
+    #[test]
+    fn groups() {
+        let key_file = KeyFile::parse(DESKTOP_FILE);
+        assert_eq!(key_file.get("Desktop Entry", "Name"), Some("Files"));
+        assert_eq!(key_file.get("Desktop Action new-window", "Name"), Some("New Window"));
+        // the first group with that name wins
+        assert_eq!(key_file.get("Desktop Entry", "Icon"), None);
+        assert_eq!(key_file.get("Desktop Entry", "Empty"), Some(""));
+        assert_eq!(key_file.get("Missing", "Name"), None);
+        // keys before the first group and comments are ignored
+        assert_eq!(KeyFile::parse("Name=Files\n#[Desktop Entry]\n").get("Desktop Entry", "Name"), None);
+    }
+
+    #[test]
+    fn escapes() {
+        let key_file = KeyFile::parse(DESKTOP_FILE);
+        assert_eq!(key_file.get_string("Desktop Entry", "Comment").unwrap(), "Access and organize\tfiles\nquickly\\");
+    }
+
+    #[test]
+    fn lists() {
+        let key_file = KeyFile::parse(DESKTOP_FILE);
+        // escaped separators, escaped backslashes and empty items
+        assert_eq!(key_file.get_list("Desktop Entry", "Keywords"), ["folder", "manager;explorer", "C:\\", "disk"]);
+        assert!(key_file.get_list("Desktop Entry", "Empty").is_empty());
+        assert!(key_file.get_list("Desktop Entry", "Missing").is_empty());
+    }
+
+    #[test]
+    fn localized_keys() {
+        let key_file = KeyFile::parse(DESKTOP_FILE);
+        let name = |locale: &str| key_file.get_locale_string_for("Desktop Entry", "Name", locale).unwrap();
+        assert_eq!(name("de_CH.UTF-8"), "Dateie");
+        assert_eq!(name("de_AT.UTF-8@euro"), "Dateien");
+        assert_eq!(name("de"), "Dateien");
+        assert_eq!(name("sr_RS@latin"), "Datoteke");
+        assert_eq!(name("sr_RS"), "Files");
+        assert_eq!(name("C"), "Files");
+        assert_eq!(name(""), "Files");
+    }
+}
@@ -0,0 +1,139 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use super::{desktop_entry::DesktopEntry, key_file::KeyFile, xdg};
+
+const DEFAULT_APPLICATIONS: &str = "Default Applications";
+const ADDED_ASSOCIATIONS: &str = "Added Associations";
+const REMOVED_ASSOCIATIONS: &str = "Removed Associations";
+
+/// An application which is registered for a MIME type
+#[derive(Debug, Clone)]
+pub struct AppInfo {
+    /// The desktop ID like `org.gnome.gedit.desktop`
+    pub desktop_id: String,
+    /// The translated display name
+    pub name: String,
+    /// The path to the icon, if the application has one
+    pub icon: Option<String>,
+}
+
+/// The applications which are registered for a MIME type
+#[derive(Debug, Clone)]
+pub struct MimeApps {
+    /// The application which opens files of this MIME type by default
+    pub default: Option<AppInfo>,
+    /// All registered applications, beginning with the default one
+    pub apps: Vec<AppInfo>,
+}
+
+/// The desktop IDs of the applications which handle a MIME type
+pub struct Associations {
+    pub default: Option<String>,
+    /// All registered applications, beginning with the default one
+    pub registered: Vec<String>,
+}
+
+/// Evaluates the `mimeapps.list` files and the `mimeinfo.cache` files of the `applications` directories
+/// as specified in the freedesktop "Association between MIME types and applications" specification
+pub fn get_associations(mime_type: &str) -> Associations {
+    get_associations_in(mime_type, &get_mimeapps_lists(), &xdg::get_applications_dirs())
+}
+
+/// Like [`get_associations`] with the `mimeapps.list` files in order of precedence and the `applications` directories
+pub fn get_associations_in(mime_type: &str, mimeapps_lists: &[PathBuf], applications_dirs: &[PathBuf]) -> Associations {
+    let is_installed = |desktop_id: &str| DesktopEntry::find(desktop_id, applications_dirs).is_some();
+
+    let mut default = None;
+    let mut registered: Vec<String> = Vec::new();
+    // associations removed in a file only affect the files with lower precedence
+    let mut removed = HashSet::new();
+    for key_file in mimeapps_lists.iter().filter_map(|file| KeyFile::load(file).ok()) {
+        if default.is_none() {
+            default = key_file
+                .get_list(DEFAULT_APPLICATIONS, mime_type)
+                .into_iter()
+                .find(|id| !removed.contains(id) && is_installed(id));
+        }
+        for id in key_file.get_list(ADDED_ASSOCIATIONS, mime_type) {
+            if !removed.contains(&id) && !registered.contains(&id) && is_installed(&id) {
+                registered.push(id);
+            }
+        }
+        removed.extend(key_file.get_list(REMOVED_ASSOCIATIONS, mime_type));
+    }
+    for key_file in applications_dirs.iter().filter_map(|dir| KeyFile::load(&dir.join("mimeinfo.cache")).ok()) {
+        for id in key_file.get_list("MIME Cache", mime_type) {
+            if !removed.contains(&id) && !registered.contains(&id) && is_installed(&id) {
+                registered.push(id);
+            }
+        }
+    }
+
+    let default = default.or_else(|| registered.first().cloned());
+    if let Some(default) = &default {
+        registered.retain(|id| id != default);
+        registered.insert(0, default.clone());
+    }
+    Associations { default, registered }
+}
+
+/// The `mimeapps.list` files in order of precedence, desktop specific ones like `gnome-mimeapps.list` first in each directory
+fn get_mimeapps_lists() -> Vec<PathBuf> {
+    let desktops = xdg::get_current_desktops();
+    std::iter::once(xdg::get_config_home())
+        .chain(xdg::get_config_dirs())
+        .chain(xdg::get_applications_dirs())
+        .flat_map(|dir| {
+            desktops
+                .iter()
+                .map(|desktop| dir.join(format!("{}-mimeapps.list", desktop)))
+                .chain(std::iter::once(dir.join("mimeapps.list")))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIME_APPS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mime_apps");
+
+    /// The default application and the registered ones of a MIME type
+    fn associations(mime_type: &str) -> (Option<String>, Vec<String>) {
+        let dir = PathBuf::from(MIME_APPS);
+        let lists = [dir.join("config/mimeapps.list"), dir.join("missing/mimeapps.list"), dir.join("system/mimeapps.list")];
+        let associations = get_associations_in(mime_type, &lists, &[dir.join("applications")]);
+        (associations.default, associations.registered)
+    }
+
+    #[test]
+    fn default_application() {
+        // the first installed default of the file with the highest precedence wins
+        let (default, registered) = associations("text/plain");
+        assert_eq!(default.unwrap(), "org.example.Editor.desktop");
+        assert_eq!(registered[0], "org.example.Editor.desktop");
+    }
+
+    #[test]
+    fn registered_applications() {
+        let (_, registered) = associations("text/plain");
+        assert_eq!(registered, ["org.example.Editor.desktop", "org.example.Viewer.desktop", "org.example.Third.desktop", "org.example.Fourth.desktop"]);
+    }
+
+    #[test]
+    fn removed_associations() {
+        // removed associations only affect the files with lower precedence and the mimeinfo.cache files,
+        // so the default of the system file is not used
+        let (default, registered) = associations("image/png");
+        assert_eq!(default.unwrap(), "org.example.Viewer.desktop");
+        assert_eq!(registered, ["org.example.Viewer.desktop"]);
+    }
+
+    #[test]
+    fn unknown_mime_type() {
+        let (default, registered) = associations("application/x-unknown");
+        assert_eq!(default, None);
+        assert!(registered.is_empty());
+    }
+}
@@ -1 +1,20 @@
-    pub mod request;
\ No newline at end of file
+#[cfg(feature = "backend-gtk")]
+pub mod request;
+pub mod app_id;
+pub(crate) mod appimage;
+pub mod desktop_entry;
+pub mod explain;
+#[cfg(feature = "backend-gtk")]
+pub(crate) mod directory;
+pub(crate) mod gvfs_metadata;
+pub mod icon_theme;
+pub(crate) mod iso9660;
+pub(crate) mod key_file;
+pub mod mime_apps;
+pub mod mountinfo;
+pub(crate) mod process;
+pub mod shared_mime;
+pub(crate) mod squashfs;
+pub mod trash;
+pub mod volume;
+pub mod xdg;
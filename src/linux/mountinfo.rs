@@ -0,0 +1,133 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::Error;
+
+pub const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// An entry of `/proc/self/mountinfo`
+pub struct Mount {
+    pub major: u32,
+    pub minor: u32,
+    pub mount_point: PathBuf,
+    pub read_only: bool,
+    pub fs_type: String,
+    pub source: String,
+}
+
+pub fn read_mounts(mountinfo: &Path) -> Result<Vec<Mount>, Error> {
+    Ok(parse(&fs::read_to_string(mountinfo)?))
+}
+
+/// Parses the content of a mountinfo file, lines which cannot be parsed are skipped
+pub fn parse(mountinfo: &str) -> Vec<Mount> {
+    mountinfo.lines().filter_map(parse_line).collect()
+}
+
+/// Returns the mount containing `path`, for stacked mounts the topmost one
+pub fn find_mount(mountinfo: &Path, path: &Path) -> Result<Option<Mount>, Error> {
+    Ok(read_mounts(mountinfo)?
+        .into_iter()
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        .fold(None, |best: Option<Mount>, mount| match best {
+            Some(best) if best.mount_point.as_os_str().len() > mount.mount_point.as_os_str().len() => Some(best),
+            _ => Some(mount),
+        }))
+}
+
+// 36 35 98:0 /mnt1 /mnt/parent rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+fn parse_line(line: &str) -> Option<Mount> {
+    let mut fields = line.split(' ');
+    let (major, minor) = fields.nth(2)?.split_once(':')?;
+    let mount_point = fields.nth(1)?;
+    let read_only = fields.next()?.split(',').any(|option| option == "ro");
+    // optional fields are terminated by a single hyphen
+    let mut fields = fields.skip_while(|field| *field != "-").skip(1);
+    Some(Mount {
+        major: major.parse().ok()?,
+        minor: minor.parse().ok()?,
+        mount_point: PathBuf::from(unescape(mount_point)),
+        read_only,
+        fs_type: fields.next()?.to_string(),
+        source: unescape(fields.next()?),
+    })
+}
+
+/// Spaces, tabs, newlines and backslashes are escaped as octal numbers like `\040`
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).filter(|_| bytes[i] == b'\\').and_then(|digits| {
+            std::str::from_utf8(digits).ok().and_then(|digits| u8::from_str_radix(digits, 8).ok())
+        });
+        match octal {
+            Some(byte) => {
+                result.push(byte);
+                i += 4;
+            }
+            None => {
+                result.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let mounts = parse("36 35 98:0 /mnt1 /mnt/parent rw,noatime master:1 - ext3 /dev/root rw,errors=continue\n");
+        assert_eq!(mounts.len(), 1);
+        let mount = &mounts[0];
+        assert_eq!((mount.major, mount.minor), (98, 0));
+        assert_eq!(mount.mount_point, Path::new("/mnt/parent"));
+        assert!(!mount.read_only);
+        assert_eq!(mount.fs_type, "ext3");
+        assert_eq!(mount.source, "/dev/root");
+        assert!(parse("37 35 11:0 / /media/cdrom ro,nosuid - iso9660 /dev/sr0 ro\n")[0].read_only);
+    }
+
+    #[test]
+    fn octal_escapes() {
+        let mounts = parse("104 22 8:33 / /media/user/My\\040Backup\\011\\134x\\012 rw - ext4 /dev/disk\\040a rw\n");
+        assert_eq!(mounts[0].mount_point, Path::new("/media/user/My Backup\t\\x\n"));
+        assert_eq!(mounts[0].source, "/dev/disk a");
+        // a backslash without three octal digits is kept
+        let mounts = parse("104 22 8:33 / /mnt/a\\b\\09\\1 rw - ext4 /dev/sdc1 rw\n");
+        assert_eq!(mounts[0].mount_point, Path::new("/mnt/a\\b\\09\\1"));
+    }
+
+    #[test]
+    fn optional_fields() {
+        let text = "22 1 259:2 / / rw,relatime - ext4 /dev/nvme0n1p2 rw\n\
+                    23 22 0:22 / /proc rw shared:12 - proc proc rw\n\
+                    24 22 0:60 / /mnt/bind rw shared:250 master:3 propagate_from:2 unbindable - tmpfs tmpfs rw\n";
+        let types: Vec<_> = parse(text).iter().map(|mount| (mount.mount_point.clone(), mount.fs_type.clone(), mount.source.clone())).collect();
+        assert_eq!(
+            types,
+            [
+                (Path::new("/").to_path_buf(), "ext4".to_string(), "/dev/nvme0n1p2".to_string()),
+                (Path::new("/proc").to_path_buf(), "proc".to_string(), "proc".to_string()),
+                (Path::new("/mnt/bind").to_path_buf(), "tmpfs".to_string(), "tmpfs".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_lines_are_skipped() {
+        let text = "\n\
+                    22 1 259:2 / / rw - ext4\n\
+                    22 1 259 / / rw - ext4 /dev/sda1 rw\n\
+                    22 1 a:2 / / rw - ext4 /dev/sda1 rw\n\
+                    22 1 259:2 / / rw ext4 /dev/sda1 rw\n\
+                    25 22 8:1 / /boot rw - vfat /dev/sda1 rw\n";
+        let mounts = parse(text);
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0].mount_point, Path::new("/boot"));
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{desktop_entry::DesktopEntry, xdg};
+
+const INTERPRETERS: &[&str] = &["python", "python2", "python3", "perl", "ruby", "node", "java", "mono", "gjs", "sh", "bash"];
+
+/// Finds the desktop entry of the application a process belongs to
+pub fn find_desktop_entry(pid: u32) -> Option<DesktopEntry> {
+    find_desktop_entry_in(Path::new(&format!("/proc/{}", pid)), &xdg::get_applications_dirs())
+}
+
+/// Like [`find_desktop_entry`] with the process directory `proc_dir` like `/proc/<pid>` and the applications directories
+pub fn find_desktop_entry_in(proc_dir: &Path, dirs: &[PathBuf]) -> Option<DesktopEntry> {
+    let environ = read_environ(proc_dir);
+    let get_env = |key: &str| {
+        environ
+            .iter()
+            .find_map(|(k, value)| (k == key).then_some(value.as_str()))
+            .filter(|value| !value.is_empty())
+    };
+
+    // child processes inherit the variable, so it only belongs to the process GIO has launched
+    let pid = proc_dir.file_name().and_then(|pid| pid.to_str());
+    get_env("GIO_LAUNCHED_DESKTOP_FILE")
+        .filter(|_| get_env("GIO_LAUNCHED_DESKTOP_FILE_PID").is_some_and(|launched_pid| Some(launched_pid) == pid))
+        .and_then(|file| DesktopEntry::load_file(Path::new(file)))
+        .or_else(|| get_env("FLATPAK_ID").and_then(|id| DesktopEntry::find(id, dirs)))
+        .or_else(|| {
+            // snaps export their desktop files as <snap>_<app>.desktop
+            get_env("SNAP_NAME").and_then(|snap| {
+                DesktopEntry::find(&format!("{}_{}", snap, snap), dirs).or_else(|| {
+                    DesktopEntry::all(dirs)
+                        .iter()
+                        .find(|entry| entry.desktop_id().starts_with(&format!("{}_", snap)))
+                        .cloned()
+                })
+            })
+        })
+        .or_else(|| {
+            get_app_ids_from_cgroup(proc_dir)
+                .iter()
+                .find_map(|app_id| DesktopEntry::find(app_id, dirs))
+        })
+        .or_else(|| find_by_binary(proc_dir, dirs))
+}
+
+fn read_environ(proc_dir: &Path) -> Vec<(String, String)> {
+    fs::read(proc_dir.join("environ"))
+        .unwrap_or_default()
+        .split(|&b| b == 0)
+        .filter_map(|var| {
+            let var = String::from_utf8_lossy(var);
+            var.split_once('=').map(|(key, value)| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// systemd puts applications into scopes or services named `app[-<launcher>]-<app id>-<random>.scope`
+/// or `app[-<launcher>]-<app id>[@<random>].service`, dashes in the app id are escaped as `\x2d`
+fn get_app_ids_from_cgroup(proc_dir: &Path) -> Vec<String> {
+    let cgroup = fs::read_to_string(proc_dir.join("cgroup")).unwrap_or_default();
+    let Some(unit) = cgroup
+        .lines()
+        .filter_map(|line| line.rsplit('/').next())
+        .find(|unit| unit.starts_with("app-"))
+    else {
+        return Vec::new();
+    };
+    let name = if let Some(scope) = unit.strip_suffix(".scope") {
+        scope.rsplit_once('-').map(|(name, _)| name).unwrap_or(scope)
+    } else if let Some(service) = unit.strip_suffix(".service") {
+        service.split('@').next().unwrap_or(service)
+    } else {
+        return Vec::new();
+    };
+    let name = name.strip_prefix("app-").unwrap_or_default();
+    let mut app_ids = vec![name.replace("\\x2d", "-")];
+    if let Some((_, app_id)) = name.split_once('-') {
+        app_ids.push(app_id.replace("\\x2d", "-"));
+    }
+    app_ids
+}
+
+/// Matches the executable and the command line against `TryExec`, `Exec`, `StartupWMClass` and the desktop ID
+fn find_by_binary(proc_dir: &Path, dirs: &[PathBuf]) -> Option<DesktopEntry> {
+    let exe = fs::read_link(proc_dir.join("exe"))
+        .ok()
+        .map(|exe| PathBuf::from(exe.to_string_lossy().trim_end_matches(" (deleted)")));
+    let cmdline = fs::read(proc_dir.join("cmdline")).unwrap_or_default();
+    let args: Vec<String> = cmdline
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).to_string())
+        .collect();
+
+    let mut binaries: Vec<String> = exe
+        .iter()
+        .chain(args.first().map(PathBuf::from).iter())
+        .map(|path| basename(&path.to_string_lossy()))
+        .collect();
+    // for scripts the script name is more meaningful than the interpreter
+    if binaries.iter().any(|binary| INTERPRETERS.contains(&binary.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))) {
+        if let Some(script) = args.iter().skip(1).find(|arg| !arg.starts_with('-')) {
+            binaries.insert(0, basename(script));
+        }
+    }
+    binaries.dedup();
+    if binaries.is_empty() {
+        return None;
+    }
+    let exe = exe.map(|exe| exe.to_string_lossy().to_string());
+
+    DesktopEntry::all(dirs)
+        .iter()
+        .filter_map(|entry| {
+            let programs = [entry.try_exec(), entry.exec_program()];
+            let rank = if programs.iter().flatten().any(|program| Some(program) == exe.as_ref()) {
+                0
+            } else if programs.iter().flatten().any(|program| binaries.contains(&basename(program))) {
+                1
+            } else if entry.startup_wm_class().map(|class| binaries.iter().any(|b| b.eq_ignore_ascii_case(&class))).unwrap_or(false) {
+                2
+            } else if binaries.iter().any(|b| entry.desktop_id().trim_end_matches(".desktop").eq_ignore_ascii_case(b)) {
+                3
+            } else {
+                return None;
+            };
+            Some((rank, entry))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, entry)| entry.clone())
+}
+
+fn basename(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APPLICATIONS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/applications");
+
+    /// Creates a process directory like `/proc/42` with the files `environ`, `cgroup` and `cmdline`, and returns the
+    /// desktop ID found for it
+    fn find(name: &str, environ: &[&str], cgroup: &str, cmdline: &[&str]) -> Option<String> {
+        let root = std::env::temp_dir().join(format!("systemicons-test-{}-process-{}", std::process::id(), name));
+        let proc_dir = root.join("42");
+        fs::create_dir_all(&proc_dir).unwrap();
+        let join = |values: &[&str]| values.iter().flat_map(|value| value.bytes().chain([0])).collect::<Vec<u8>>();
+        fs::write(proc_dir.join("environ"), join(environ)).unwrap();
+        fs::write(proc_dir.join("cgroup"), cgroup).unwrap();
+        fs::write(proc_dir.join("cmdline"), join(cmdline)).unwrap();
+        let desktop_id = find_desktop_entry_in(&proc_dir, &[PathBuf::from(APPLICATIONS)]).map(|entry| entry.desktop_id().to_string());
+        fs::remove_dir_all(&root).unwrap();
+        desktop_id
+    }
+
+    fn desktop_file(desktop_id: &str) -> String {
+        PathBuf::from(APPLICATIONS).join(desktop_id).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn gio_launched_desktop_file() {
+        let environ = format!("GIO_LAUNCHED_DESKTOP_FILE={}", desktop_file("firefox.desktop"));
+        assert_eq!(find("gio", &["HOME=/home/user", &environ, "GIO_LAUNCHED_DESKTOP_FILE_PID=42"], "", &["/usr/lib/firefox/firefox"]).unwrap(), "firefox.desktop");
+        // an empty variable is ignored
+        assert_eq!(find("gio-empty", &["GIO_LAUNCHED_DESKTOP_FILE=", "GIO_LAUNCHED_DESKTOP_FILE_PID=42"], "", &["nautilus"]).unwrap(), "org.gnome.Nautilus.desktop");
+    }
+
+    #[test]
+    fn gio_launched_desktop_file_of_parent() {
+        // a shell started from a terminal inherits the variables of the terminal
+        let environ = format!("GIO_LAUNCHED_DESKTOP_FILE={}", desktop_file("firefox.desktop"));
+        assert_eq!(find("gio-parent", &[&environ, "GIO_LAUNCHED_DESKTOP_FILE_PID=41"], "", &["nautilus"]).unwrap(), "org.gnome.Nautilus.desktop");
+        assert_eq!(find("gio-no-pid", &[&environ], "", &["nautilus"]).unwrap(), "org.gnome.Nautilus.desktop");
+    }
+
+    #[test]
+    fn flatpak_id() {
+        assert_eq!(find("flatpak-id", &["FLATPAK_ID=com.discordapp.Discord"], "", &["/app/bin/discord"]).unwrap(), "com.discordapp.Discord.desktop");
+    }
+
+    #[test]
+    fn snap_name() {
+        assert_eq!(find("snap-name", &["SNAP_NAME=spotify"], "", &["/snap/spotify/current/usr/share/spotify/spotify"]).unwrap(), "spotify_spotify.desktop");
+    }
+
+    #[test]
+    fn flatpak_cgroup() {
+        let cgroup = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-flatpak-com.discordapp.Discord-2467.scope\n";
+        assert_eq!(find("flatpak-cgroup", &[], cgroup, &["/app/bin/discord"]).unwrap(), "com.discordapp.Discord.desktop");
+    }
+
+    #[test]
+    fn launcher_cgroup() {
+        let cgroup = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-gnome-org.gnome.Nautilus-8195.scope\n";
+        assert_eq!(find("gnome-cgroup", &[], cgroup, &[]).unwrap(), "org.gnome.Nautilus.desktop");
+        // dashes of the app id are escaped
+        let cgroup = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-gnome\\x2dterminal@a1b2.service\n";
+        assert_eq!(find("service-cgroup", &[], cgroup, &[]).unwrap(), "gnome-terminal.desktop");
+    }
+
+    #[test]
+    fn exec_with_field_codes() {
+        // Exec=nautilus --new-window %U
+        assert_eq!(find("exec", &[], "", &["nautilus", "--new-window", "/home/user"]).unwrap(), "org.gnome.Nautilus.desktop");
+        // Exec=env BAMF_DESKTOP_FILE_HINT=... /snap/bin/spotify %U
+        assert_eq!(find("exec-env", &[], "", &["/snap/bin/spotify", "--uri=spotify:track"]).unwrap(), "spotify_spotify.desktop");
+    }
+
+    #[test]
+    fn script_of_interpreter() {
+        // Exec=/opt/idea/bin/idea.sh %f
+        assert_eq!(find("script", &[], "", &["/bin/bash", "-e", "/opt/idea/bin/idea.sh", "Main.java"]).unwrap(), "jetbrains-idea.desktop");
+    }
+
+    #[test]
+    fn unknown_process() {
+        let cgroup = "0::/user.slice/user-1000.slice/session-2.scope\n";
+        assert_eq!(find("unknown", &["HOME=/home/user"], cgroup, &["/usr/bin/unknown-daemon"]), None);
+    }
+}
@@ -1,52 +1,498 @@
-use std::{ffi::{CStr, CString, c_void}, fs::{self, File}, io::Read};
+use std::{ffi::{CStr, CString, c_char, c_void}, fs::{self, File}, io::Read, os::unix::{ffi::OsStrExt, fs::MetadataExt}, path::{Path, PathBuf}, ptr, sync::atomic::{AtomicPtr, Ordering}, time::SystemTime};
+use gdk_pixbuf_sys::{gdk_pixbuf_loader_close, gdk_pixbuf_loader_get_pixbuf, gdk_pixbuf_loader_new, gdk_pixbuf_loader_set_size, gdk_pixbuf_loader_write, gdk_pixbuf_save_to_bufferv};
 use gio_sys::GThemedIcon;
-use glib::gobject_ffi::{g_object_unref, GObject};
-use glib_sys::g_free;
-use gtk_sys::{GTK_ICON_LOOKUP_NO_SVG, GtkIconTheme, gtk_icon_info_get_filename, gtk_icon_theme_choose_icon, gtk_icon_theme_get_default};
+use glib::gobject_ffi::{g_object_get, g_object_unref, GObject};
+use glib_sys::{g_error_free, g_free, GError};
+use gtk_sys::{GTK_ICON_LOOKUP_FORCE_SVG, GTK_ICON_LOOKUP_NO_SVG, GtkIconLookupFlags, GtkIconTheme, gtk_icon_info_get_filename, gtk_icon_theme_append_search_path, gtk_icon_theme_choose_icon, gtk_icon_theme_get_default, gtk_settings_get_default};
 
-use crate::{Error, InnerError};
+use crate::{hash::fnv1a, pe, shortcut::{self, Shortcut}, Error};
 
-static mut DEFAULT_THEME: Option<*mut GtkIconTheme> = None;
+use super::{app_id, appimage, desktop_entry::DesktopEntry, directory::{self, CustomIcon}, mime_apps::{self, AppInfo, MimeApps}, mountinfo::MOUNTINFO, process, trash, volume::{self, UDEV_DATA}, xdg};
+
+static DEFAULT_THEME: AtomicPtr<GtkIconTheme> = AtomicPtr::new(ptr::null_mut());
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// The number of icons kept in each cache directory, the least recently used ones are removed
+const MAX_CACHED_ICONS: usize = 1000;
 
 pub fn get_icon(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
     let filename = get_icon_as_file(ext, size)?;
-    let mut f = File::open(&filename)?;
-    let metadata = fs::metadata(&filename)?;
-    let mut buffer = vec![0; metadata.len() as usize];
-    f.read(&mut buffer)?;    
-    Ok(buffer)
+    Ok(fs::read(filename)?)
 }
 
+#[tracing::instrument(level = "debug")]
 pub fn get_icon_as_file(ext: &str, size: i32) -> Result<String, Error> {
-    let result: String;
+    get_extension_icon_as_file(ext, size, GTK_ICON_LOOKUP_NO_SVG)
+}
+
+/// The scalable icon of a file extension, if the icon theme has one
+pub fn get_svg_icon_as_file(ext: &str, size: i32) -> Result<String, Error> {
+    let filename = get_extension_icon_as_file(ext, size, GTK_ICON_LOOKUP_FORCE_SVG)?;
+    if !filename.ends_with(".svg") {
+        return Err(Error::not_found(ext, Some(size), "No scalable icon found"))
+    }
+    Ok(filename)
+}
+
+fn get_extension_icon_as_file(ext: &str, size: i32, flags: GtkIconLookupFlags) -> Result<String, Error> {
+    let filename = CString::new(ext).map_err(|_| Error::invalid_input(ext, "The file name contains a NUL character"))?;
     unsafe {
-        let filename = CString::new(ext).unwrap();
         let null: u8 = 0;
         let p_null = &null as *const u8;
         let nullsize: usize = 0;
         let mut res = 0;
         let p_res = &mut res as *mut i32;
         let p_res = gio_sys::g_content_type_guess(filename.as_ptr(), p_null, nullsize, p_res);
-        let icon = gio_sys::g_content_type_get_icon(p_res);
+        let result = choose_content_type_icon_with(p_res, size, flags);
         g_free(p_res as *mut c_void);
-        if DEFAULT_THEME.is_none() {
-            let theme = gtk_icon_theme_get_default();
-            if theme.is_null() {
-                println!("You have to initialize GTK!");
-                return Err(Error{ message: "You have to initialize GTK!".to_string(), inner_error:  InnerError::GtkInitError})
+        result
+    }
+}
+
+pub fn get_icon_for_mime_type(mime_type: &str, size: i32) -> Result<Vec<u8>, Error> {
+    let filename = get_icon_for_mime_type_as_file(mime_type, size)?;
+    Ok(fs::read(filename)?)
+}
+
+#[tracing::instrument(level = "debug")]
+pub fn get_icon_for_mime_type_as_file(mime_type: &str, size: i32) -> Result<String, Error> {
+    get_mime_type_icon_as_file(mime_type, size, GTK_ICON_LOOKUP_NO_SVG)
+}
+
+/// The scalable icon of a MIME type, if the icon theme has one
+pub fn get_svg_icon_for_mime_type_as_file(mime_type: &str, size: i32) -> Result<String, Error> {
+    let filename = get_mime_type_icon_as_file(mime_type, size, GTK_ICON_LOOKUP_FORCE_SVG)?;
+    if !filename.ends_with(".svg") {
+        return Err(Error::not_found(mime_type, Some(size), "No scalable icon found"))
+    }
+    Ok(filename)
+}
+
+fn get_mime_type_icon_as_file(mime_type: &str, size: i32, flags: GtkIconLookupFlags) -> Result<String, Error> {
+    let mime_type_c = CString::new(mime_type).map_err(|_| Error::invalid_input(mime_type, "The MIME type contains a NUL character"))?;
+    unsafe {
+        let content_type = gio_sys::g_content_type_from_mime_type(mime_type_c.as_ptr());
+        if content_type.is_null() {
+            return Err(Error::not_found(mime_type, Some(size), "Unknown MIME type"))
+        }
+        let result = choose_content_type_icon_with(content_type, size, flags);
+        g_free(content_type as *mut c_void);
+        result
+    }
+}
+
+/// Guesses the MIME type of a file from its name and its first bytes, like file managers do
+#[tracing::instrument(level = "debug")]
+pub fn get_mime_type(path: &str) -> Result<String, Error> {
+    if fs::metadata(path)?.is_dir() {
+        return Ok("inode/directory".to_string())
+    }
+    let mut data = Vec::with_capacity(4096);
+    fs::File::open(path)?.take(4096).read_to_end(&mut data)?;
+    let filename = CString::new(path).map_err(|_| Error::invalid_input(path, "The path contains a NUL character"))?;
+    unsafe {
+        let content_type = gio_sys::g_content_type_guess(filename.as_ptr(), data.as_ptr(), data.len(), ptr::null_mut());
+        let mime_type = gio_sys::g_content_type_get_mime_type(content_type);
+        g_free(content_type as *mut c_void);
+        if mime_type.is_null() {
+            return Err(Error::not_found(path, None, "No MIME type found"))
+        }
+        let result = CStr::from_ptr(mime_type).to_str().map(|mime_type| mime_type.to_string()).map_err(Error::from);
+        g_free(mime_type as *mut c_void);
+        result
+    }
+}
+
+/// Guesses the MIME type of a file from its name only, `None` if GIO is not certain
+#[cfg(feature = "backend-gtk")]
+pub fn guess_mime_type(name: &str) -> Option<String> {
+    let filename = CString::new(name).ok()?;
+    unsafe {
+        let mut uncertain = 0;
+        let content_type = gio_sys::g_content_type_guess(filename.as_ptr(), ptr::null(), 0, &mut uncertain);
+        let mime_type = gio_sys::g_content_type_get_mime_type(content_type);
+        g_free(content_type as *mut c_void);
+        if mime_type.is_null() {
+            return None
+        }
+        let result = CStr::from_ptr(mime_type).to_str().ok().filter(|_| uncertain == 0).map(|mime_type| mime_type.to_string());
+        g_free(mime_type as *mut c_void);
+        result
+    }
+}
+
+/// The name of the icon theme configured in GTK
+pub fn get_current_icon_theme() -> Result<String, Error> {
+    unsafe {
+        let settings = gtk_settings_get_default();
+        if settings.is_null() {
+            return Err(Error::backend_unavailable("GTK", "You have to initialize GTK!"))
+        }
+        let mut name: *mut c_char = ptr::null_mut();
+        g_object_get(settings as *mut GObject, c"gtk-icon-theme-name".as_ptr(), &mut name as *mut *mut c_char, ptr::null::<c_char>());
+        if name.is_null() {
+            return Err(Error::not_found("", None, "No icon theme configured"))
+        }
+        let result = CStr::from_ptr(name).to_str().map(|name| name.to_string()).map_err(Error::from);
+        g_free(name as *mut c_void);
+        result
+    }
+}
+
+unsafe fn choose_content_type_icon_with(content_type: *const c_char, size: i32, flags: GtkIconLookupFlags) -> Result<String, Error> {
+    let icon = gio_sys::g_content_type_get_icon(content_type);
+    let icon_names = gio_sys::g_themed_icon_get_names(icon as *mut GThemedIcon) as *mut *const c_char;
+    let result = choose_icon_with(icon_names, size, flags);
+    g_object_unref(icon as *mut GObject);
+    result
+}
+
+pub fn get_icon_for_path(path: &str, size: i32) -> Result<Vec<u8>, Error> {
+    if appimage::is_appimage(Path::new(path)) {
+        if let Ok(icon) = get_appimage_icon(Path::new(path), size) {
+            return Ok(icon)
+        }
+    } else if pe::has_pe_extension(Path::new(path)) {
+        if let Ok(icon) = pe::get_icon(Path::new(path), 0, size) {
+            return Ok(icon)
+        }
+    } else if shortcut::is_shortcut(Path::new(path)) {
+        if let Ok(icon) = shortcut::get_icon(Path::new(path), size) {
+            return Ok(icon.icon)
+        }
+    }
+    let filename = get_icon_for_path_as_file(path, size)?;
+    Ok(fs::read(filename)?)
+}
+
+#[tracing::instrument(level = "debug")]
+pub fn get_icon_for_path_as_file(path: &str, size: i32) -> Result<String, Error> {
+    if Path::new(path).is_dir() {
+        match directory::get_custom_icon(Path::new(path)) {
+            Some(CustomIcon::File(file)) => return Ok(file.to_string_lossy().to_string()),
+            Some(CustomIcon::Name(name)) => {
+                if let Ok(file) = get_icon_by_names(&[&name], size) {
+                    return Ok(file)
+                }
+            }
+            None => {}
+        }
+        // a trailing slash makes g_content_type_guess report inode/directory
+        get_icon_as_file(&format!("{}/", path.trim_end_matches('/')), size)
+    } else if appimage::is_appimage(Path::new(path)) {
+        get_cached_icon_as_file(Path::new(path), size, "appimage", get_appimage_icon).or_else(|_| get_icon_as_file(path, size))
+    } else if pe::has_pe_extension(Path::new(path)) {
+        get_cached_icon_as_file(Path::new(path), size, "pe", |path, size| pe::get_icon(path, 0, size))
+            .or_else(|_| get_icon_as_file(path, size))
+    } else if shortcut::is_shortcut(Path::new(path)) {
+        get_cached_icon_as_file(Path::new(path), size, "shortcut", |path, size| shortcut::get_icon(path, size).map(|icon| icon.icon))
+            .or_else(|_| get_shortcut_target_icon_as_file(Path::new(path), size))
+            .or_else(|_| get_icon_as_file(path, size))
+    } else {
+        get_icon_as_file(path, size)
+    }
+}
+
+/// The icon of the target's type, when the icon of a shortcut can not be extracted
+fn get_shortcut_target_icon_as_file(path: &Path, size: i32) -> Result<String, Error> {
+    let shortcut = Shortcut::open(path)?;
+    let target = shortcut
+        .target
+        .filter(|target| !target.contains("://"))
+        .ok_or_else(|| Error::not_found(&path.to_string_lossy(), Some(size), "The shortcut has no file as target"))?;
+    let name = target.trim_end_matches('\\').rsplit('\\').next().unwrap_or_default();
+    if shortcut.is_directory {
+        get_icon_as_file(&format!("{}/", name), size)
+    } else {
+        get_icon_as_file(name, size)
+    }
+}
+
+/// The icon embedded in an AppImage as PNG, other formats like SVG are rendered with gdk-pixbuf
+fn get_appimage_icon(path: &Path, size: i32) -> Result<Vec<u8>, Error> {
+    let icon = appimage::get_icon(path, size)?;
+    if icon.starts_with(PNG_SIGNATURE) {
+        Ok(icon)
+    } else {
+        render_png(&icon, size)
+    }
+}
+
+/// Icons embedded in files like AppImages or Windows executables are extracted to `$XDG_CACHE_HOME/systemicons/<kind>`,
+/// the file name is a hash of the path, the modification time and the length of the file. Each directory keeps the
+/// [`MAX_CACHED_ICONS`] most recently used icons
+fn get_cached_icon_as_file<F>(path: &Path, size: i32, kind: &str, extract: F) -> Result<String, Error>
+where
+    F: Fn(&Path, i32) -> Result<Vec<u8>, Error>,
+{
+    let metadata = fs::metadata(path)?;
+    let key = [path.as_os_str().as_bytes(), b"\0", &metadata.mtime().to_le_bytes(), &metadata.mtime_nsec().to_le_bytes(), &metadata.len().to_le_bytes()].concat();
+    let hash = fnv1a(&key);
+    let dir = xdg::get_cache_home().join("systemicons").join(kind);
+    let file = dir.join(format!("{:016x}-{}.png", hash, size));
+    if file.is_file() {
+        // the modification time tells which icons have been used recently
+        let _ = File::options().append(true).open(&file).and_then(|cached| cached.set_modified(SystemTime::now()));
+    } else {
+        let icon = extract(path, size)?;
+        fs::create_dir_all(&dir)?;
+        // concurrent requests must not see a partially written file
+        let temp_file = dir.join(format!(".{:016x}-{}.{}", hash, size, std::process::id()));
+        fs::write(&temp_file, icon)?;
+        fs::rename(&temp_file, &file)?;
+        prune_cache(&dir);
+    }
+    Ok(file.to_string_lossy().to_string())
+}
+
+/// Removes the least recently used files, including temporary files left behind, when a cache directory holds more
+/// than [`MAX_CACHED_ICONS`] files
+fn prune_cache(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    if files.len() <= MAX_CACHED_ICONS {
+        return;
+    }
+    files.sort();
+    for (_, file) in &files[..files.len() - MAX_CACHED_ICONS] {
+        let _ = fs::remove_file(file);
+    }
+}
+
+fn render_png(data: &[u8], size: i32) -> Result<Vec<u8>, Error> {
+    unsafe {
+        let loader = gdk_pixbuf_loader_new();
+        gdk_pixbuf_loader_set_size(loader, size, size);
+        let mut error = ptr::null_mut();
+        let loaded = gdk_pixbuf_loader_write(loader, data.as_ptr(), data.len(), &mut error) != 0;
+        let loaded = if loaded {
+            gdk_pixbuf_loader_close(loader, &mut error) != 0
+        } else {
+            gdk_pixbuf_loader_close(loader, ptr::null_mut());
+            false
+        };
+        let pixbuf = gdk_pixbuf_loader_get_pixbuf(loader);
+        let result = if !loaded || pixbuf.is_null() {
+            Err(Error::decode("", pixbuf_error(error, "Icon could not be loaded"), None))
+        } else {
+            let mut buffer = ptr::null_mut();
+            let mut buffer_size = 0;
+            if gdk_pixbuf_save_to_bufferv(pixbuf, &mut buffer, &mut buffer_size, c"png".as_ptr(), ptr::null_mut(), ptr::null_mut(), &mut error) != 0 {
+                let png = std::slice::from_raw_parts(buffer, buffer_size).to_vec();
+                g_free(buffer as *mut c_void);
+                Ok(png)
+            } else {
+                Err(Error::encode("", Some(size), pixbuf_error(error, "Icon could not be converted to PNG")))
             }
-            let theme = gtk_icon_theme_get_default();
-            DEFAULT_THEME = Some(theme);
+        };
+        g_object_unref(loader as *mut GObject);
+        result
+    }
+}
+
+/// The message of a failed gdk-pixbuf call
+unsafe fn pixbuf_error(error: *mut GError, message: &str) -> String {
+    if error.is_null() {
+        message.to_string()
+    } else {
+        let detail = CStr::from_ptr((*error).message).to_string_lossy().to_string();
+        g_error_free(error);
+        format!("{}: {}", message, detail)
+    }
+}
+
+pub fn get_trash_icon(size: i32) -> Result<Vec<u8>, Error> {
+    let filename = get_trash_icon_as_file(size)?;
+    Ok(fs::read(filename)?)
+}
+
+pub fn get_trash_icon_as_file(size: i32) -> Result<String, Error> {
+    get_icon_by_names(trash::get_trash_icon_names(), size)
+}
+
+pub fn get_volume_icon(mount_path: &str, size: i32) -> Result<Vec<u8>, Error> {
+    let filename = get_volume_icon_as_file(mount_path, size)?;
+    Ok(fs::read(filename)?)
+}
+
+pub fn get_volume_icon_as_file(mount_path: &str, size: i32) -> Result<String, Error> {
+    let names = volume::get_volume_icon_names(Path::new(mount_path), Path::new(MOUNTINFO), Path::new(UDEV_DATA))?;
+    get_icon_by_names(&names.iter().map(|name| name.as_str()).collect::<Vec<_>>(), size)
+}
+
+pub fn get_app_icon(desktop_id: &str, size: i32) -> Result<Vec<u8>, Error> {
+    let filename = get_app_icon_as_file(desktop_id, size)?;
+    Ok(fs::read(filename)?)
+}
+
+pub fn get_app_icon_as_file(desktop_id: &str, size: i32) -> Result<String, Error> {
+    let icon = DesktopEntry::find(desktop_id, &xdg::get_applications_dirs())
+        .and_then(|entry| entry.icon())
+        .ok_or_else(|| Error::not_found(desktop_id, Some(size), "No icon found for the application"))?;
+    get_icon_by_icon_key(&icon, size)
+}
+
+pub fn get_icon_for_uri(uri: &str, size: i32) -> Result<Vec<u8>, Error> {
+    let filename = get_icon_for_uri_as_file(uri, size)?;
+    Ok(fs::read(filename)?)
+}
+
+pub fn get_icon_for_uri_as_file(uri: &str, size: i32) -> Result<String, Error> {
+    let scheme = uri
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| scheme.starts_with(|c: char| c.is_ascii_alphabetic()))
+        .filter(|scheme| scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.'))
+        .ok_or_else(|| Error::invalid_input(uri, "Not a valid URI"))?
+        .to_lowercase();
+    if scheme == "file" {
+        let (path, _) = glib::filename_from_uri(uri).map_err(|err| Error::invalid_input(uri, err.to_string()))?;
+        return get_icon_for_path_as_file(&path.to_string_lossy(), size)
+    }
+    let handler_icon = mime_apps::get_associations(&format!("x-scheme-handler/{}", scheme))
+        .default
+        .and_then(|desktop_id| DesktopEntry::find(&desktop_id, &xdg::get_applications_dirs()))
+        .and_then(|entry| entry.icon())
+        .and_then(|icon| get_icon_by_icon_key(&icon, size).ok());
+    match handler_icon {
+        Some(icon) => Ok(icon),
+        None => get_icon_by_names(get_scheme_icon_names(&scheme), size),
+    }
+}
+
+/// Icons for URIs without a registered handler application
+fn get_scheme_icon_names(scheme: &str) -> &'static [&'static str] {
+    match scheme {
+        "smb" | "sftp" | "ssh" | "ftp" | "ftps" | "dav" | "davs" | "webdav" | "webdavs" | "nfs" | "afp" | "network" => &["folder-remote"],
+        "http" | "https" => &["web-browser", "text-html"],
+        "mailto" => &["mail-message-new", "internet-mail"],
+        "trash" => trash::get_trash_icon_names(),
+        "computer" => &["computer"],
+        _ => &["text-html", "text-x-generic"],
+    }
+}
+
+pub fn get_process_icon(pid: u32, size: i32) -> Result<Vec<u8>, Error> {
+    let filename = get_process_icon_as_file(pid, size)?;
+    Ok(fs::read(filename)?)
+}
+
+pub fn get_process_icon_as_file(pid: u32, size: i32) -> Result<String, Error> {
+    process::find_desktop_entry(pid)
+        .and_then(|entry| entry.icon())
+        .and_then(|icon| get_icon_by_icon_key(&icon, size).ok())
+        .map(Ok)
+        .unwrap_or_else(|| get_icon_by_names(&["application-x-executable"], size))
+}
+
+pub fn get_icon_for_app_id(id: &str, size: i32) -> Result<Vec<u8>, Error> {
+    let filename = get_icon_for_app_id_as_file(id, size)?;
+    Ok(fs::read(filename)?)
+}
+
+pub fn get_icon_for_app_id_as_file(id: &str, size: i32) -> Result<String, Error> {
+    let icon = app_id::find_app_for_app_id(id, &xdg::get_applications_dirs())
+        .and_then(|(entry, _)| entry.icon())
+        .ok_or_else(|| Error::not_found(id, Some(size), "No application found for the app id"))?;
+    get_icon_by_icon_key(&icon, size)
+}
+
+pub fn get_apps_for_mime_type(mime_type: &str, size: i32) -> MimeApps {
+    let applications_dirs = xdg::get_applications_dirs();
+    let associations = mime_apps::get_associations(mime_type);
+    let apps: Vec<AppInfo> = associations
+        .registered
+        .iter()
+        .filter_map(|desktop_id| {
+            let entry = DesktopEntry::find(desktop_id, &applications_dirs)?;
+            Some(AppInfo {
+                desktop_id: desktop_id.clone(),
+                name: entry.name().unwrap_or_else(|| desktop_id.trim_end_matches(".desktop").to_string()),
+                icon: entry.icon().and_then(|icon| get_icon_by_icon_key(&icon, size).ok()),
+            })
+        })
+        .collect();
+    let default = associations
+        .default
+        .and_then(|desktop_id| apps.iter().find(|app| app.desktop_id == desktop_id).cloned());
+    MimeApps { default, apps }
+}
+
+/// Resolves the value of an `Icon` key, which is either an absolute path or an icon name.
+/// Icons which are not in the icon theme are looked up in `/usr/share/pixmaps`
+pub fn get_icon_by_icon_key(icon: &str, size: i32) -> Result<String, Error> {
+    if Path::new(icon).is_absolute() {
+        return if Path::new(icon).is_file() {
+            Ok(icon.to_string())
+        } else {
+            Err(Error::not_found(icon, Some(size), "Icon file not found"))
         }
-        let icon_names = gio_sys::g_themed_icon_get_names(icon as *mut GThemedIcon) as *mut *const i8;
-        let icon_info = gtk_icon_theme_choose_icon(DEFAULT_THEME.unwrap(), icon_names, size, GTK_ICON_LOOKUP_NO_SVG);
-        let filename = gtk_icon_info_get_filename(icon_info);
-        let res_str = CStr::from_ptr(filename);
-        result = res_str.to_str()?.to_string();
-        g_object_unref(icon as *mut GObject);
     }
-    Ok(result)
+    // some applications specify the icon name with extension
+    let name = icon.strip_suffix(".png").or_else(|| icon.strip_suffix(".svg")).or_else(|| icon.strip_suffix(".xpm")).unwrap_or(icon);
+    get_icon_by_names(&[name], size).or_else(|err| {
+        let pixmaps = PathBuf::from("/usr/share/pixmaps");
+        [icon.to_string(), format!("{}.png", name), format!("{}.svg", name), format!("{}.xpm", name)]
+            .iter()
+            .map(|file| pixmaps.join(file))
+            .find(|file| file.is_file())
+            .map(|file| file.to_string_lossy().to_string())
+            .ok_or(err)
+    })
+}
+
+/// Looks up the first of the icon names which is present in the current icon theme
+pub fn get_icon_by_names(names: &[&str], size: i32) -> Result<String, Error> {
+    let names: Vec<CString> = names.iter().filter_map(|name| CString::new(*name).ok()).collect();
+    let mut icon_names: Vec<*const c_char> = names.iter().map(|name| name.as_ptr()).collect();
+    icon_names.push(ptr::null());
+    unsafe { choose_icon(icon_names.as_mut_ptr(), size) }
+}
+
+unsafe fn choose_icon(icon_names: *mut *const c_char, size: i32) -> Result<String, Error> {
+    choose_icon_with(icon_names, size, GTK_ICON_LOOKUP_NO_SVG)
+}
+
+unsafe fn choose_icon_with(icon_names: *mut *const c_char, size: i32, flags: GtkIconLookupFlags) -> Result<String, Error> {
+    let icon_info = gtk_icon_theme_choose_icon(default_theme()?, icon_names, size, flags);
+    if icon_info.is_null() {
+        return Err(Error::not_found("", Some(size), "No icon found in the icon theme"))
+    }
+    let filename = gtk_icon_info_get_filename(icon_info);
+    let result = if filename.is_null() {
+        Err(Error::not_found("", Some(size), "No icon found in the icon theme"))
+    } else {
+        CStr::from_ptr(filename).to_str().map(|filename| filename.to_string()).map_err(Error::from)
+    };
+    g_object_unref(icon_info as *mut GObject);
+    result
 }
 
-pub fn init() { gtk::init().unwrap(); }
+fn default_theme() -> Result<*mut GtkIconTheme, Error> {
+    let theme = DEFAULT_THEME.load(Ordering::Relaxed);
+    if !theme.is_null() {
+        return Ok(theme)
+    }
+    let theme = unsafe { gtk_icon_theme_get_default() };
+    if theme.is_null() {
+        tracing::warn!("GTK is not initialized, call systemicons::init() first");
+        return Err(Error::backend_unavailable("GTK", "You have to initialize GTK!"))
+    }
+    // GTK only searches $XDG_DATA_DIRS, so the icons of Flatpak and Snap applications may be missing
+    for dir in xdg::get_missing_data_dirs().iter().map(|dir| dir.join("icons")).filter(|dir| dir.is_dir()) {
+        if let Ok(dir) = CString::new(dir.as_os_str().as_bytes()) {
+            unsafe { gtk_icon_theme_append_search_path(theme, dir.as_ptr()) };
+        }
+    }
+    DEFAULT_THEME.store(theme, Ordering::Relaxed);
+    Ok(theme)
+}
 
+pub fn init() -> Result<(), Error> {
+    gtk::init().map_err(|err| Error::BackendUnavailable { backend: "GTK", reason: "GTK could not be initialized".to_string(), source: Some(Box::new(err)) })
+}
@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// The number of bytes which are read for the magic rules and the text check
+const MAX_SNIFF_SIZE: u64 = 4096;
+const DEFAULT_GLOB_WEIGHT: i32 = 50;
+
+/// Why a MIME type has been detected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MimeReason {
+    /// The request already was a MIME type
+    Given,
+    Directory,
+    /// The file name matches a glob pattern of `mime/globs2`
+    Glob { pattern: String, weight: i32 },
+    /// The content matches a rule of `mime/magic`
+    Magic { priority: i32 },
+    EmptyFile,
+    /// No glob and no magic rule matches, but the content is valid UTF-8 without control characters
+    Text,
+    /// Nothing matches
+    Fallback,
+}
+
+/// A step from the detected MIME type to related types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MimeStep {
+    /// The detected type is an alias of the canonical type, like `application/x-pdf` of `application/pdf`
+    Alias { alias: String, canonical: String },
+    /// The type is a subclass of the parent, like `application/xml` of `text/plain`
+    Subclass { child: String, parent: String },
+}
+
+/// The shared MIME-info database of freedesktop.org, read from the `mime` directories of the XDG data directories
+pub struct MimeDatabase {
+    globs: Vec<Glob>,
+    aliases: HashMap<String, String>,
+    subclasses: HashMap<String, Vec<String>>,
+    icons: HashMap<String, String>,
+    generic_icons: HashMap<String, String>,
+    magic: Vec<MagicSection>,
+    types: Vec<String>,
+}
+
+struct Glob {
+    weight: i32,
+    mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+struct MagicSection {
+    priority: i32,
+    mime_type: String,
+    rules: Vec<MagicRule>,
+}
+
+struct MagicRule {
+    indent: usize,
+    offset: usize,
+    range: usize,
+    value: Vec<u8>,
+    mask: Option<Vec<u8>>,
+}
+
+impl MimeDatabase {
+    /// Loads the database of the data directories, which are given in order of precedence
+    pub fn load(data_dirs: &[PathBuf]) -> MimeDatabase {
+        let mut database = MimeDatabase {
+            globs: Vec::new(),
+            aliases: HashMap::new(),
+            subclasses: HashMap::new(),
+            icons: HashMap::new(),
+            generic_icons: HashMap::new(),
+            magic: Vec::new(),
+            types: Vec::new(),
+        };
+        for dir in data_dirs.iter().map(|dir| dir.join("mime")) {
+            database.load_dir(&dir);
+        }
+        database.magic.sort_by_key(|section| std::cmp::Reverse(section.priority));
+        database.types.sort();
+        database.types.dedup();
+        database
+    }
+
+    fn load_dir(&mut self, dir: &Path) {
+        let read_lines = |name: &str| fs::read_to_string(dir.join(name)).unwrap_or_default();
+        for line in read_lines("globs2").lines().filter(|line| !line.starts_with('#')) {
+            let mut fields = line.splitn(4, ':');
+            if let (Some(weight), Some(mime_type), Some(pattern)) = (fields.next(), fields.next(), fields.next()) {
+                let case_sensitive = fields.next().map(|flags| flags.split(',').any(|flag| flag == "cs")).unwrap_or(false);
+                let weight = weight.parse().unwrap_or(DEFAULT_GLOB_WEIGHT);
+                self.globs.push(Glob { weight, mime_type: mime_type.to_string(), pattern: pattern.to_string(), case_sensitive });
+            }
+        }
+        for line in read_lines("aliases").lines() {
+            if let Some((alias, canonical)) = line.split_once(' ') {
+                self.aliases.entry(alias.to_string()).or_insert_with(|| canonical.to_string());
+            }
+        }
+        for line in read_lines("subclasses").lines() {
+            if let Some((child, parent)) = line.split_once(' ') {
+                let parents = self.subclasses.entry(child.to_string()).or_default();
+                if !parents.iter().any(|known| known == parent) {
+                    parents.push(parent.to_string());
+                }
+            }
+        }
+        for (file, map) in [("icons", &mut self.icons), ("generic-icons", &mut self.generic_icons)] {
+            for line in read_lines(file).lines() {
+                if let Some((mime_type, icon)) = line.split_once(':') {
+                    map.entry(mime_type.to_string()).or_insert_with(|| icon.to_string());
+                }
+            }
+        }
+        self.types.extend(read_lines("types").lines().filter(|line| !line.is_empty()).map(|line| line.to_string()));
+        if let Ok(magic) = fs::read(dir.join("magic")) {
+            self.magic.extend(parse_magic(&magic));
+        }
+    }
+
+    /// Detects the MIME type of a file name with the glob patterns, the highest weight and then the longest pattern wins
+    pub fn detect_name(&self, name: &str) -> Option<(String, MimeReason)> {
+        let lower_name = name.to_lowercase();
+        self.globs
+            .iter()
+            .filter(|glob| if glob.case_sensitive { glob_matches(&glob.pattern, name) } else { glob_matches(&glob.pattern.to_lowercase(), &lower_name) })
+            .max_by_key(|glob| (glob.weight, glob.pattern.len()))
+            .map(|glob| (glob.mime_type.clone(), MimeReason::Glob { pattern: glob.pattern.clone(), weight: glob.weight }))
+    }
+
+    /// Detects the MIME type of a file like file managers do: the file name is preferred, the content is only
+    /// checked if no glob pattern matches
+    pub fn detect_file(&self, path: &Path) -> (String, MimeReason) {
+        if path.is_dir() {
+            return ("inode/directory".to_string(), MimeReason::Directory);
+        }
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        if let Some(detected) = self.detect_name(&name) {
+            return detected;
+        }
+        let mut data = Vec::new();
+        if let Ok(file) = File::open(path) {
+            let _ = file.take(MAX_SNIFF_SIZE).read_to_end(&mut data);
+        }
+        if data.is_empty() {
+            return ("application/x-zerosize".to_string(), MimeReason::EmptyFile);
+        }
+        if let Some(section) = self.magic.iter().find(|section| rules_match(&section.rules, 0, &data)) {
+            return (section.mime_type.clone(), MimeReason::Magic { priority: section.priority });
+        }
+        if looks_like_text(&data) {
+            ("text/plain".to_string(), MimeReason::Text)
+        } else {
+            ("application/octet-stream".to_string(), MimeReason::Fallback)
+        }
+    }
+
+    /// All MIME types of the database, sorted
+    pub fn mime_types(&self) -> &[String] {
+        &self.types
+    }
+
+    /// Returns the canonical type of an alias
+    pub fn unalias(&self, mime_type: &str) -> Option<&str> {
+        self.aliases.get(mime_type).map(|canonical| canonical.as_str())
+    }
+
+    /// Returns the steps from a type to its canonical type and all its ancestors. All `text` types are subclasses
+    /// of `text/plain`, all other types except `inode` types of `application/octet-stream`
+    pub fn ancestors(&self, mime_type: &str) -> (String, Vec<MimeStep>) {
+        let mut steps = Vec::new();
+        let canonical = match self.unalias(mime_type) {
+            Some(canonical) => {
+                steps.push(MimeStep::Alias { alias: mime_type.to_string(), canonical: canonical.to_string() });
+                canonical.to_string()
+            }
+            None => mime_type.to_string(),
+        };
+        let mut queue = vec![canonical.clone()];
+        let mut visited = vec![canonical.clone()];
+        while let Some(child) = (!queue.is_empty()).then(|| queue.remove(0)) {
+            let mut parents = self.subclasses.get(&child).cloned().unwrap_or_default();
+            if parents.is_empty() {
+                if child.starts_with("text/") && child != "text/plain" {
+                    parents.push("text/plain".to_string());
+                } else if !child.starts_with("inode/") && child != "application/octet-stream" {
+                    parents.push("application/octet-stream".to_string());
+                }
+            }
+            for parent in parents {
+                let parent = self.unalias(&parent).map(|parent| parent.to_string()).unwrap_or(parent);
+                steps.push(MimeStep::Subclass { child: child.clone(), parent: parent.clone() });
+                if !visited.contains(&parent) {
+                    visited.push(parent.clone());
+                    queue.push(parent);
+                }
+            }
+        }
+        (canonical, steps)
+    }
+
+    /// The icon names of a MIME type in the order GIO uses them: the icon of `mime/icons`, the type with `-`
+    /// instead of `/`, the legacy GNOME name and the generic icon
+    pub fn icon_names(&self, mime_type: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        if let Some(icon) = self.icons.get(mime_type) {
+            names.push(icon.clone());
+        }
+        names.push(mime_type.replace('/', "-"));
+        names.push(format!("gnome-mime-{}", mime_type.replace('/', "-")));
+        let media_type = mime_type.split('/').next().unwrap_or_default();
+        names.push(self.generic_icons.get(mime_type).cloned().unwrap_or_else(|| format!("{}-x-generic", media_type)));
+        names.dedup();
+        names
+    }
+}
+
+/// Matches the shell glob patterns of shared-mime-info: `*`, `?` and character classes like `[ch]`
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    glob_matches_at(&pattern, &name)
+}
+
+fn glob_matches_at(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| glob_matches_at(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && glob_matches_at(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let Some(end) = pattern.iter().skip(2).position(|&c| c == ']').map(|end| end + 2) else {
+                return name.first() == Some(&'[') && glob_matches_at(&pattern[1..], &name[1..]);
+            };
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            let (negated, class) = match pattern[1] {
+                '!' | '^' => (true, &pattern[2..end]),
+                _ => (false, &pattern[1..end]),
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+            matched != negated && glob_matches_at(&pattern[end + 1..], &name[1..])
+        }
+        Some(&c) => name.first() == Some(&c) && glob_matches_at(&pattern[1..], &name[1..]),
+    }
+}
+
+/// Parses the binary `mime/magic` file: sections like `[50:application/pdf]` followed by rules like
+/// `[indent]>offset=<length as u16 BE><value>[&<mask>][~word size][+range length]`
+fn parse_magic(data: &[u8]) -> Vec<MagicSection> {
+    let Some(mut rest) = data.strip_prefix(b"MIME-Magic\0\n") else {
+        return Vec::new();
+    };
+    let mut sections: Vec<MagicSection> = Vec::new();
+    while !rest.is_empty() {
+        if rest[0] == b'[' {
+            let Some(end) = rest.iter().position(|&c| c == b'\n') else {
+                break;
+            };
+            let header = String::from_utf8_lossy(&rest[1..end]);
+            let header = header.trim_end_matches(']');
+            if let Some((priority, mime_type)) = header.split_once(':') {
+                sections.push(MagicSection { priority: priority.parse().unwrap_or(0), mime_type: mime_type.to_string(), rules: Vec::new() });
+            }
+            rest = &rest[end + 1..];
+            continue;
+        }
+        let Some((rule, remaining)) = parse_magic_rule(rest) else {
+            break;
+        };
+        if let Some(section) = sections.last_mut() {
+            section.rules.push(rule);
+        }
+        rest = remaining;
+    }
+    sections
+}
+
+fn parse_magic_rule(data: &[u8]) -> Option<(MagicRule, &[u8])> {
+    let (indent, rest) = parse_number(data);
+    let rest = rest.strip_prefix(b">")?;
+    let (offset, rest) = parse_number(rest);
+    let rest = rest.strip_prefix(b"=")?;
+    let len = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+    let mut value = rest.get(2..2 + len)?.to_vec();
+    let mut rest = &rest[2 + len..];
+    let mut mask = None;
+    if let Some(after) = rest.strip_prefix(b"&") {
+        mask = Some(after.get(..len)?.to_vec());
+        rest = &after[len..];
+    }
+    let mut word_size = 1;
+    if let Some(after) = rest.strip_prefix(b"~") {
+        let (size, after) = parse_number(after);
+        word_size = size.unwrap_or(1).max(1);
+        rest = after;
+    }
+    let mut range = 1;
+    if let Some(after) = rest.strip_prefix(b"+") {
+        let (length, after) = parse_number(after);
+        range = length.unwrap_or(1).max(1);
+        rest = after;
+    }
+    // unknown extensions are ignored up to the end of the line
+    let end = rest.iter().position(|&c| c == b'\n')?;
+    rest = &rest[end + 1..];
+    // values with a word size are stored big endian
+    if cfg!(target_endian = "little") && (word_size == 2 || word_size == 4) {
+        for word in value.chunks_mut(word_size) {
+            word.reverse();
+        }
+        if let Some(mask) = &mut mask {
+            for word in mask.chunks_mut(word_size) {
+                word.reverse();
+            }
+        }
+    }
+    Some((MagicRule { indent: indent.unwrap_or_default(), offset: offset.unwrap_or_default(), range, value, mask }, rest))
+}
+
+fn parse_number(data: &[u8]) -> (Option<usize>, &[u8]) {
+    let digits = data.iter().take_while(|c| c.is_ascii_digit()).count();
+    (std::str::from_utf8(&data[..digits]).ok().and_then(|number| number.parse().ok()), &data[digits..])
+}
+
+/// A rule matches if its value is found in its range and, if it has nested rules, one of them matches too
+fn rules_match(rules: &[MagicRule], indent: usize, data: &[u8]) -> bool {
+    let mut i = 0;
+    while i < rules.len() {
+        let rule = &rules[i];
+        let children_end = rules[i + 1..].iter().position(|child| child.indent <= rule.indent).map(|end| i + 1 + end).unwrap_or(rules.len());
+        if rule.indent == indent && rule_matches(rule, data) {
+            let children = &rules[i + 1..children_end];
+            if children.is_empty() || rules_match(children, indent + 1, data) {
+                return true;
+            }
+        }
+        i = children_end;
+    }
+    false
+}
+
+fn rule_matches(rule: &MagicRule, data: &[u8]) -> bool {
+    (rule.offset..rule.offset + rule.range).any(|offset| {
+        let Some(bytes) = data.get(offset..offset + rule.value.len()) else {
+            return false;
+        };
+        match &rule.mask {
+            Some(mask) => bytes.iter().zip(&rule.value).zip(mask).all(|((byte, value), mask)| byte & mask == value & mask),
+            None => bytes == rule.value.as_slice(),
+        }
+    })
+}
+
+/// Text is valid UTF-8 without control characters other than whitespace, the last character may be cut off
+fn looks_like_text(data: &[u8]) -> bool {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(err) if err.error_len().is_none() => std::str::from_utf8(&data[..err.valid_up_to()]).unwrap_or_default(),
+        Err(_) => return false,
+    };
+    text.chars().all(|c| !c.is_control() || c.is_whitespace() || c == '\u{1b}')
+}
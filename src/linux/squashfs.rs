@@ -0,0 +1,401 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    os::unix::fs::FileExt,
+};
+
+use super::appimage::{Image, NodeKind};
+
+const MAGIC: u32 = 0x7371_7368;
+const METADATA_SIZE: usize = 8192;
+const METADATA_UNCOMPRESSED: u16 = 0x8000;
+const DATA_UNCOMPRESSED: u32 = 1 << 24;
+const NO_FRAGMENT: u32 = 0xffff_ffff;
+const FRAGMENT_ENTRY_SIZE: usize = 16;
+const MIN_BLOCK_SIZE: u32 = 4096;
+const MAX_BLOCK_SIZE: u32 = 1024 * 1024;
+/// Icons are small, larger files are not read
+const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+const ZLIB: u16 = 1;
+const XZ: u16 = 4;
+const ZSTD: u16 = 6;
+
+const DIR: u16 = 1;
+const FILE: u16 = 2;
+const SYMLINK: u16 = 3;
+const LDIR: u16 = 8;
+const LFILE: u16 = 9;
+const LSYMLINK: u16 = 10;
+
+/// A read only SquashFS 4.0 file system, which starts at `offset` inside of a file
+pub struct SquashFs {
+    file: File,
+    offset: u64,
+    compression: u16,
+    block_size: u32,
+    root_inode: u64,
+    inode_table: u64,
+    directory_table: u64,
+    fragment_table: u64,
+}
+
+/// A directory entry, inodes are only read when they are needed
+#[derive(Clone)]
+pub struct Node {
+    inode: u64,
+    kind: NodeKind,
+}
+
+enum Inode {
+    Dir { block: u32, offset: u16, size: u32 },
+    File { start: u64, size: u64, fragment: u32, fragment_offset: u32, blocks: Vec<u32> },
+    Symlink(String),
+    Other,
+}
+
+impl SquashFs {
+    pub fn open(file: File, offset: u64) -> io::Result<SquashFs> {
+        let mut superblock = [0; 96];
+        file.read_exact_at(&mut superblock, offset)?;
+        let u16_at = |pos: usize| u16::from_le_bytes([superblock[pos], superblock[pos + 1]]);
+        let u32_at = |pos: usize| u32::from_le_bytes(superblock[pos..pos + 4].try_into().unwrap_or_default());
+        let u64_at = |pos: usize| u64::from_le_bytes(superblock[pos..pos + 8].try_into().unwrap_or_default());
+        if u32_at(0) != MAGIC || u16_at(28) != 4 {
+            return Err(invalid_data("No SquashFS 4.0 file system"));
+        }
+        let compression = u16_at(20);
+        if ![ZLIB, XZ, ZSTD].contains(&compression) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("Unsupported SquashFS compression {}", compression)));
+        }
+        let block_size = u32_at(12);
+        if !block_size.is_power_of_two() || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) || block_size.trailing_zeros() != u16_at(22) as u32 {
+            return Err(invalid_data("Invalid SquashFS block size"));
+        }
+        Ok(SquashFs {
+            file,
+            offset,
+            compression,
+            block_size,
+            root_inode: u64_at(32),
+            inode_table: u64_at(64),
+            directory_table: u64_at(72),
+            fragment_table: u64_at(80),
+        })
+    }
+
+    fn read_inode(&self, inode: u64) -> io::Result<Inode> {
+        let mut reader = MetadataReader::new(self, add(self.inode_table, inode >> 16)?, (inode & 0xffff) as usize)?;
+        let inode_type = reader.read_u16()?;
+        reader.skip(14)?;
+        let inode = match inode_type {
+            DIR => {
+                let block = reader.read_u32()?;
+                reader.skip(4)?;
+                let size = reader.read_u16()? as u32;
+                let offset = reader.read_u16()?;
+                Inode::Dir { block, offset, size }
+            }
+            LDIR => {
+                reader.skip(4)?;
+                let size = reader.read_u32()?;
+                let block = reader.read_u32()?;
+                reader.skip(6)?;
+                let offset = reader.read_u16()?;
+                Inode::Dir { block, offset, size }
+            }
+            FILE | LFILE => {
+                let (start, size, fragment, fragment_offset) = if inode_type == FILE {
+                    let start = reader.read_u32()? as u64;
+                    let fragment = reader.read_u32()?;
+                    let fragment_offset = reader.read_u32()?;
+                    (start, reader.read_u32()? as u64, fragment, fragment_offset)
+                } else {
+                    let start = reader.read_u64()?;
+                    let size = reader.read_u64()?;
+                    reader.skip(12)?;
+                    let fragment = reader.read_u32()?;
+                    let fragment_offset = reader.read_u32()?;
+                    reader.skip(4)?;
+                    (start, size, fragment, fragment_offset)
+                };
+                if size > MAX_FILE_SIZE {
+                    return Err(invalid_data("File too large"));
+                }
+                let block_size = self.block_size as u64;
+                // the tail end of the file is stored in a fragment block
+                let count = if fragment == NO_FRAGMENT { size.div_ceil(block_size) } else { size / block_size };
+                let blocks = (0..count).map(|_| reader.read_u32()).collect::<io::Result<_>>()?;
+                Inode::File { start, size, fragment, fragment_offset, blocks }
+            }
+            SYMLINK | LSYMLINK => {
+                reader.skip(4)?;
+                let size = reader.read_u32()? as usize;
+                Inode::Symlink(String::from_utf8_lossy(reader.read(size)?).to_string())
+            }
+            _ => Inode::Other,
+        };
+        Ok(inode)
+    }
+
+    fn read_fragment(&self, fragment: u32) -> io::Result<Vec<u8>> {
+        let index = fragment as usize * FRAGMENT_ENTRY_SIZE;
+        let mut location = [0; 8];
+        self.file.read_exact_at(&mut location, add(self.position(self.fragment_table)?, (index / METADATA_SIZE * 8) as u64)?)?;
+        let mut reader = MetadataReader::new(self, u64::from_le_bytes(location), index % METADATA_SIZE)?;
+        let start = reader.read_u64()?;
+        let size = reader.read_u32()?;
+        self.read_data_block(start, size)
+    }
+
+    fn read_data_block(&self, start: u64, size: u32) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; (size & !DATA_UNCOMPRESSED) as usize];
+        self.file.read_exact_at(&mut data, self.position(start)?)?;
+        if size & DATA_UNCOMPRESSED != 0 {
+            Ok(data)
+        } else {
+            self.decompress(&data, self.block_size as usize)
+        }
+    }
+
+    /// The position in the file of an offset inside of the file system
+    fn position(&self, offset: u64) -> io::Result<u64> {
+        add(self.offset, offset)
+    }
+
+    fn decompress(&self, data: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+        decompress(self.compression, data, limit)
+    }
+}
+
+/// Decompresses a block, which must not grow larger than `limit`, the size of a data or a metadata block.
+/// Otherwise a small crafted block could be decompressed to gigabytes
+fn decompress(compression: u16, data: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    let mut result = Vec::new();
+    let take = limit as u64 + 1;
+    match compression {
+        ZLIB => {
+            flate2::read::ZlibDecoder::new(data).take(take).read_to_end(&mut result)?;
+        }
+        XZ => {
+            let mut output = LimitedWriter { data: result, limit };
+            lzma_rs::xz_decompress(&mut BufReader::new(data), &mut output).map_err(|err| invalid_data(&err.to_string()))?;
+            result = output.data;
+        }
+        _ => {
+            ruzstd::decoding::StreamingDecoder::new(data)
+                .map_err(|err| invalid_data(&err.to_string()))?
+                .take(take)
+                .read_to_end(&mut result)?;
+        }
+    }
+    if result.len() > limit {
+        return Err(too_large());
+    }
+    Ok(result)
+}
+
+/// lzma-rs only writes into a writer, which stops it when the limit is exceeded
+struct LimitedWriter {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.data.len() + buf.len() > self.limit {
+            return Err(too_large());
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Image for SquashFs {
+    type Node = Node;
+
+    fn root(&self) -> io::Result<Node> {
+        Ok(Node { inode: self.root_inode, kind: NodeKind::Dir })
+    }
+
+    fn kind(node: &Node) -> &NodeKind {
+        &node.kind
+    }
+
+    fn read_dir(&self, dir: &Node) -> io::Result<Vec<(String, Node)>> {
+        let Inode::Dir { block, offset, size } = self.read_inode(dir.inode)? else {
+            return Err(invalid_data("No directory"));
+        };
+        let mut reader = MetadataReader::new(self, add(self.directory_table, block as u64)?, offset as usize)?;
+        // the size includes the virtual entries . and ..
+        let mut remaining = (size as usize).saturating_sub(3);
+        let mut entries = Vec::new();
+        while remaining >= 12 {
+            let count = reader.read_u32()? as usize + 1;
+            let inode_block = reader.read_u32()? as u64;
+            reader.skip(4)?;
+            remaining -= 12;
+            for _ in 0..count {
+                let inode_offset = reader.read_u16()? as u64;
+                reader.skip(2)?;
+                let entry_type = reader.read_u16()?;
+                let name_size = reader.read_u16()? as usize + 1;
+                let name = String::from_utf8_lossy(reader.read(name_size)?).to_string();
+                remaining = remaining.saturating_sub(8 + name_size);
+                let inode = inode_block << 16 | inode_offset;
+                let kind = match entry_type {
+                    DIR | LDIR => NodeKind::Dir,
+                    FILE | LFILE => NodeKind::File,
+                    SYMLINK | LSYMLINK => match self.read_inode(inode)? {
+                        Inode::Symlink(target) => NodeKind::Symlink(target),
+                        _ => NodeKind::Other,
+                    },
+                    _ => NodeKind::Other,
+                };
+                entries.push((name, Node { inode, kind }));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn read_file(&self, file: &Node) -> io::Result<Vec<u8>> {
+        let Inode::File { start, size, fragment, fragment_offset, blocks } = self.read_inode(file.inode)? else {
+            return Err(invalid_data("No regular file"));
+        };
+        let mut data = Vec::with_capacity(size as usize);
+        let mut position = start;
+        for block in blocks {
+            if block == 0 {
+                // sparse block
+                data.resize(data.len() + self.block_size as usize, 0);
+                continue;
+            }
+            data.extend(self.read_data_block(position, block)?);
+            position = add(position, (block & !DATA_UNCOMPRESSED) as u64)?;
+        }
+        if fragment != NO_FRAGMENT {
+            let fragment = self.read_fragment(fragment)?;
+            let tail = (size % self.block_size as u64) as usize;
+            let tail = fragment
+                .get(fragment_offset as usize..(fragment_offset as usize).saturating_add(tail))
+                .ok_or_else(|| invalid_data("Invalid fragment"))?;
+            data.extend_from_slice(tail);
+        }
+        data.truncate(size as usize);
+        Ok(data)
+    }
+}
+
+/// Reads the metadata of the inode and directory tables, which is stored in consecutive blocks of 8 KiB
+struct MetadataReader<'a> {
+    squashfs: &'a SquashFs,
+    next_block: u64,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<'a> MetadataReader<'a> {
+    fn new(squashfs: &'a SquashFs, block: u64, offset: usize) -> io::Result<MetadataReader<'a>> {
+        let mut reader = MetadataReader { squashfs, next_block: block, buffer: Vec::new(), position: offset };
+        reader.fill(offset)?;
+        Ok(reader)
+    }
+
+    fn fill(&mut self, len: usize) -> io::Result<()> {
+        while self.buffer.len() < self.position.max(len) {
+            let mut header = [0; 2];
+            let position = self.squashfs.position(self.next_block)?;
+            self.squashfs.file.read_exact_at(&mut header, position)?;
+            let header = u16::from_le_bytes(header);
+            let mut block = vec![0; (header & !METADATA_UNCOMPRESSED) as usize];
+            self.squashfs.file.read_exact_at(&mut block, add(position, 2)?)?;
+            self.next_block = add(self.next_block, 2 + block.len() as u64)?;
+            let block = if header & METADATA_UNCOMPRESSED != 0 { block } else { self.squashfs.decompress(&block, METADATA_SIZE)? };
+            if block.is_empty() {
+                return Err(invalid_data("Empty metadata block"));
+            }
+            self.buffer.extend(block);
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, len: usize) -> io::Result<&[u8]> {
+        self.fill(self.position + len)?;
+        self.position += len;
+        Ok(&self.buffer[self.position - len..self.position])
+    }
+
+    fn skip(&mut self, len: usize) -> io::Result<()> {
+        self.read(len).map(|_| ())
+    }
+
+    fn read_u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.read(2)?.try_into().unwrap_or_default()))
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.read(4)?.try_into().unwrap_or_default()))
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.read(8)?.try_into().unwrap_or_default()))
+    }
+}
+
+/// Offsets are read from the superblock and the inodes, so they may overflow
+fn add(offset: u64, len: u64) -> io::Result<u64> {
+    offset.checked_add(len).ok_or_else(|| invalid_data("Offset out of range"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn too_large() -> io::Error {
+    invalid_data("The decompressed block is larger than the block size")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compresses `len` zeros, which shrink to a few bytes
+    fn compress(compression: u16, len: usize) -> Vec<u8> {
+        let data = vec![0; len];
+        match compression {
+            ZLIB => {
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(&data).unwrap();
+                encoder.finish().unwrap()
+            }
+            XZ => {
+                let mut result = Vec::new();
+                lzma_rs::xz_compress(&mut &data[..], &mut result).unwrap();
+                result
+            }
+            _ => ruzstd::encoding::compress_to_vec(&data[..], ruzstd::encoding::CompressionLevel::Fastest),
+        }
+    }
+
+    #[test]
+    fn block_within_limit() {
+        for compression in [ZLIB, XZ, ZSTD] {
+            assert_eq!(decompress(compression, &compress(compression, METADATA_SIZE), METADATA_SIZE).unwrap().len(), METADATA_SIZE);
+        }
+    }
+
+    #[test]
+    fn decompression_bomb() {
+        for compression in [ZLIB, XZ, ZSTD] {
+            let block = compress(compression, 4 * MAX_BLOCK_SIZE as usize);
+            for limit in [METADATA_SIZE, MAX_BLOCK_SIZE as usize] {
+                let err = decompress(compression, &block, limit).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{} {}", compression, limit);
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    ffi::CString,
+    fs::{self, File},
+    io,
+    os::unix::{ffi::OsStrExt, fs::PermissionsExt, io::AsRawFd},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::Error;
+
+use super::{
+    mountinfo::{self, Mount, MOUNTINFO},
+    volume::REMOTE_FS_TYPES,
+    xdg,
+};
+
+const POLL_TIMEOUT_MS: i32 = 500;
+/// The file systems of the kernel and of automounts, which GIO regards as system internal mounts
+const VIRTUAL_FS_TYPES: &[&str] = &[
+    "auto", "autofs", "bdev", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "cpuset", "debugfs", "devpts",
+    "devtmpfs", "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs", "proc", "pstore", "ramfs", "rpc_pipefs",
+    "securityfs", "selinuxfs", "sysfs", "tracefs", "usbfs",
+];
+
+pub fn get_trash_icon_names() -> &'static [&'static str] {
+    if is_trash_full() {
+        &["user-trash-full", "user-trash"]
+    } else {
+        &["user-trash"]
+    }
+}
+
+/// The trash is full, when the home trash or one of the trash directories on the mounted volumes contains files
+pub fn is_trash_full() -> bool {
+    is_trash_full_in(&xdg::get_data_home(), Path::new(MOUNTINFO))
+}
+
+/// Like [`is_trash_full`] with the home trash in `data_home` and the mounts of the file `mountinfo`
+pub fn is_trash_full_in(data_home: &Path, mountinfo: &Path) -> bool {
+    get_trash_files_dirs(data_home, mountinfo).iter().any(|dir| {
+        fs::read_dir(dir)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false)
+    })
+}
+
+/// Returns the `files` directories of all existing trash directories
+fn get_trash_files_dirs(data_home: &Path, mountinfo: &Path) -> Vec<PathBuf> {
+    let uid = unsafe { libc::getuid() };
+    let mut dirs = vec![data_home.join("Trash/files")];
+    for mount in mountinfo::read_mounts(mountinfo).unwrap_or_default().into_iter().filter(may_have_trash) {
+        let shared_trash = mount.mount_point.join(".Trash");
+        // $topdir/.Trash must not be a symbolic link and must have the sticky bit set
+        let is_valid_shared_trash = fs::symlink_metadata(&shared_trash)
+            .map(|metadata| metadata.is_dir() && metadata.permissions().mode() & libc::S_ISVTX != 0)
+            .unwrap_or(false);
+        if is_valid_shared_trash {
+            dirs.push(shared_trash.join(uid.to_string()).join("files"));
+        }
+        dirs.push(mount.mount_point.join(format!(".Trash-{}", uid)).join("files"));
+    }
+    dirs.retain(|dir| dir.is_dir());
+    dirs.dedup();
+    dirs
+}
+
+/// Looking for trash directories on virtual and remote file systems may block on dead network mounts or trigger
+/// automounts, and files cannot be moved to the trash of a mount the user cannot write to
+fn may_have_trash(mount: &Mount) -> bool {
+    let fs_type = mount.fs_type.as_str();
+    !mount.read_only
+        && !VIRTUAL_FS_TYPES.contains(&fs_type)
+        && !REMOTE_FS_TYPES.contains(&fs_type)
+        && CString::new(mount.mount_point.as_os_str().as_bytes())
+            .map(|mount_point| unsafe { libc::access(mount_point.as_ptr(), libc::W_OK) } == 0)
+            .unwrap_or(false)
+}
+
+/// Watches the trash directories with inotify. The watching stops when it is dropped
+pub struct TrashWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for TrashWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+pub fn watch_trash<F>(on_change: F) -> Result<TrashWatcher, Error>
+where
+    F: Fn(bool) + Send + 'static,
+{
+    let inotify = Inotify::new()?;
+    // mountinfo signals POLLPRI when volumes are mounted or unmounted
+    let mountinfo = File::open(MOUNTINFO)?;
+    inotify.add_watches();
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread = thread::spawn(move || {
+        let mut is_full = is_trash_full();
+        let mut fds = [
+            libc::pollfd { fd: inotify.fd, events: libc::POLLIN, revents: 0 },
+            libc::pollfd { fd: mountinfo.as_raw_fd(), events: libc::POLLPRI, revents: 0 },
+        ];
+        while !thread_stop.load(Ordering::Relaxed) {
+            let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, POLL_TIMEOUT_MS) };
+            if ready <= 0 {
+                continue;
+            }
+            if fds[0].revents != 0 {
+                inotify.drain();
+            }
+            // new trash directories may have been created or mounted
+            inotify.add_watches();
+            let now_full = is_trash_full();
+            if now_full != is_full {
+                is_full = now_full;
+                on_change(is_full);
+            }
+        }
+    });
+    Ok(TrashWatcher { stop, thread: Some(thread) })
+}
+
+struct Inotify {
+    fd: i32,
+}
+
+impl Inotify {
+    fn new() -> Result<Inotify, Error> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            Err(io::Error::last_os_error().into())
+        } else {
+            Ok(Inotify { fd })
+        }
+    }
+
+    fn add_watches(&self) {
+        let mask = libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO | libc::IN_DELETE_SELF;
+        // the home trash directory itself is watched too, so that the creation of its files directory is noticed
+        let data_home = xdg::get_data_home();
+        let dirs = get_trash_files_dirs(&data_home, Path::new(MOUNTINFO)).into_iter().chain([data_home.join("Trash")]);
+        for dir in dirs.filter(|dir| dir.is_dir()) {
+            if let Ok(dir) = CString::new(dir.as_os_str().as_bytes()) {
+                unsafe { libc::inotify_add_watch(self.fd, dir.as_ptr(), mask) };
+            }
+        }
+    }
+
+    fn drain(&self) {
+        let mut buffer = [0u8; 4096];
+        while unsafe { libc::read(self.fd, buffer.as_mut_ptr() as *mut _, buffer.len()) } > 0 {}
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::MetadataExt;
+
+    use super::*;
+
+    /// A data home and a volume mounted at `volume dir` (with a space, which mountinfo escapes)
+    struct Dirs {
+        root: PathBuf,
+        data_home: PathBuf,
+        volume: PathBuf,
+        mountinfo: PathBuf,
+        uid: u32,
+    }
+
+    impl Dirs {
+        fn new(name: &str) -> Dirs {
+            let root = std::env::temp_dir().join(format!("systemicons-test-{}-trash-{}", std::process::id(), name));
+            let data_home = root.join("data");
+            let volume = root.join("volume dir");
+            fs::create_dir_all(data_home.join("Trash/files")).unwrap();
+            fs::create_dir_all(&volume).unwrap();
+            let mountinfo = root.join("mountinfo");
+            let uid = fs::metadata(&root).unwrap().uid();
+            let dirs = Dirs { root, data_home, volume, mountinfo, uid };
+            dirs.mount("rw,nosuid", "vfat");
+            dirs
+        }
+
+        /// Writes the mountinfo file with the volume mounted with `options` as `fs_type`
+        fn mount(&self, options: &str, fs_type: &str) {
+            let mount_point = self.volume.to_str().unwrap().replace(' ', "\\040");
+            let line = format!("101 22 8:17 / {} {} shared:300 - {} /dev/sdb1 rw\n", mount_point, options, fs_type);
+            fs::write(&self.mountinfo, line).unwrap();
+        }
+
+        fn is_full(&self) -> bool {
+            is_trash_full_in(&self.data_home, &self.mountinfo)
+        }
+    }
+
+    impl Drop for Dirs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn home_trash() {
+        let dirs = Dirs::new("home");
+        assert!(!dirs.is_full());
+        fs::write(dirs.data_home.join("Trash/files/report.pdf"), b"").unwrap();
+        assert!(dirs.is_full());
+        fs::remove_file(dirs.data_home.join("Trash/files/report.pdf")).unwrap();
+        assert!(!dirs.is_full());
+    }
+
+    #[test]
+    fn missing_home_trash() {
+        let dirs = Dirs::new("missing");
+        fs::remove_dir_all(dirs.data_home.join("Trash")).unwrap();
+        assert!(!dirs.is_full());
+    }
+
+    #[test]
+    fn volume_trash() {
+        let dirs = Dirs::new("volume");
+        let files = dirs.volume.join(format!(".Trash-{}", dirs.uid)).join("files");
+        fs::create_dir_all(&files).unwrap();
+        assert!(!dirs.is_full());
+        fs::create_dir(files.join("photos")).unwrap();
+        assert!(dirs.is_full());
+    }
+
+    #[test]
+    fn skipped_volumes() {
+        let dirs = Dirs::new("skipped");
+        fs::create_dir_all(dirs.volume.join(format!(".Trash-{}", dirs.uid)).join("files/photos")).unwrap();
+        assert!(dirs.is_full());
+        for (options, fs_type) in [("ro,nosuid", "vfat"), ("rw", "nfs4"), ("rw", "fuse.sshfs"), ("rw", "autofs"), ("rw", "proc"), ("rw", "cgroup2")] {
+            dirs.mount(options, fs_type);
+            assert!(!dirs.is_full(), "{} {}", options, fs_type);
+        }
+    }
+
+    #[test]
+    fn shared_volume_trash_needs_sticky_bit() {
+        let dirs = Dirs::new("shared");
+        let shared = dirs.volume.join(".Trash");
+        fs::create_dir_all(shared.join(dirs.uid.to_string()).join("files/report.pdf")).unwrap();
+        assert!(!dirs.is_full());
+        fs::set_permissions(&shared, fs::Permissions::from_mode(0o1777)).unwrap();
+        assert!(dirs.is_full());
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::MetadataExt,
+    path::Path,
+};
+
+use crate::Error;
+
+use super::mountinfo::{self, Mount};
+
+pub const UDEV_DATA: &str = "/run/udev/data";
+
+pub const REMOTE_FS_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "ncpfs", "afs", "9p", "ceph", "glusterfs", "davfs", "fuse.sshfs",
+    "fuse.rclone", "fuse.davfs2", "fuse.curlftpfs", "fuse.gvfsd-fuse",
+];
+
+/// Determines the icon names for the volume mounted at `mount_path`, the most specific one first
+pub fn get_volume_icon_names(mount_path: &Path, mountinfo: &Path, udev_data: &Path) -> Result<Vec<String>, Error> {
+    let mount_path = mount_path.canonicalize().unwrap_or_else(|_| mount_path.to_path_buf());
+    let mount = mountinfo::find_mount(mountinfo, &mount_path)?
+        .ok_or_else(|| Error::not_found(&mount_path.to_string_lossy(), None, "No mount found"))?;
+    let names: &[&str] = if REMOTE_FS_TYPES.contains(&mount.fs_type.as_str()) {
+        &["folder-remote"]
+    } else {
+        let properties = read_udev_properties(&mount, udev_data);
+        if let Some(icon_name) = properties.get("UDISKS_ICON_NAME").filter(|name| !name.is_empty()) {
+            return Ok(vec![icon_name.clone(), "drive-harddisk".to_string()]);
+        }
+        get_icon_names(&mount, &properties)
+    };
+    Ok(names.iter().map(|name| name.to_string()).collect())
+}
+
+fn get_icon_names(mount: &Mount, properties: &HashMap<String, String>) -> &'static [&'static str] {
+    let is_set = |key: &str| properties.get(key).map(|value| value == "1").unwrap_or(false);
+    let bus = properties.get("ID_BUS").map(|bus| bus.as_str());
+    if is_set("ID_CDROM") || properties.get("ID_TYPE").map(|t| t == "cd").unwrap_or(false) || mount.fs_type == "iso9660" || mount.fs_type == "udf" {
+        &["media-optical", "drive-optical"]
+    } else if is_set("ID_DRIVE_FLOPPY") || is_set("ID_DRIVE_FLOPPY_ZIP") {
+        &["media-floppy", "drive-removable-media"]
+    } else if properties.keys().any(|key| key.starts_with("ID_DRIVE_FLASH")) {
+        &["media-flash", "drive-removable-media"]
+    } else if is_set("ID_DRIVE_THUMB") || bus == Some("usb") {
+        &["drive-removable-media-usb", "drive-removable-media", "drive-harddisk"]
+    } else if bus == Some("ieee1394") {
+        &["drive-removable-media-ieee1394", "drive-removable-media", "drive-harddisk"]
+    } else if mount.source.starts_with("/dev/mmcblk") {
+        &["media-flash", "drive-removable-media"]
+    } else if !properties.is_empty() || mount.source.starts_with("/dev/") {
+        &["drive-harddisk"]
+    } else {
+        // virtual file systems like tmpfs or proc
+        &["folder"]
+    }
+}
+
+/// Reads the properties (`E:KEY=VALUE` lines) of the udev database entry `b<major>:<minor>`
+fn read_udev_properties(mount: &Mount, udev_data: &Path) -> HashMap<String, String> {
+    let read = |major: u32, minor: u32| fs::read_to_string(udev_data.join(format!("b{}:{}", major, minor))).ok();
+    // btrfs and others report an anonymous device number, so the source device is tried as well
+    let content = read(mount.major, mount.minor).or_else(|| {
+        fs::metadata(&mount.source)
+            .ok()
+            .filter(|_| mount.source.starts_with("/dev/"))
+            .and_then(|metadata| {
+                let device = metadata.rdev();
+                read(libc::major(device), libc::minor(device))
+            })
+    });
+    content
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.strip_prefix("E:")?.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
@@ -0,0 +1,137 @@
+use std::{env, path::{Path, PathBuf}};
+
+/// `$XDG_DATA_HOME`, defaults to `~/.local/share`
+pub fn get_data_home() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".local/share"))
+}
+
+/// `$XDG_DATA_DIRS` (defaults to `/usr/local/share:/usr/share`), followed by the export directories of Flatpak and Snap
+/// and the host directories inside of a Flatpak sandbox, if they are missing
+pub fn get_data_dirs() -> Vec<PathBuf> {
+    let mut dirs = get_env_data_dirs();
+    dirs.extend(get_missing_data_dirs());
+    dirs
+}
+
+/// The export directories of Flatpak and Snap, and the host directories when running inside of a Flatpak sandbox,
+/// which are not contained in `$XDG_DATA_DIRS`. They are missing for example in systemd services and cron jobs
+pub fn get_missing_data_dirs() -> Vec<PathBuf> {
+    get_missing_data_dirs_in(&get_data_home(), &get_env_data_dirs(), Path::new("/.flatpak-info").exists())
+}
+
+/// Like [`get_missing_data_dirs`] with `$XDG_DATA_HOME`, the directories of `$XDG_DATA_DIRS` and whether the process
+/// runs inside of a Flatpak sandbox
+pub fn get_missing_data_dirs_in(data_home: &Path, env_dirs: &[PathBuf], in_flatpak: bool) -> Vec<PathBuf> {
+    let mut dirs = vec![
+        data_home.join("flatpak/exports/share"),
+        PathBuf::from("/var/lib/flatpak/exports/share"),
+        PathBuf::from("/var/lib/snapd/desktop"),
+    ];
+    if in_flatpak {
+        dirs.push(PathBuf::from("/run/host/user-share"));
+        dirs.push(PathBuf::from("/run/host/share"));
+    }
+    dirs.retain(|dir| !env_dirs.contains(dir));
+    dirs
+}
+
+fn get_env_data_dirs() -> Vec<PathBuf> {
+    env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string())
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The `applications` directories in order of precedence, beginning with the one in `$XDG_DATA_HOME`
+pub fn get_applications_dirs() -> Vec<PathBuf> {
+    std::iter::once(get_data_home())
+        .chain(get_data_dirs())
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// `$XDG_CONFIG_HOME`, defaults to `~/.config`
+pub fn get_config_home() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".config"))
+}
+
+/// `$XDG_CONFIG_DIRS`, defaults to `/etc/xdg`
+pub fn get_config_dirs() -> Vec<PathBuf> {
+    env::var("XDG_CONFIG_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/etc/xdg".to_string())
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The lowercased names of `$XDG_CURRENT_DESKTOP`, like `gnome` or `kde`
+pub fn get_current_desktops() -> Vec<String> {
+    env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(|desktop| desktop.to_lowercase())
+        .collect()
+}
+
+/// `$XDG_CACHE_HOME`, defaults to `~/.cache`
+pub fn get_cache_home() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".cache"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missing(env_dirs: &[&str], in_flatpak: bool) -> Vec<PathBuf> {
+        let env_dirs: Vec<_> = env_dirs.iter().map(PathBuf::from).collect();
+        get_missing_data_dirs_in(Path::new("/home/user/.local/share"), &env_dirs, in_flatpak)
+    }
+
+    #[test]
+    fn systemd_service() {
+        // services and cron jobs only get the default data directories
+        assert_eq!(
+            missing(&["/usr/local/share", "/usr/share"], false),
+            [
+                PathBuf::from("/home/user/.local/share/flatpak/exports/share"),
+                PathBuf::from("/var/lib/flatpak/exports/share"),
+                PathBuf::from("/var/lib/snapd/desktop"),
+            ]
+        );
+    }
+
+    #[test]
+    fn desktop_session() {
+        let env_dirs = [
+            "/home/user/.local/share/flatpak/exports/share",
+            "/var/lib/flatpak/exports/share/",
+            "/usr/local/share",
+            "/usr/share",
+            "/var/lib/snapd/desktop",
+        ];
+        assert!(missing(&env_dirs, false).is_empty());
+    }
+
+    #[test]
+    fn flatpak_sandbox() {
+        let dirs = missing(&["/app/share", "/usr/share", "/usr/share/runtime/share", "/run/host/user-share"], true);
+        assert_eq!(dirs[3..], [PathBuf::from("/run/host/share")]);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Error;
+use crate::{input, pe, shortcut, Error};
 use cocoa::{
     base::{id, nil, YES},
     foundation::NSSize,
@@ -26,19 +26,30 @@ pub fn get_icon(ext: &str, size: f64) -> Result<Vec<u8>, Error> {
     Ok(buffer)
 }
 
+pub fn get_icon_for_path(path: &str, size: f64) -> Result<Vec<u8>, Error> {
+    // NSWorkspace only knows generic icons for Windows executables and shortcuts
+    if pe::has_pe_extension(Path::new(path)) {
+        if let Ok(icon) = pe::get_icon(Path::new(path), 0, size as i32) {
+            return Ok(icon);
+        }
+    } else if shortcut::is_shortcut(Path::new(path)) {
+        if let Ok(icon) = shortcut::get_icon(Path::new(path), size as i32) {
+            return Ok(icon.icon);
+        }
+    }
+    get_icon(path, size)
+}
+
 pub fn get_icon_as_file(ext: &str, size: f64) -> Result<String, Error> {
+    let source_path = CString::new(ext).map_err(|_| Error::invalid_input(ext, "The path contains a NUL character"))?;
+    let temp_out_path = env::temp_dir().join(format!("{}.png", input::file_name(ext)?));
+    let temp_out_path = temp_out_path.to_str().ok_or_else(|| Error::invalid_input(ext, "The temporary directory is not UTF-8"))?.to_string();
+    let out_path = CString::new(temp_out_path.as_str()).map_err(|_| Error::invalid_input(ext, "The temporary directory contains a NUL character"))?;
     unsafe {
         // convert &str to NSString
-        let ns_source_path: id =
-            msg_send![class!(NSString), stringWithCString: CString::new(ext).unwrap()];
-
-        let temp_out_path = format!(
-            "{}{}.png",
-            env::temp_dir().to_str().unwrap(),
-            Path::new(ext).file_name().unwrap().to_str().unwrap()
-        );
+        let ns_source_path: id = msg_send![class!(NSString), stringWithCString: source_path.as_ptr()];
 
-        let ns_out_path: id = msg_send![class!(NSString), stringWithCString: CString::new(temp_out_path.as_str()).unwrap() ];
+        let ns_out_path: id = msg_send![class!(NSString), stringWithCString: out_path.as_ptr()];
 
         // get shared workspace
         let ns_workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
@@ -59,6 +70,6 @@ pub fn get_icon_as_file(ext: &str, size: f64) -> Result<String, Error> {
         let _: () = msg_send![png_data, writeToFile:ns_out_path atomically:YES];
         let _: () = msg_send![image_rep, autorelease];
 
-        Ok(temp_out_path.to_string())
+        Ok(temp_out_path)
     }
 }
@@ -0,0 +1,317 @@
+//! Reading the icons from the resources of Windows executables and libraries (PE files like `.exe` or `.dll`).
+//!
+//! The parser is written in Rust and works the same on every platform, so it can be used for `.exe` files
+//! in Wine prefixes or on mounted Windows partitions.
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{ico, Error};
+
+const RT_ICON: u32 = 3;
+const RT_GROUP_ICON: u32 = 14;
+const RESOURCE_DIRECTORY: usize = 2;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PE_EXTENSIONS: &[&str] = &["exe", "dll", "cpl", "ocx", "scr", "mun"];
+
+/// The ID of a resource, which is either a number or a name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceId {
+    Id(u16),
+    Name(String),
+}
+
+/// An icon resource (`RT_GROUP_ICON`), which contains the same icon in several sizes
+#[derive(Debug, Clone)]
+pub struct IconGroup {
+    pub id: ResourceId,
+    pub entries: Vec<IconEntry>,
+}
+
+/// An image of an icon group, which is stored as `RT_ICON` resource
+#[derive(Debug, Clone)]
+pub struct IconEntry {
+    /// The ID of the `RT_ICON` resource
+    pub id: u16,
+    pub width: u32,
+    pub height: u32,
+    pub bit_count: u16,
+    /// Since Windows Vista large icons may be stored PNG compressed
+    pub is_png: bool,
+}
+
+/// A parsed PE file
+pub struct PeFile {
+    /// The whole file, or only the section containing the resources when the file has been opened
+    data: Vec<u8>,
+    /// The file offset of `data`
+    base: usize,
+    sections: Vec<Section>,
+    /// The offset of the resource directory in `data`
+    resources: Option<usize>,
+}
+
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+/// Returns whether the file has the extension of a PE file containing icons, like `.exe` or `.dll`
+pub fn has_pe_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| PE_EXTENSIONS.iter().any(|pe_ext| ext.eq_ignore_ascii_case(pe_ext)))
+        .unwrap_or(false)
+}
+
+/// Extracts an icon from a PE file as PNG, the image which fits `size` best is used.
+/// The index is interpreted like in `ExtractIconEx`: a positive index selects the icon group by its position,
+/// a negative one selects the icon group with the resource ID `-index`
+pub fn get_icon(path: &Path, index: i32, size: i32) -> Result<Vec<u8>, Error> {
+    let pe_file = PeFile::open(path)?;
+    let groups = pe_file.icon_groups();
+    let group = if index < 0 {
+        let id = u16::try_from(index.unsigned_abs()).map_err(|_| Error::invalid_input(&path.to_string_lossy(), format!("The resource ID {} is out of range", index)))?;
+        groups.iter().find(|group| group.id == ResourceId::Id(id))
+    } else {
+        groups.get(index as usize)
+    };
+    let group = group.ok_or_else(|| Error::not_found(&path.to_string_lossy(), Some(size), format!("Icon {} not found", index)))?;
+    pe_file.get_icon(group, size)
+}
+
+impl PeFile {
+    /// Reads the headers and the section containing the resources, executables like installers may be gigabytes large
+    pub fn open(path: &Path) -> Result<PeFile, Error> {
+        let mut file = File::open(path)?;
+        let mut dos_header = [0; 64];
+        read_header(&mut file, &mut dos_header, "No PE file")?;
+        if &dos_header[..2] != b"MZ" {
+            return Err(invalid_data("No PE file"));
+        }
+        let pe_header = read_u32(&dos_header, 0x3c).ok_or_else(|| invalid_data("No PE file"))?;
+        file.seek(SeekFrom::Start(pe_header as u64))?;
+        let mut headers = vec![0; 24];
+        read_header(&mut file, &mut headers, "No PE file")?;
+        let number_of_sections = read_u16(&headers, 6).ok_or_else(|| invalid_data("Invalid COFF header"))? as usize;
+        let optional_header_size = read_u16(&headers, 20).ok_or_else(|| invalid_data("Invalid COFF header"))? as usize;
+        headers.resize(24 + optional_header_size + number_of_sections * 40, 0);
+        read_header(&mut file, &mut headers[24..], "Invalid section table")?;
+        let (sections, resource_rva) = parse_headers(&headers)?;
+
+        let Some(section) = resource_rva.and_then(|rva| find_section(&sections, rva)) else {
+            return Ok(PeFile { data: Vec::new(), base: 0, sections, resources: None });
+        };
+        let base = section.raw_offset as usize;
+        let mut data = Vec::new();
+        file.seek(SeekFrom::Start(base as u64))?;
+        // the section may be truncated
+        file.take(section.raw_size as u64).read_to_end(&mut data)?;
+        let mut pe_file = PeFile { data, base, sections, resources: None };
+        pe_file.resources = resource_rva.and_then(|rva| pe_file.rva_to_offset(rva));
+        Ok(pe_file)
+    }
+
+    pub fn parse(data: Vec<u8>) -> Result<PeFile, Error> {
+        if data.get(..2) != Some(b"MZ") {
+            return Err(invalid_data("No PE file"));
+        }
+        let pe_header = read_u32(&data, 0x3c).ok_or_else(|| invalid_data("No PE file"))? as usize;
+        let (sections, resource_rva) = parse_headers(data.get(pe_header..).unwrap_or_default())?;
+        let mut pe_file = PeFile { data, base: 0, sections, resources: None };
+        pe_file.resources = resource_rva.and_then(|rva| pe_file.rva_to_offset(rva));
+        Ok(pe_file)
+    }
+
+    /// Lists all icon groups in the order of the resource directory
+    pub fn icon_groups(&self) -> Vec<IconGroup> {
+        self.resources_of_type(RT_GROUP_ICON)
+            .into_iter()
+            .filter_map(|(id, data)| {
+                let count = read_u16(data, 4)? as usize;
+                let entries = (0..count)
+                    .filter_map(|i| {
+                        // GRPICONDIRENTRY
+                        let entry = data.get(6 + i * 14..6 + (i + 1) * 14)?;
+                        let id = read_u16(entry, 12)?;
+                        let image = self.icon_image(id)?;
+                        let is_png = image.starts_with(PNG_SIGNATURE);
+                        let (width, height) = if is_png {
+                            (read_u32_be(image, 16)?, read_u32_be(image, 20)?)
+                        } else {
+                            (if entry[0] == 0 { 256 } else { entry[0] as u32 }, if entry[1] == 0 { 256 } else { entry[1] as u32 })
+                        };
+                        Some(IconEntry { id, width, height, bit_count: read_u16(entry, 6)?, is_png })
+                    })
+                    .collect();
+                Some(IconGroup { id, entries })
+            })
+            .collect()
+    }
+
+    /// Returns the raw data of an `RT_ICON` resource, which is either a PNG file or a DIB without file header
+    pub fn icon_image(&self, id: u16) -> Option<&[u8]> {
+        self.resources_of_type(RT_ICON)
+            .into_iter()
+            .find(|(resource_id, _)| *resource_id == ResourceId::Id(id))
+            .map(|(_, data)| data)
+    }
+
+    /// Returns the image of the icon group which fits `size` best as PNG. This is the smallest image
+    /// which is at least as large as `size` with the highest color depth, or the largest one
+    pub fn get_icon(&self, group: &IconGroup, size: i32) -> Result<Vec<u8>, Error> {
+        let min_size = size.max(0) as u32;
+        let entry = group
+            .entries
+            .iter()
+            .filter(|entry| entry.width >= min_size)
+            .min_by_key(|entry| (entry.width, u16::MAX - entry.bit_count))
+            .or_else(|| group.entries.iter().max_by_key(|entry| (entry.width, entry.bit_count)))
+            .ok_or_else(|| Error::not_found("", Some(size), "Icon group is empty"))?;
+        self.get_png(entry)
+    }
+
+    /// Returns an image of an icon group as PNG
+    pub fn get_png(&self, entry: &IconEntry) -> Result<Vec<u8>, Error> {
+        let image = self.icon_image(entry.id).ok_or_else(|| Error::not_found("", None, format!("Icon image {} not found", entry.id)))?;
+        ico::IconEntry::from_data(image.to_vec())?.to_png()
+    }
+
+    /// Returns the resources of a type with their IDs, for each resource the first language is used
+    fn resources_of_type(&self, resource_type: u32) -> Vec<(ResourceId, &[u8])> {
+        let Some(root) = self.resources else {
+            return Vec::new();
+        };
+        let Some(type_dir) = self
+            .directory_entries(root)
+            .into_iter()
+            .find(|(id, _)| *id == ResourceId::Id(resource_type as u16))
+            .and_then(|(_, offset)| offset.subdirectory())
+        else {
+            return Vec::new();
+        };
+        self.directory_entries(root + type_dir)
+            .into_iter()
+            .filter_map(|(id, offset)| {
+                let language_dir = offset.subdirectory()?;
+                let (_, data_entry) = self.directory_entries(root + language_dir).into_iter().next()?;
+                let data_entry = root + data_entry.0 as usize;
+                let rva = read_u32(&self.data, data_entry)?;
+                let size = read_u32(&self.data, data_entry + 4)? as usize;
+                let offset = self.rva_to_offset(rva)?;
+                Some((id, self.data.get(offset..offset + size)?))
+            })
+            .collect()
+    }
+
+    /// Reads the entries of an `IMAGE_RESOURCE_DIRECTORY`
+    fn directory_entries(&self, offset: usize) -> Vec<(ResourceId, EntryOffset)> {
+        let (Some(named), Some(ids)) = (read_u16(&self.data, offset + 12), read_u16(&self.data, offset + 14)) else {
+            return Vec::new();
+        };
+        (0..named as usize + ids as usize)
+            .filter_map(|i| {
+                let entry = offset + 16 + i * 8;
+                let name = read_u32(&self.data, entry)?;
+                let id = if name & 0x8000_0000 != 0 {
+                    // the name is stored as length prefixed UTF-16 string
+                    let name_offset = self.resources? + (name & 0x7fff_ffff) as usize;
+                    let len = read_u16(&self.data, name_offset)? as usize;
+                    let chars = (0..len).map(|c| read_u16(&self.data, name_offset + 2 + c * 2)).collect::<Option<Vec<_>>>()?;
+                    ResourceId::Name(String::from_utf16_lossy(&chars))
+                } else {
+                    ResourceId::Id(name as u16)
+                };
+                Some((id, EntryOffset(read_u32(&self.data, entry + 4)?)))
+            })
+            .collect()
+    }
+
+    /// The offset of an RVA in `data`
+    fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        let section = find_section(&self.sections, rva)?;
+        ((rva - section.virtual_address) as usize + section.raw_offset as usize).checked_sub(self.base)
+    }
+}
+
+/// Reads the section table and the RVA of the resource directory, `headers` start with the PE signature
+fn parse_headers(headers: &[u8]) -> Result<(Vec<Section>, Option<u32>), Error> {
+    if headers.get(..4) != Some(b"PE\0\0") {
+        return Err(invalid_data("No PE file"));
+    }
+    let coff_header = 4;
+    let number_of_sections = read_u16(headers, coff_header + 2).ok_or_else(|| invalid_data("Invalid COFF header"))?;
+    let optional_header_size = read_u16(headers, coff_header + 16).ok_or_else(|| invalid_data("Invalid COFF header"))?;
+    let optional_header = coff_header + 20;
+    // PE32 or PE32+
+    let data_directories = match read_u16(headers, optional_header) {
+        Some(0x10b) => optional_header + 96,
+        Some(0x20b) => optional_header + 112,
+        _ => return Err(invalid_data("Invalid optional header")),
+    };
+    let number_of_directories = read_u32(headers, data_directories - 4).unwrap_or(0) as usize;
+
+    let section_table = optional_header + optional_header_size as usize;
+    let sections = (0..number_of_sections as usize)
+        .map(|i| section_table + i * 40)
+        .map(|section| {
+            Some(Section {
+                virtual_size: read_u32(headers, section + 8)?,
+                virtual_address: read_u32(headers, section + 12)?,
+                raw_size: read_u32(headers, section + 16)?,
+                raw_offset: read_u32(headers, section + 20)?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| invalid_data("Invalid section table"))?;
+
+    let resource_rva = if number_of_directories > RESOURCE_DIRECTORY {
+        read_u32(headers, data_directories + RESOURCE_DIRECTORY * 8).filter(|rva| *rva != 0)
+    } else {
+        None
+    };
+    Ok((sections, resource_rva))
+}
+
+fn find_section(sections: &[Section], rva: u32) -> Option<&Section> {
+    sections
+        .iter()
+        .find(|section| rva >= section.virtual_address && rva - section.virtual_address < section.virtual_size.max(section.raw_size))
+}
+
+/// Reads a header, a file which is too short is no valid PE file
+fn read_header(file: &mut File, header: &mut [u8], message: &str) -> Result<(), Error> {
+    file.read_exact(header).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => invalid_data(message),
+        _ => err.into(),
+    })
+}
+
+/// The offset of a resource directory entry, the high bit is set for subdirectories
+struct EntryOffset(u32);
+
+impl EntryOffset {
+    fn subdirectory(&self) -> Option<usize> {
+        (self.0 & 0x8000_0000 != 0).then_some((self.0 & 0x7fff_ffff) as usize)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::decode("", message, None)
+}
@@ -0,0 +1,388 @@
+//! Parsing property lists like the `Info.plist` of macOS bundles, in the XML and in the binary format.
+use std::cell::Cell;
+
+use crate::Error;
+
+const BINARY_MAGIC: &[u8] = b"bplist00";
+const MAX_DEPTH: usize = 64;
+/// Objects may be referenced several times, like the keys of dictionaries in an array, but each object should not be
+/// visited much more often than that, or shared references could make the parsing time grow exponentially
+const VISITS_PER_OBJECT: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Real(f64),
+    Bool(bool),
+    Data(Vec<u8>),
+    /// Seconds since 2001-01-01
+    Date(f64),
+    Array(Vec<Value>),
+    Dict(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Returns the value of a key of a dictionary
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(entries) => entries.iter().find(|(entry_key, _)| entry_key == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse(data: &[u8]) -> Result<Value, Error> {
+    if data.starts_with(BINARY_MAGIC) {
+        BinaryParser::new(data)?.parse()
+    } else {
+        let text = std::str::from_utf8(data)?;
+        let mut parser = XmlParser { text, position: 0 };
+        parser.parse()
+    }
+}
+
+struct XmlParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+enum Tag<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    Empty(&'a str),
+}
+
+impl<'a> XmlParser<'a> {
+    fn parse(&mut self) -> Result<Value, Error> {
+        loop {
+            match self.next_tag()? {
+                Some(Tag::Open("plist")) => break,
+                Some(_) => continue,
+                None => return Err(invalid_data("No property list")),
+            }
+        }
+        let tag = self.next_tag()?.ok_or_else(|| invalid_data("Empty property list"))?;
+        self.parse_value(tag, 0)
+    }
+
+    fn parse_value(&mut self, tag: Tag<'a>, depth: usize) -> Result<Value, Error> {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("Property list is nested too deeply"));
+        }
+        let value = match tag {
+            Tag::Empty("string") => Value::String(String::new()),
+            Tag::Empty("data") => Value::Data(Vec::new()),
+            Tag::Empty("array") => Value::Array(Vec::new()),
+            Tag::Empty("dict") => Value::Dict(Vec::new()),
+            Tag::Empty("true") => Value::Bool(true),
+            Tag::Empty("false") => Value::Bool(false),
+            Tag::Open("string") => Value::String(self.text_until("string")?),
+            Tag::Open("integer") => {
+                let text = self.text_until("integer")?;
+                let text = text.trim();
+                let integer = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+                    Some(hex) => i64::from_str_radix(hex, 16).ok(),
+                    None => text.parse().ok(),
+                };
+                Value::Integer(integer.ok_or_else(|| invalid_data("Invalid integer"))?)
+            }
+            Tag::Open("real") => Value::Real(self.text_until("real")?.trim().parse().map_err(|_| invalid_data("Invalid real"))?),
+            Tag::Open("data") => Value::Data(decode_base64(&self.text_until("data")?)?),
+            // dates are not needed for bundles and only kept as text
+            Tag::Open("date") => Value::String(self.text_until("date")?),
+            Tag::Open("array") => {
+                let mut values = Vec::new();
+                loop {
+                    match self.next_tag()?.ok_or_else(|| invalid_data("Unterminated array"))? {
+                        Tag::Close("array") => break,
+                        tag => values.push(self.parse_value(tag, depth + 1)?),
+                    }
+                }
+                Value::Array(values)
+            }
+            Tag::Open("dict") => {
+                let mut entries = Vec::new();
+                loop {
+                    let key = match self.next_tag()?.ok_or_else(|| invalid_data("Unterminated dict"))? {
+                        Tag::Close("dict") => break,
+                        Tag::Open("key") => self.text_until("key")?,
+                        Tag::Empty("key") => String::new(),
+                        _ => return Err(invalid_data("Expected key in dict")),
+                    };
+                    let tag = self.next_tag()?.ok_or_else(|| invalid_data("Unterminated dict"))?;
+                    entries.push((key, self.parse_value(tag, depth + 1)?));
+                }
+                Value::Dict(entries)
+            }
+            _ => return Err(invalid_data("Unexpected element in property list")),
+        };
+        Ok(value)
+    }
+
+    /// Returns the next tag, the XML declaration, the doctype, comments and text between tags are skipped
+    fn next_tag(&mut self) -> Result<Option<Tag<'a>>, Error> {
+        loop {
+            let Some(start) = self.text[self.position..].find('<') else {
+                return Ok(None);
+            };
+            let rest = &self.text[self.position + start..];
+            let (skip_end, is_tag) = if rest.starts_with("<!--") {
+                ("-->", false)
+            } else if rest.starts_with("<?") {
+                ("?>", false)
+            } else if rest.starts_with("<!") {
+                (">", false)
+            } else {
+                (">", true)
+            };
+            let end = rest.find(skip_end).ok_or_else(|| invalid_data("Unterminated tag"))?;
+            self.position += start + end + skip_end.len();
+            if !is_tag {
+                continue;
+            }
+            let tag = &rest[1..end];
+            return Ok(Some(if let Some(name) = tag.strip_prefix('/') {
+                Tag::Close(name.trim())
+            } else if let Some(tag) = tag.strip_suffix('/') {
+                Tag::Empty(element_name(tag))
+            } else {
+                Tag::Open(element_name(tag))
+            }));
+        }
+    }
+
+    /// Reads the text content of an element up to its closing tag
+    fn text_until(&mut self, name: &str) -> Result<String, Error> {
+        let mut text = String::new();
+        loop {
+            let rest = &self.text[self.position..];
+            if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").ok_or_else(|| invalid_data("Unterminated CDATA section"))?;
+                text.push_str(&cdata[..end]);
+                self.position += "<![CDATA[".len() + end + 3;
+                continue;
+            }
+            let end = rest.find('<').ok_or_else(|| invalid_data("Unterminated element"))?;
+            text.push_str(&unescape(&rest[..end])?);
+            self.position += end;
+            if !self.text[self.position..].starts_with("<![CDATA[") {
+                break;
+            }
+        }
+        match self.next_tag()? {
+            Some(Tag::Close(close)) if close == name => Ok(text),
+            _ => Err(invalid_data(&format!("Expected </{}>", name))),
+        }
+    }
+}
+
+fn element_name(tag: &str) -> &str {
+    tag.split_whitespace().next().unwrap_or_default()
+}
+
+fn unescape(text: &str) -> Result<String, Error> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or_else(|| invalid_data("Invalid entity"))?;
+        let entity = &rest[start + 1..start + end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|decimal| decimal.parse()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        result.push(c.ok_or_else(|| invalid_data(&format!("Invalid entity &{};", entity)))?);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(invalid_data("Invalid base64 data")),
+        };
+        bits = bits << 6 | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            data.push((bits >> bit_count) as u8);
+        }
+    }
+    Ok(data)
+}
+
+struct BinaryParser<'a> {
+    data: &'a [u8],
+    offset_size: usize,
+    ref_size: usize,
+    offsets: usize,
+    object_count: usize,
+    top_object: usize,
+    /// The number of objects which may still be parsed
+    budget: Cell<usize>,
+}
+
+impl<'a> BinaryParser<'a> {
+    fn new(data: &'a [u8]) -> Result<BinaryParser<'a>, Error> {
+        let trailer = data
+            .len()
+            .checked_sub(32)
+            .filter(|&trailer| trailer >= BINARY_MAGIC.len())
+            .ok_or_else(|| invalid_data("Binary property list is too short"))?;
+        let read = |offset: usize| read_be(&data[trailer + offset..trailer + offset + 8]) as usize;
+        if read(8) > data.len() || read(24) > data.len() {
+            return Err(invalid_data("Invalid binary property list trailer"));
+        }
+        let object_count = read(8);
+        Ok(BinaryParser {
+            data,
+            offset_size: data[trailer + 6] as usize,
+            ref_size: data[trailer + 7] as usize,
+            object_count,
+            top_object: read(16),
+            offsets: read(24),
+            budget: Cell::new(object_count.saturating_mul(VISITS_PER_OBJECT)),
+        })
+    }
+
+    fn parse(&self) -> Result<Value, Error> {
+        self.parse_object(self.top_object, 0)
+    }
+
+    fn parse_object(&self, index: usize, depth: usize) -> Result<Value, Error> {
+        if depth > MAX_DEPTH || index >= self.object_count {
+            return Err(invalid_data("Invalid object reference"));
+        }
+        let budget = self.budget.get().checked_sub(1).ok_or_else(|| invalid_data("Too many object references"))?;
+        self.budget.set(budget);
+        let offset = self.offsets + index * self.offset_size;
+        let offset = read_be(self.bytes(offset, self.offset_size)?) as usize;
+        if offset >= self.data.len() {
+            return Err(invalid_data("Invalid object offset"));
+        }
+        let marker = *self.bytes(offset, 1)?.first().unwrap_or(&0);
+        let info = (marker & 0x0f) as usize;
+        let value = match marker >> 4 {
+            0x0 if marker == 0x08 => Value::Bool(false),
+            0x0 if marker == 0x09 => Value::Bool(true),
+            0x1 => {
+                let len = 1 << info;
+                Value::Integer(read_be(self.bytes(offset + 1, len)?) as i64)
+            }
+            0x2 | 0x3 => {
+                let bytes = self.bytes(offset + 1, 1 << info)?;
+                let real = match bytes.len() {
+                    4 => f32::from_be_bytes(bytes.try_into().unwrap_or_default()) as f64,
+                    8 => f64::from_be_bytes(bytes.try_into().unwrap_or_default()),
+                    _ => return Err(invalid_data("Invalid real")),
+                };
+                if marker >> 4 == 0x2 {
+                    Value::Real(real)
+                } else {
+                    Value::Date(real)
+                }
+            }
+            0x4 => {
+                let (start, len) = self.length(offset, info)?;
+                Value::Data(self.bytes(start, len)?.to_vec())
+            }
+            0x5 => {
+                let (start, len) = self.length(offset, info)?;
+                Value::String(self.bytes(start, len)?.iter().map(|&c| c as char).collect())
+            }
+            0x6 => {
+                let (start, len) = self.length(offset, info)?;
+                let len = len.checked_mul(2).ok_or_else(|| invalid_data("Invalid length"))?;
+                let chars: Vec<u16> = self.bytes(start, len)?.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                Value::String(String::from_utf16_lossy(&chars))
+            }
+            0xa => {
+                let (start, len) = self.length(offset, info)?;
+                let values = (0..len)
+                    .map(|i| self.parse_object(self.reference(start + i * self.ref_size)?, depth + 1))
+                    .collect::<Result<_, _>>()?;
+                Value::Array(values)
+            }
+            0xd => {
+                let (start, len) = self.length(offset, info)?;
+                let entries = (0..len)
+                    .map(|i| {
+                        let key = match self.parse_object(self.reference(start + i * self.ref_size)?, depth + 1)? {
+                            Value::String(key) => key,
+                            _ => return Err(invalid_data("Dictionary key is no string")),
+                        };
+                        let value = self.parse_object(self.reference(start + (len + i) * self.ref_size)?, depth + 1)?;
+                        Ok((key, value))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Value::Dict(entries)
+            }
+            _ => return Err(invalid_data(&format!("Unsupported object type {:#x}", marker))),
+        };
+        Ok(value)
+    }
+
+    /// Returns the start of the content and the number of elements of an object, a count of 15 means that
+    /// an integer object with the count follows the marker
+    fn length(&self, offset: usize, info: usize) -> Result<(usize, usize), Error> {
+        if info != 0x0f {
+            return Ok((offset + 1, info));
+        }
+        let marker = *self.bytes(offset + 1, 1)?.first().unwrap_or(&0);
+        if marker >> 4 != 0x1 {
+            return Err(invalid_data("Invalid length"));
+        }
+        let len = 1 << (marker & 0x0f);
+        let count = read_be(self.bytes(offset + 2, len)?) as usize;
+        if count > self.data.len() {
+            return Err(invalid_data("Invalid length"));
+        }
+        Ok((offset + 2 + len, count))
+    }
+
+    fn reference(&self, offset: usize) -> Result<usize, Error> {
+        Ok(read_be(self.bytes(offset, self.ref_size)?) as usize)
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], Error> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| invalid_data("Binary property list is truncated"))
+    }
+}
+
+fn read_be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |value, &byte| value << 8 | byte as u64)
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::decode("", message, None)
+}
@@ -0,0 +1,372 @@
+//! Reading Windows shortcuts: Shell Links (`.lnk`) and Internet Shortcuts (`.url`).
+//!
+//! The parsers work the same on every platform, so the icons of shortcuts in Windows user folders
+//! can be shown on other systems. Windows paths are resolved relative to the shortcut, if the target
+//! is not found on the local system.
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{ico, pe, Error};
+
+const LNK_HEADER_SIZE: usize = 0x4c;
+const LNK_CLSID: [u8; 16] = [0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46];
+
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x01;
+const HAS_LINK_INFO: u32 = 0x02;
+const HAS_NAME: u32 = 0x04;
+const HAS_RELATIVE_PATH: u32 = 0x08;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+const HAS_EXP_STRING: u32 = 0x200;
+const HAS_EXP_ICON: u32 = 0x4000;
+
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x01;
+const COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX: u32 = 0x02;
+
+const ENVIRONMENT_VARIABLE_DATA_BLOCK: u32 = 0xa000_0001;
+const ICON_ENVIRONMENT_DATA_BLOCK: u32 = 0xa000_0007;
+/// The file entry extension block of a shell item, which contains the long name
+const FILE_ENTRY_EXTENSION: u32 = 0xbeef_0004;
+
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+
+/// The icon file of a shortcut and the index of the icon in it, as in `ExtractIconEx`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconLocation {
+    pub path: String,
+    pub index: i32,
+}
+
+/// The content of a `.lnk` or `.url` file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shortcut {
+    /// The Windows path of the target like `C:\Windows\notepad.exe`, or the URL of an Internet Shortcut
+    pub target: Option<String>,
+    /// The path of the target relative to the shortcut
+    pub relative_path: Option<String>,
+    pub working_dir: Option<String>,
+    pub arguments: Option<String>,
+    pub description: Option<String>,
+    /// The explicit icon, environment variables like `%SystemRoot%` are not expanded
+    pub icon_location: Option<IconLocation>,
+    pub is_directory: bool,
+}
+
+/// The icon of a shortcut as PNG together with the target of the shortcut
+#[derive(Debug, Clone)]
+pub struct ShortcutIcon {
+    pub icon: Vec<u8>,
+    pub target: Option<String>,
+}
+
+/// Returns whether the file has the extension `.lnk` or `.url`
+pub fn is_shortcut(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("lnk") || ext.eq_ignore_ascii_case("url"))
+        .unwrap_or(false)
+}
+
+/// Extracts the icon of a shortcut as PNG. The explicit icon location is preferred, otherwise the icon
+/// of the target is used, if the target is an executable, a library or an icon file
+pub fn get_icon(path: &Path, size: i32) -> Result<ShortcutIcon, Error> {
+    let shortcut = Shortcut::open(path)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    get_icon_with(&shortcut, size, |windows_path| resolve_path(dir, windows_path))
+        .map(|icon| ShortcutIcon { icon, target: shortcut.target.clone() })
+        .ok_or_else(|| Error::not_found(&path.to_string_lossy(), Some(size), "No icon found for the shortcut"))
+}
+
+/// Extracts the icon of a shortcut, `resolve` maps Windows paths to local paths
+pub(crate) fn get_icon_with<F>(shortcut: &Shortcut, size: i32, resolve: F) -> Option<Vec<u8>>
+where
+    F: Fn(&str) -> Option<PathBuf>,
+{
+    let explicit = shortcut.icon_location.iter().filter_map(|location| Some((resolve(&location.path)?, location.index)));
+    let target = shortcut
+        .relative_path
+        .iter()
+        .chain(shortcut.target.iter())
+        .filter_map(|target| resolve(target))
+        .map(|target| (target, 0));
+    explicit.chain(target).find_map(|(file, index)| extract_icon(&file, index, size).ok())
+}
+
+/// Extracts an icon from an executable, a library or an icon file
+pub(crate) fn extract_icon(file: &Path, index: i32, size: i32) -> Result<Vec<u8>, Error> {
+    let is_ico = file
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("ico") || ext.eq_ignore_ascii_case("cur"))
+        .unwrap_or(false);
+    if is_ico {
+        ico::get_icon(file, size)
+    } else {
+        pe::get_icon(file, index, size)
+    }
+}
+
+/// Maps a Windows path to a local path. Environment variables are expanded, relative paths are resolved
+/// relative to `dir`, absolute paths are only used if they exist on the local system
+fn resolve_path(dir: &Path, windows_path: &str) -> Option<PathBuf> {
+    let expanded = expand_environment(windows_path, |name| env::var(name).ok());
+    let path = PathBuf::from(&expanded);
+    if path.exists() {
+        return Some(path);
+    }
+    // variables may expand to local paths, which are followed by Windows separators
+    let local = expanded.replace('\\', "/");
+    if local.starts_with('/') {
+        return Some(PathBuf::from(local)).filter(|path| !expanded.starts_with('\\') && path.exists());
+    }
+    let relative = dir.join(local);
+    (expanded.get(1..2) != Some(":") && relative.exists()).then_some(relative)
+}
+
+/// Replaces `%NAME%` with the value of the variable, unknown variables are kept
+pub(crate) fn expand_environment<F>(path: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        result.push_str(&rest[..start]);
+        match lookup(name) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+impl Shortcut {
+    /// Reads a `.url` file as Internet Shortcut and every other file as Shell Link
+    pub fn open(path: &Path) -> Result<Shortcut, Error> {
+        let data = fs::read(path)?;
+        let is_url = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.eq_ignore_ascii_case("url")).unwrap_or(false);
+        if is_url {
+            Shortcut::parse_url(&String::from_utf8_lossy(&data))
+        } else {
+            Shortcut::parse_lnk(&data)
+        }
+    }
+
+    /// Parses a Shell Link in the binary format of `.lnk` files
+    pub fn parse_lnk(data: &[u8]) -> Result<Shortcut, Error> {
+        if read_u32(data, 0) != Some(LNK_HEADER_SIZE as u32) || data.get(4..20) != Some(&LNK_CLSID[..]) {
+            return Err(invalid_data("No Shell Link"));
+        }
+        let flags = read_u32(data, 0x14).unwrap_or(0);
+        let attributes = read_u32(data, 0x18).unwrap_or(0);
+        let icon_index = read_u32(data, 0x38).unwrap_or(0) as i32;
+        let unicode = flags & IS_UNICODE != 0;
+        let truncated = || invalid_data("Shell Link is truncated");
+
+        let mut position = LNK_HEADER_SIZE;
+        let mut id_list_target = None;
+        if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+            let size = read_u16(data, position).ok_or_else(truncated)? as usize;
+            id_list_target = parse_id_list(data.get(position + 2..position + 2 + size).ok_or_else(truncated)?);
+            position += 2 + size;
+        }
+        let mut link_info_target = None;
+        if flags & HAS_LINK_INFO != 0 {
+            let size = read_u32(data, position).ok_or_else(truncated)? as usize;
+            link_info_target = parse_link_info(data.get(position..position + size).ok_or_else(truncated)?);
+            position += size;
+        }
+        let mut strings = Vec::new();
+        for flag in [HAS_NAME, HAS_RELATIVE_PATH, HAS_WORKING_DIR, HAS_ARGUMENTS, HAS_ICON_LOCATION] {
+            if flags & flag == 0 {
+                strings.push(None);
+                continue;
+            }
+            let len = read_u16(data, position).ok_or_else(truncated)? as usize;
+            let bytes = if unicode { len * 2 } else { len };
+            let string = data.get(position + 2..position + 2 + bytes).ok_or_else(truncated)?;
+            strings.push(Some(if unicode { decode_utf16(string) } else { decode_ansi(string) }));
+            position += 2 + bytes;
+        }
+        let (mut environment_target, mut environment_icon) = (None, None);
+        while let (Some(size), Some(signature)) = (read_u32(data, position), read_u32(data, position + 4)) {
+            let size = size as usize;
+            let Some(block) = data.get(position..position + size).filter(|_| size >= 8) else {
+                break;
+            };
+            match signature {
+                ENVIRONMENT_VARIABLE_DATA_BLOCK => environment_target = parse_environment_block(block),
+                ICON_ENVIRONMENT_DATA_BLOCK => environment_icon = parse_environment_block(block),
+                _ => {}
+            }
+            position += size;
+        }
+
+        let [description, relative_path, working_dir, arguments, icon_location]: [Option<String>; 5] =
+            strings.try_into().unwrap_or_default();
+        let icon_path = environment_icon.filter(|_| flags & HAS_EXP_ICON != 0).or(icon_location);
+        let target = link_info_target.or(environment_target.filter(|_| flags & HAS_EXP_STRING != 0)).or(id_list_target);
+        Ok(Shortcut {
+            target,
+            relative_path,
+            working_dir,
+            arguments,
+            description,
+            icon_location: icon_path.filter(|path| !path.is_empty()).map(|path| IconLocation { path, index: icon_index }),
+            is_directory: attributes & FILE_ATTRIBUTE_DIRECTORY != 0,
+        })
+    }
+
+    /// Parses the INI format of `.url` files, the keys of the `[InternetShortcut]` section are used
+    pub fn parse_url(text: &str) -> Result<Shortcut, Error> {
+        let mut in_section = false;
+        let mut found = false;
+        let (mut url, mut icon_file, mut icon_index) = (None, None, 0);
+        for line in text.lines().map(|line| line.trim()) {
+            if line.starts_with('[') {
+                in_section = line.eq_ignore_ascii_case("[InternetShortcut]");
+                found |= in_section;
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=').filter(|_| in_section) else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "url" => url = Some(value),
+                "iconfile" => icon_file = Some(value),
+                "iconindex" => icon_index = value.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+        if !found {
+            return Err(invalid_data("No Internet Shortcut"));
+        }
+        Ok(Shortcut {
+            target: url,
+            icon_location: icon_file.filter(|path| !path.is_empty()).map(|path| IconLocation { path, index: icon_index }),
+            ..Default::default()
+        })
+    }
+}
+
+/// Builds the path from the shell items of a `LinkTargetIDList`. Only items of the file system are
+/// supported: the "My Computer" root, volumes and file entries
+fn parse_id_list(data: &[u8]) -> Option<String> {
+    let mut path = String::new();
+    let mut position = 0;
+    while let Some(size) = read_u16(data, position).filter(|&size| size >= 2) {
+        let item = data.get(position + 2..position + size as usize)?;
+        position += size as usize;
+        let item_type = *item.first()?;
+        match item_type & 0x70 {
+            // root folder like "My Computer"
+            0x10 => {}
+            // volume, the name is like "C:\"
+            0x20 => path = decode_ansi(item.get(1..)?),
+            0x30 => {
+                let name = long_name(item).or_else(|| Some(decode_ansi(item.get(12..)?)))?;
+                if !path.is_empty() && !path.ends_with('\\') {
+                    path.push('\\');
+                }
+                path.push_str(&name);
+            }
+            _ => return None,
+        }
+    }
+    (!path.is_empty()).then_some(path)
+}
+
+/// Reads the long name from the extension block of a file entry shell item
+fn long_name(item: &[u8]) -> Option<String> {
+    // the primary name is a null terminated ANSI string, padded to an even size
+    let name_len = item.get(12..)?.iter().position(|&c| c == 0)? + 1;
+    let extension = 12 + name_len + name_len % 2;
+    let block = item.get(extension..)?;
+    if read_u32(block, 4)? != FILE_ENTRY_EXTENSION {
+        return None;
+    }
+    let version = read_u16(block, 2)?;
+    let offset = match version {
+        3..=6 => 20,
+        7 => 38,
+        8 => 42,
+        _ => 46,
+    };
+    Some(decode_utf16(block.get(offset..)?)).filter(|name| !name.is_empty())
+}
+
+/// Returns the path of the target from the `LinkInfo` structure, which is a local path or a network path
+fn parse_link_info(data: &[u8]) -> Option<String> {
+    let header_size = read_u32(data, 4)?;
+    let flags = read_u32(data, 8)?;
+    let string_at = |ansi_offset: usize, unicode_offset: Option<usize>| match unicode_offset.and_then(|offset| read_u32(data, offset)) {
+        Some(offset) if offset != 0 => decode_utf16(data.get(offset as usize..).unwrap_or_default()),
+        _ => {
+            let offset = read_u32(data, ansi_offset).unwrap_or(0) as usize;
+            if offset == 0 {
+                String::new()
+            } else {
+                decode_ansi(data.get(offset..).unwrap_or_default())
+            }
+        }
+    };
+    let has_unicode = header_size >= 0x24;
+    let suffix = string_at(24, has_unicode.then_some(32));
+    let base = if flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+        string_at(16, has_unicode.then_some(28))
+    } else if flags & COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX != 0 {
+        let link = read_u32(data, 20)? as usize;
+        let link_data = data.get(link..)?;
+        let name_offset = read_u32(link_data, 8)?;
+        let unicode_offset = read_u32(link_data, 20).filter(|_| name_offset > 0x14).filter(|&offset| offset != 0);
+        match unicode_offset {
+            Some(offset) => decode_utf16(link_data.get(offset as usize..)?),
+            None => decode_ansi(link_data.get(name_offset as usize..)?),
+        }
+    } else {
+        return None;
+    };
+    Some(if suffix.is_empty() || base.ends_with('\\') { base + &suffix } else { format!("{}\\{}", base, suffix) })
+}
+
+/// Reads the path of an `EnvironmentVariableDataBlock` or an `IconEnvironmentDataBlock`,
+/// which contains 260 ANSI characters followed by 260 UTF-16 characters
+fn parse_environment_block(block: &[u8]) -> Option<String> {
+    let unicode = decode_utf16(block.get(268..788)?);
+    let path = if unicode.is_empty() { decode_ansi(block.get(8..268)?) } else { unicode };
+    (!path.is_empty()).then_some(path)
+}
+
+/// Decodes a UTF-16 string, which ends at a null character or the end of the data
+fn decode_utf16(data: &[u8]) -> String {
+    let chars: Vec<u16> = data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).take_while(|&c| c != 0).collect();
+    String::from_utf16_lossy(&chars)
+}
+
+/// Decodes an ANSI string as Latin-1, which ends at a null character or the end of the data
+fn decode_ansi(data: &[u8]) -> String {
+    data.iter().take_while(|&&c| c != 0).map(|&c| c as char).collect()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::decode("", message, None)
+}
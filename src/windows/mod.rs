@@ -1,25 +1,13 @@
 pub mod request;
 mod drop;
 
-use image::ImageError;
 use windows::core::Error as WinError;
 
-use crate::{Error, InnerError};
-
-impl From<ImageError> for Error {
-    fn from(error: ImageError) -> Self {
-        Error {
-            message: error.to_string(),
-            inner_error: InnerError::ImageError(error),
-        }
-    }
-}
+use crate::Error;
 
+/// A failing function of Windows
 impl From<WinError> for Error {
     fn from(error: WinError) -> Self {
-        Error {
-            message: error.to_string(),
-            inner_error: InnerError::WinError(error),
-        }
+        Error::BackendUnavailable { backend: "Windows", reason: error.message(), source: Some(Box::new(error)) }
     }
 }
\ No newline at end of file
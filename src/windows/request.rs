@@ -1,20 +1,25 @@
-use std::{io::Cursor, mem, ptr, thread, time::Duration };
+use std::{mem, path::Path, thread, time::Duration };
 use windows::{
-    core::PCWSTR,
+    core::{Error as WinError, PCWSTR},
     Win32::{
-        Foundation::FALSE, Graphics::Gdi::{
-            GetBitmapBits, GetObjectW, BITMAP, BITMAPINFOHEADER, HBITMAP
+        Graphics::Gdi::{
+            CreateCompatibleDC, GetDIBits, GetObjectW, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HBITMAP, HDC
         }, Storage::FileSystem::FILE_ATTRIBUTE_NORMAL, UI::{
             Shell::{
                 ExtractIconExW, SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, SHGFI_LARGEICON, SHGFI_SMALLICON, SHGFI_TYPENAME, SHGFI_USEFILEATTRIBUTES
         }, WindowsAndMessaging::{GetIconInfo, HICON, ICONINFO}}
     },
 };
-use image::ImageFormat;
 
-use crate::{windows::drop::{BitmapDropper, IconDropper}, Error};
+use crate::{ico::IconImage, pe, windows::drop::{BitmapDropper, DcDropper, IconDropper}, Error};
 
 pub fn get_icon(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
+    // the resources contain all sizes, ExtractIconExW only provides the large or the small icon
+    if ext.to_lowercase().ends_with(".exe") {
+        if let Ok(icon) = pe::get_icon(Path::new(ext), 0, size) {
+            return Ok(icon);
+        }
+    }
     let mut icon = if ext.to_lowercase().ends_with(".exe") {
         let mut icon = extract_icon(ext, size);
         if icon.is_invalid() {
@@ -34,126 +39,72 @@ pub fn get_icon(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
 
     let _icon_dropper = IconDropper(icon);
 
-    let mut icon_info = ICONINFO {
-        fIcon: FALSE,
-        hbmColor: HBITMAP::default(),
-        hbmMask: HBITMAP::default(),
-        xHotspot: 0,
-        yHotspot: 0,
-    };
+    let mut icon_info = ICONINFO::default();
     unsafe { GetIconInfo(icon, &mut icon_info)?; }
 
     let _color_dropper = BitmapDropper(icon_info.hbmColor);
     let _mask_dropper = BitmapDropper(icon_info.hbmMask);
 
-    let mut bmp_color = BITMAP {
-        bmBits: ptr::null_mut(),
-        bmBitsPixel: 0,
-        bmHeight: 0,
-        bmPlanes: 0,
-        bmType: 0,
-        bmWidth: 0,
-        bmWidthBytes: 0,
-    };
-    unsafe {GetObjectW(icon_info.hbmColor, mem::size_of_val(&bmp_color) as i32, Some(&mut bmp_color as *mut _ as *mut _)) }; 
-
-    let mut bmp_mask = BITMAP {
-        bmBits: ptr::null_mut(),
-        bmBitsPixel: 0,
-        bmHeight: 0,
-        bmPlanes: 0,
-        bmType: 0,
-        bmWidth: 0,
-        bmWidthBytes: 0,
-    };
-    unsafe {GetObjectW(icon_info.hbmMask, mem::size_of_val(&bmp_mask) as i32, Some(&mut bmp_mask as *mut _ as *mut _)) };
-
-    fn get_bitmap_count(bitmap: &BITMAP)->i32 {
-        let mut n_width_bytes = bitmap.bmWidthBytes;
-        // bitmap scanlines MUST be a multiple of 4 bytes when stored
-        // inside a bitmap resource, so round up if necessary
-        if n_width_bytes & 3 != 0 {
-            n_width_bytes = (n_width_bytes + 4) & !3;
+    let dc = unsafe { CreateCompatibleDC(HDC::default()) };
+    let _dc_dropper = DcDropper(dc);
+
+    let image = if icon_info.hbmColor.is_invalid() {
+        // monochrome icons contain the AND mask and the XOR bitmap one below the other in the mask bitmap
+        let (width, height, pixels) = get_bitmap_pixels(dc, icon_info.hbmMask)?;
+        let height = height / 2;
+        let count = width as usize * height as usize;
+        let rgba = (0..count)
+            .flat_map(|i| {
+                let xor = pixels[(count + i) * 4];
+                if pixels[i * 4] != 0 { [0, 0, 0, 0] } else { [xor, xor, xor, 255] }
+            })
+            .collect();
+        IconImage::from_rgba(width, height, rgba)?
+    } else {
+        let (width, height, mut pixels) = get_bitmap_pixels(dc, icon_info.hbmColor)?;
+        // icons without alpha channel use the AND mask for transparency
+        let mask = if pixels.chunks(4).any(|pixel| pixel[3] != 0) {
+            None
+        } else {
+            Some(get_bitmap_pixels(dc, icon_info.hbmMask)?.2)
+        };
+        for (i, pixel) in pixels.chunks_mut(4).enumerate() {
+            pixel.swap(0, 2);
+            if let Some(mask) = &mask {
+                pixel[3] = if mask[i * 4] != 0 { 0 } else { 255 };
+            }
         }
-    
-        n_width_bytes * bitmap.bmHeight
-    }
-
-    let icon_header_size = mem::size_of::<ICONHEADER>();
-    let icon_dir_size = mem::size_of::<ICONDIR>();
-    let info_header_size = mem::size_of::<BITMAPINFOHEADER>();
-    let bitmap_bytes_count = get_bitmap_count(&bmp_color) as usize;
-    let mask_bytes_count = get_bitmap_count(&bmp_mask) as usize;
-
-    let complete_size = icon_header_size + icon_dir_size + info_header_size + bitmap_bytes_count + mask_bytes_count;
-
-    let image_bytes_count = bitmap_bytes_count + mask_bytes_count;
-    let mut bytes = Vec::<u8>::with_capacity(complete_size);
-    unsafe { bytes.set_len(complete_size) };
-
-    let iconheader = ICONHEADER { 
-        id_reserved: 0, 
-        id_type: 1, // Type 1 = ICON (type 2 = CURSOR)
-        id_count: 1, // number of ICONDIRs
+        IconImage::from_rgba(width, height, pixels)?
     };
-    let byte_ptr: *mut u8 = unsafe {mem::transmute(&iconheader) };
-    unsafe {ptr::copy_nonoverlapping(byte_ptr, bytes.as_mut_ptr(), icon_header_size)}; 
-    let pos = icon_header_size;
-
-    let color_count = if bmp_color.bmBitsPixel >= 8 { 
-        0 
-    } else { 
-        1 << (bmp_color.bmBitsPixel * bmp_color.bmPlanes) 
-    };
-
-    // Create the ICONDIR structure
-    let icon_dir = ICONDIR {
-        b_width: bmp_color.bmWidth as u8,
-        b_height: bmp_color.bmHeight as u8,
-        b_color_count: color_count,
-        b_reserved: 0,
-        w_planes: bmp_color.bmPlanes,
-        w_bit_count: bmp_color.bmBitsPixel,
-        dw_image_offset: (icon_header_size + 16) as u32,
-        dw_bytes_in_res: (mem::size_of::<BITMAPINFOHEADER>() + image_bytes_count) as u32
-    };
-
-    let byte_ptr: *mut u8 = unsafe { mem::transmute(&icon_dir) };
-    unsafe { ptr::copy_nonoverlapping(byte_ptr, bytes[pos..].as_mut_ptr(), icon_dir_size) }; 
-    let pos = pos + icon_dir_size;
+    image.to_png()
+}
 
-    let bi_header = BITMAPINFOHEADER {
-        biSize: info_header_size as u32,
-        biWidth: bmp_color.bmWidth,
-        biHeight: bmp_color.bmHeight * 2, // height of color+mono
-        biPlanes: bmp_color.bmPlanes,
-        biBitCount: bmp_color.bmBitsPixel,
-        biSizeImage: image_bytes_count as u32,
-        biClrImportant: 0,
-        biClrUsed: 0,
-        biCompression: 0,
-        biXPelsPerMeter: 0,
-        biYPelsPerMeter: 0
+/// Reads the pixels of a bitmap as 32 bit BGRA, row by row beginning at the top
+fn get_bitmap_pixels(dc: HDC, bitmap: HBITMAP) -> Result<(u32, u32, Vec<u8>), Error> {
+    let mut bmp = BITMAP::default();
+    if unsafe { GetObjectW(bitmap, mem::size_of_val(&bmp) as i32, Some(&mut bmp as *mut _ as *mut _)) } == 0 {
+        return Err(Error::decode("", "Invalid icon bitmap", None));
+    }
+    let (width, height) = (bmp.bmWidth.unsigned_abs(), bmp.bmHeight.unsigned_abs());
+    let mut info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            // negative for top-down rows
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
     };
-    let byte_ptr: *mut u8 = unsafe {mem::transmute(&bi_header) };
-    unsafe { ptr::copy_nonoverlapping(byte_ptr, bytes[pos..].as_mut_ptr(), info_header_size) }; 
-    let pos = pos + info_header_size;
-
-    // write the RGBQUAD color table (for 16 and 256 colour icons)
-    if bmp_color.bmBitsPixel == 2 || bmp_color.bmBitsPixel == 8 {}        
-
-    write_icon_data_to_memory(&mut bytes[pos..], icon_info.hbmColor, 
-        &bmp_color, bitmap_bytes_count as usize);
-    let pos = pos + bitmap_bytes_count as usize;
-    write_icon_data_to_memory(&mut bytes[pos..], icon_info.hbmMask, 
-        &bmp_mask, mask_bytes_count as usize);
-
-    let im = image::load_from_memory(&bytes)?; // Assuming bytes contains valid icon data
-    let mut png_bytes: Vec<u8> = Vec::new();
-    let mut cursor = Cursor::new(&mut png_bytes);
-    im.write_to(&mut cursor, ImageFormat::Png)?;
-
-    Ok(png_bytes)
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    let lines = unsafe { GetDIBits(dc, bitmap, 0, height, Some(pixels.as_mut_ptr() as *mut _), &mut info, DIB_RGB_COLORS) };
+    if lines == 0 {
+        return Err(WinError::from_win32().into());
+    }
+    Ok((width, height, pixels))
 }
 
 fn get_icon_from_ext(ext: &str, size: i32) -> HICON {
@@ -205,57 +156,6 @@ fn extract_icon(path: &str, size: i32) -> HICON {
     icons[0]
 }
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-struct ICONHEADER {
-    id_reserved: i16, 
-    id_type: i16,
-    id_count: i16,
-}
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-struct ICONDIR {
-    b_width: u8,
-    b_height: u8,
-    b_color_count: u8,
-    b_reserved: u8,
-    w_planes: u16, // for cursors, this field = wXHotSpot
-    w_bit_count: u16, // for cursors, this field = wYHotSpot
-    dw_bytes_in_res: u32,
-    dw_image_offset: u32, // file-offset to the start of ICONIMAGE
-}
-
-fn write_icon_data_to_memory(mem: &mut [u8], h_bitmap: HBITMAP, bmp: &BITMAP, bitmap_byte_count: usize) {
-    unsafe {
-        let mut icon_data = Vec::<u8>::with_capacity(bitmap_byte_count);
-        icon_data.set_len(bitmap_byte_count);
-
-        GetBitmapBits(h_bitmap, bitmap_byte_count as i32, icon_data.as_mut_ptr() as *mut _);
-
-        // bitmaps are stored inverted (vertically) when on disk..
-        // so write out each line in turn, starting at the bottom + working
-        // towards the top of the bitmap. Also, the bitmaps are stored in packed
-        // in memory - scanlines are NOT 32bit aligned, just 1-after-the-other
-        let mut pos = 0;
-        let nd4 = |x: i32| (x + 3) & !3;
-        let amount_to_pad = (nd4(bmp.bmWidthBytes) - bmp.bmWidthBytes) as usize;
-        for i in (0..bmp.bmHeight).rev() {
-            // Write the bitmap scanline
-            
-            ptr::copy_nonoverlapping(icon_data[(i * bmp.bmWidthBytes) as usize..].as_ptr(), mem[pos..].as_mut_ptr(), bmp.bmWidthBytes as usize); // 1 line of BYTES
-            pos += bmp.bmWidthBytes as usize;
-
-            // extend to a 32bit boundary (in the file) if necessary
-            if amount_to_pad != 0 {
-                let padding: [u8; 4] = [0; 4];
-                ptr::copy_nonoverlapping(padding.as_ptr(), mem[pos..].as_mut_ptr(), amount_to_pad); 
-                pos += amount_to_pad;
-            }
-        }
-    }
-}
-
 fn utf_16_null_terminated(x: &str) -> Vec<u16> {
     x.encode_utf16().chain(std::iter::once(0)).collect()
 }
\ No newline at end of file
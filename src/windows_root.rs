@@ -0,0 +1,187 @@
+//! Icons of a Windows installation which is not the running system, like a mounted NTFS partition or a Windows share.
+//!
+//! Windows paths like `%SystemRoot%\system32\shell32.dll` are resolved inside of the root. Since Windows
+//! ignores the case of file names, the components of the paths are matched case insensitively.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    pe,
+    shortcut::{self, IconLocation, Shortcut},
+    Error,
+};
+
+/// The index of the closed folder icon in `shell32.dll`
+const FOLDER_ICON_INDEX: i32 = 3;
+
+/// The root directory of a Windows installation, which is the mounted system drive `C:`
+#[derive(Debug, Clone)]
+pub struct WindowsRoot {
+    root: PathBuf,
+}
+
+impl WindowsRoot {
+    pub fn new<P: Into<PathBuf>>(root: P) -> WindowsRoot {
+        WindowsRoot { root: root.into() }
+    }
+
+    /// Maps a Windows path to a path below the root. Environment variables of the system like `%SystemRoot%`,
+    /// `%windir%` or `%ProgramFiles%` are expanded, the drive letter is ignored. Returns `None` for network paths,
+    /// unknown variables and files which do not exist
+    pub fn resolve(&self, windows_path: &str) -> Option<PathBuf> {
+        let expanded = shortcut::expand_environment(windows_path, system_variable);
+        if expanded.contains('%') || expanded.starts_with("\\\\") {
+            return None;
+        }
+        let path = match expanded.get(1..2) {
+            Some(":") => &expanded[2..],
+            _ => &expanded,
+        };
+        resolve_in(&self.root, path)
+    }
+
+    /// Returns the icon of a file or directory below the root as PNG. The custom icons of folders in `desktop.ini`
+    /// are honoured, other folders get the folder icon of `shell32.dll`. Shortcuts show their explicit icon
+    /// or the icon of their target, executables, libraries and icon files their own icon.
+    pub fn get_icon_for_path(&self, path: &Path, size: i32) -> Result<Vec<u8>, Error> {
+        let not_found = || Error::not_found(&path.to_string_lossy(), Some(size), "No icon found");
+        if path.is_dir() {
+            if let Some(icon) = self.get_desktop_ini_icon(path, size) {
+                return Ok(icon);
+            }
+            return self.get_default_folder_icon(size);
+        }
+        let dir = path.parent().unwrap_or(Path::new("."));
+        if shortcut::is_shortcut(path) {
+            let shortcut = Shortcut::open(path)?;
+            shortcut::get_icon_with(&shortcut, size, |windows_path| self.resolve_from(dir, windows_path)).ok_or_else(not_found)
+        } else if pe::has_pe_extension(path) || is_icon_file(path) {
+            shortcut::extract_icon(path, 0, size)
+        } else {
+            Err(not_found())
+        }
+    }
+
+    /// Returns the default folder icon, since Windows 10 the icons of `shell32.dll` are stored in `shell32.dll.mun`
+    pub fn get_default_folder_icon(&self, size: i32) -> Result<Vec<u8>, Error> {
+        let shell32 = ["%SystemRoot%\\SystemResources\\shell32.dll.mun", "%SystemRoot%\\System32\\shell32.dll"]
+            .iter()
+            .filter_map(|path| self.resolve(path))
+            .find_map(|path| pe::get_icon(&path, FOLDER_ICON_INDEX, size).ok());
+        shell32.ok_or_else(|| Error::not_found(&self.root.to_string_lossy(), Some(size), "No shell32.dll found"))
+    }
+
+    /// Reads the icon of `[.ShellClassInfo]` in the `desktop.ini` of a folder
+    fn get_desktop_ini_icon(&self, dir: &Path, size: i32) -> Option<Vec<u8>> {
+        let desktop_ini = resolve_in(dir, "desktop.ini")?;
+        let location = parse_desktop_ini(&decode_text(&fs::read(desktop_ini).ok()?))?;
+        let file = self.resolve_from(dir, &location.path)?;
+        shortcut::extract_icon(&file, location.index, size).ok()
+    }
+
+    /// Resolves absolute Windows paths in the root and relative ones in `dir`
+    fn resolve_from(&self, dir: &Path, windows_path: &str) -> Option<PathBuf> {
+        let is_relative = !windows_path.starts_with(['\\', '/', '%']) && windows_path.get(1..2) != Some(":");
+        if is_relative {
+            resolve_in(dir, windows_path)
+        } else {
+            self.resolve(windows_path)
+        }
+    }
+}
+
+/// Returns the icon location of a `desktop.ini`, `IconResource=path,index` is preferred over
+/// `IconFile=path` and `IconIndex=index`
+fn parse_desktop_ini(text: &str) -> Option<IconLocation> {
+    let mut in_section = false;
+    let (mut icon_resource, mut icon_file, mut icon_index) = (None, None, 0);
+    for line in text.lines().map(|line| line.trim()) {
+        if line.starts_with('[') {
+            in_section = line.eq_ignore_ascii_case("[.ShellClassInfo]");
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_section) else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim().to_ascii_lowercase().as_str() {
+            "iconresource" => icon_resource = Some(value.to_string()),
+            "iconfile" => icon_file = Some(value.to_string()),
+            "iconindex" => icon_index = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    let location = match icon_resource.filter(|resource| !resource.is_empty()) {
+        Some(resource) => match resource.rsplit_once(',') {
+            Some((path, index)) if index.trim().parse::<i32>().is_ok() => {
+                IconLocation { path: path.trim().to_string(), index: index.trim().parse().unwrap_or(0) }
+            }
+            _ => IconLocation { path: resource, index: 0 },
+        },
+        None => IconLocation { path: icon_file?, index: icon_index },
+    };
+    (!location.path.is_empty()).then_some(location)
+}
+
+/// The environment variables of the system with their default values
+fn system_variable(name: &str) -> Option<String> {
+    let value = match name.to_ascii_lowercase().as_str() {
+        "systemdrive" => "C:",
+        "systemroot" | "windir" => "C:\\Windows",
+        "programfiles" | "programw6432" => "C:\\Program Files",
+        "programfiles(x86)" => "C:\\Program Files (x86)",
+        "commonprogramfiles" | "commonprogramw6432" => "C:\\Program Files\\Common Files",
+        "commonprogramfiles(x86)" => "C:\\Program Files (x86)\\Common Files",
+        "programdata" | "allusersprofile" => "C:\\ProgramData",
+        "public" => "C:\\Users\\Public",
+        _ => return None,
+    };
+    Some(value.to_string())
+}
+
+/// Looks up a relative Windows path in `dir`, the case of the components is ignored
+fn resolve_in(dir: &Path, windows_path: &str) -> Option<PathBuf> {
+    let mut path = dir.to_path_buf();
+    let mut depth = 0;
+    for component in windows_path.split(['\\', '/']).filter(|component| !component.is_empty() && *component != ".") {
+        if component == ".." {
+            // never leave `dir`
+            if depth == 0 {
+                return None;
+            }
+            depth -= 1;
+            path.pop();
+            continue;
+        }
+        depth += 1;
+        let exact = path.join(component);
+        path = if exact.exists() {
+            exact
+        } else {
+            fs::read_dir(&path)
+                .ok()?
+                .filter_map(|entry| entry.ok())
+                .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(component))?
+                .path()
+        };
+    }
+    path.exists().then_some(path)
+}
+
+fn is_icon_file(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.eq_ignore_ascii_case("ico")).unwrap_or(false)
+}
+
+/// `desktop.ini` files are often stored as UTF-16 with byte order mark
+fn decode_text(data: &[u8]) -> String {
+    match data {
+        [0xff, 0xfe, rest @ ..] => {
+            let chars: Vec<u16> = rest.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&chars)
+        }
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest).to_string(),
+        _ => String::from_utf8_lossy(data).to_string(),
+    }
+}
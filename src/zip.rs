@@ -0,0 +1,87 @@
+//! Writes zip archives without compression, icons are PNG or small SVG files, which hardly get smaller
+use std::{fs::File, io::Write, path::Path};
+
+use crate::Error;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const VERSION: u16 = 20;
+/// The names are UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+/// 1980-01-01 00:00 in MS-DOS format, the files get no modification time, so archives are reproducible
+const DOS_DATE: u16 = 0x21;
+
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    pub fn add(&mut self, name: &str, content: &[u8]) -> Result<(), Error> {
+        let too_large = || Error::invalid_input(name, "The zip archive is too large");
+        let offset = u32::try_from(self.data.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(content.len()).map_err(|_| too_large())?;
+        let name_len = u16::try_from(name.len()).map_err(|_| too_large())?;
+        self.entries = self.entries.checked_add(1).ok_or_else(too_large)?;
+        let crc = crc32(content);
+
+        let mut header = Vec::new();
+        header.extend(VERSION.to_le_bytes());
+        header.extend(FLAG_UTF8.to_le_bytes());
+        header.extend(0u16.to_le_bytes()); // stored
+        header.extend(0u16.to_le_bytes()); // time
+        header.extend(DOS_DATE.to_le_bytes());
+        header.extend(crc.to_le_bytes());
+        header.extend(size.to_le_bytes()); // compressed size
+        header.extend(size.to_le_bytes());
+        header.extend(name_len.to_le_bytes());
+        header.extend(0u16.to_le_bytes()); // extra field length
+
+        self.data.extend(LOCAL_HEADER.to_le_bytes());
+        self.data.extend(&header);
+        self.data.extend(name.as_bytes());
+        self.data.extend(content);
+
+        self.central_directory.extend(CENTRAL_HEADER.to_le_bytes());
+        self.central_directory.extend(VERSION.to_le_bytes()); // made by
+        self.central_directory.extend(&header);
+        self.central_directory.extend(0u16.to_le_bytes()); // comment length
+        self.central_directory.extend(0u16.to_le_bytes()); // disk number
+        self.central_directory.extend(0u16.to_le_bytes()); // internal attributes
+        self.central_directory.extend(0u32.to_le_bytes()); // external attributes
+        self.central_directory.extend(offset.to_le_bytes());
+        self.central_directory.extend(name.as_bytes());
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>, Error> {
+        let too_large = || Error::invalid_input("", "The zip archive is too large");
+        let offset = u32::try_from(self.data.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(self.central_directory.len()).map_err(|_| too_large())?;
+        self.data.append(&mut self.central_directory);
+        self.data.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // disk number
+        self.data.extend(0u16.to_le_bytes()); // disk of the central directory
+        self.data.extend(self.entries.to_le_bytes());
+        self.data.extend(self.entries.to_le_bytes());
+        self.data.extend(size.to_le_bytes());
+        self.data.extend(offset.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes()); // comment length
+        Ok(self.data)
+    }
+
+    pub fn write_to(self, path: &Path) -> Result<(), Error> {
+        let data = self.finish()?;
+        File::create(path)?.write_all(&data)?;
+        Ok(())
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 })
+    })
+}
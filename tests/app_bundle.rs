@@ -0,0 +1,127 @@
+use std::{fs, path::PathBuf};
+
+use systemicons::{icns::IconFamily, ico::IconImage, Error};
+
+const XML_INFO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<!-- the extension is usually omitted -->
+	<key>CFBundleIconFile</key>
+	<string>App&amp;Icon</string>
+	<key>CFBundleSupportedPlatforms</key>
+	<array>
+		<string>MacOSX</string>
+	</array>
+	<key>LSRequiresNativeExecution</key>
+	<true/>
+	<key>NSHumanReadableCopyright</key>
+	<string/>
+</dict>
+</plist>
+"#;
+
+fn icns(color: [u8; 4]) -> Vec<u8> {
+    let mut family = IconFamily::new();
+    for (os_type, size) in [(b"il32", 32), (b"ic07", 128)] {
+        let image = IconImage::from_rgba(size, size, color.repeat((size * size) as usize)).unwrap();
+        family.add_image(os_type, &image).unwrap();
+    }
+    family.write()
+}
+
+/// A binary property list with a dictionary of ASCII strings
+fn binary_plist(entries: &[(&str, &str)]) -> Vec<u8> {
+    let mut data = b"bplist00".to_vec();
+    let strings: Vec<&str> = entries.iter().map(|(key, _)| *key).chain(entries.iter().map(|(_, value)| *value)).collect();
+    let mut offsets = vec![data.len()];
+    data.push(0xd0 | entries.len() as u8);
+    data.extend(1..=strings.len() as u8);
+    for string in strings {
+        offsets.push(data.len());
+        data.push(0x5f);
+        data.extend([0x10, string.len() as u8]);
+        data.extend(string.as_bytes());
+    }
+    let offset_table = data.len();
+    data.extend(offsets.iter().map(|&offset| offset as u8));
+    data.extend([0; 6]);
+    data.extend([1, 1]);
+    data.extend((offsets.len() as u64).to_be_bytes());
+    data.extend(0u64.to_be_bytes());
+    data.extend((offset_table as u64).to_be_bytes());
+    data
+}
+
+fn bundle(name: &str, info: &[u8], icons: &[(&str, Vec<u8>)]) -> PathBuf {
+    let bundle = std::env::temp_dir().join(format!("systemicons-test-{}-{}.app", std::process::id(), name));
+    let resources = bundle.join("Contents").join("Resources");
+    fs::create_dir_all(&resources).unwrap();
+    fs::write(bundle.join("Contents").join("Info.plist"), info).unwrap();
+    for (file_name, icon) in icons {
+        fs::write(resources.join(file_name), icon).unwrap();
+    }
+    bundle
+}
+
+fn pixel(png: &[u8]) -> (u32, [u8; 4]) {
+    let image = IconImage::from_png(png).unwrap();
+    (image.width, image.rgba[..4].try_into().unwrap())
+}
+
+#[test]
+fn xml_info_plist() {
+    let bundle = bundle("xml", XML_INFO.as_bytes(), &[("app&icon.ICNS", icns([1, 2, 3, 255]))]);
+    let small = systemicons::get_app_bundle_icon(bundle.to_str().unwrap(), 32);
+    let large = systemicons::get_app_bundle_icon(bundle.to_str().unwrap(), 64);
+    fs::remove_dir_all(&bundle).unwrap();
+    assert_eq!(pixel(&small.unwrap()), (32, [1, 2, 3, 255]));
+    assert_eq!(pixel(&large.unwrap()), (128, [1, 2, 3, 255]));
+}
+
+#[test]
+fn binary_info_plist() {
+    let info = binary_plist(&[("CFBundleIdentifier", "org.example.App"), ("CFBundleIconName", "AppIcon")]);
+    let bundle = bundle("binary", &info, &[("Other.icns", icns([9, 9, 9, 255])), ("AppIcon.icns", icns([4, 5, 6, 255]))]);
+    let icon = systemicons::get_app_bundle_icon(bundle.to_str().unwrap(), 16);
+    fs::remove_dir_all(&bundle).unwrap();
+    assert_eq!(pixel(&icon.unwrap()), (32, [4, 5, 6, 255]));
+}
+
+#[test]
+fn shared_references() {
+    // every array references the next one twice, which would be 2^40 objects without a limit
+    let mut data = b"bplist00".to_vec();
+    let mut offsets = Vec::new();
+    for index in 1..=40u8 {
+        offsets.push(data.len());
+        data.extend([0xa2, index, index]);
+    }
+    offsets.push(data.len());
+    data.extend(b"\x54Icon");
+    let offset_table = data.len();
+    data.extend(offsets.iter().map(|&offset| offset as u8));
+    data.extend([0; 6]);
+    data.extend([1, 1]);
+    data.extend((offsets.len() as u64).to_be_bytes());
+    data.extend(0u64.to_be_bytes());
+    data.extend((offset_table as u64).to_be_bytes());
+    let bundle = bundle("shared", &data, &[]);
+    let err = systemicons::get_app_bundle_icon(bundle.to_str().unwrap(), 32).unwrap_err();
+    fs::remove_dir_all(&bundle).unwrap();
+    assert!(matches!(err, Error::Decode { .. }), "{:?}", err);
+}
+
+#[test]
+fn missing_icon() {
+    let bundle = bundle("missing", XML_INFO.as_bytes(), &[]);
+    let err = systemicons::get_app_bundle_icon(bundle.to_str().unwrap(), 32).unwrap_err();
+    fs::remove_dir_all(&bundle).unwrap();
+    assert!(matches!(err, Error::NotFound { .. }));
+}
+
+#[test]
+fn no_bundle() {
+    let err = systemicons::get_app_bundle_icon(std::env::temp_dir().to_str().unwrap(), 32).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }));
+}
@@ -0,0 +1,62 @@
+#![cfg(target_os = "linux")]
+
+use systemicons::{find_app_for_app_id_in, AppIdMatch, AppIdRule};
+
+const APPLICATIONS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/applications");
+
+fn find(id: &str) -> Option<AppIdMatch> {
+    find_app_for_app_id_in(id, &[APPLICATIONS])
+}
+
+fn assert_match(id: &str, desktop_id: &str, rule: AppIdRule) {
+    assert_eq!(find(id), Some(AppIdMatch { desktop_id: desktop_id.to_string(), rule }), "app id {}", id);
+}
+
+#[test]
+fn desktop_id() {
+    assert_match("org.gnome.Nautilus", "org.gnome.Nautilus.desktop", AppIdRule::DesktopId);
+    assert_match("org.gnome.Nautilus.desktop", "org.gnome.Nautilus.desktop", AppIdRule::DesktopId);
+}
+
+#[test]
+fn desktop_id_with_vendor_prefix() {
+    assert_match("kde-org.kde.dolphin", "kde-org.kde.dolphin.desktop", AppIdRule::DesktopId);
+}
+
+#[test]
+fn startup_wm_class() {
+    assert_match("jetbrains-idea-ce", "jetbrains-idea.desktop", AppIdRule::StartupWmClass);
+}
+
+#[test]
+fn lowercase_window_class() {
+    assert_match("Firefox", "firefox.desktop", AppIdRule::Lowercase);
+}
+
+#[test]
+fn dash_underscore_variant() {
+    assert_match("Gnome_terminal", "gnome-terminal.desktop", AppIdRule::DashUnderscore);
+}
+
+#[test]
+fn flatpak() {
+    assert_match("discord", "com.discordapp.Discord.desktop", AppIdRule::Flatpak);
+}
+
+#[test]
+fn snap() {
+    assert_match("spotify", "spotify_spotify.desktop", AppIdRule::Snap);
+}
+
+#[test]
+fn reverse_dns() {
+    assert_match("Nautilus", "org.gnome.Nautilus.desktop", AppIdRule::ReverseDns);
+    assert_match("dolphin", "kde-org.kde.dolphin.desktop", AppIdRule::ReverseDns);
+}
+
+#[test]
+fn hidden_or_unknown() {
+    assert_eq!(find("removed"), None);
+    assert_eq!(find("unknown-app"), None);
+    assert_eq!(find(""), None);
+}
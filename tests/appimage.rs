@@ -0,0 +1,35 @@
+#![cfg(all(target_os = "linux", feature = "backend-gtk"))]
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/appimage");
+
+fn icon(file: &str, size: i32) -> Vec<u8> {
+    systemicons::get_icon_for_path(&format!("{}/{}", FIXTURES, file), size).unwrap()
+}
+
+#[test]
+fn type2_zstd() {
+    assert!(icon("type2-zstd.AppImage", 32).starts_with(b"\x89PNG\r\n\x1a\nSMALL"));
+}
+
+#[test]
+fn type2_gzip() {
+    assert!(icon("type2-gzip.AppImage", 32).starts_with(b"\x89PNG\r\n\x1a\nSMALL"));
+}
+
+#[test]
+fn hicolor_size_follows_symlinks() {
+    let icon = icon("type2-zstd.AppImage", 256);
+    assert!(icon.starts_with(b"\x89PNG\r\n\x1a\nBIG"));
+    assert_eq!(icon.len(), 10011);
+}
+
+#[test]
+fn largest_hicolor_size() {
+    assert!(icon("type2-gzip.AppImage", 512).starts_with(b"\x89PNG\r\n\x1a\nBIG"));
+}
+
+#[test]
+fn type1_rock_ridge() {
+    assert!(icon("type1.AppImage", 32).starts_with(b"\x89PNG\r\n\x1a\nSMALL"));
+    assert_eq!(icon("type1.AppImage", 256).len(), 10011);
+}
@@ -0,0 +1,87 @@
+use std::{cell::RefCell, io::Cursor};
+
+use image::{ImageFormat, RgbaImage};
+use systemicons::{
+    atlas::{build_atlas_from, AtlasOptions, Sprite},
+    batch::get_icons_with,
+    Error,
+};
+
+fn png(size: u32, color: [u8; 4]) -> Vec<u8> {
+    let mut png = Cursor::new(Vec::new());
+    RgbaImage::from_pixel(size, size, color.into()).write_to(&mut png, ImageFormat::Png).unwrap();
+    png.into_inner()
+}
+
+/// Text files share the icon of `.txt`, `.pdf` has its own, which is always 48 pixels large
+fn load(ext: &str, size: i32) -> Result<Vec<u8>, Error> {
+    match ext {
+        ".txt" | ".md" => Ok(png(size as u32, [10, 0, 0, 255])),
+        ".pdf" => Ok(png(48, [20, 0, 0, 255])),
+        _ => Err(Error::NotFound { input: ext.to_string(), size: Some(size), reason: "No icon".to_string() }),
+    }
+}
+
+const REQUESTS: &[(&str, i32)] = &[(".txt", 16), (".PDF", 32), (".md", 16), (".txt", 16), (".zzz", 16), (".pdf", 16), (".zzz", 16)];
+
+#[test]
+fn batch_deduplicates() {
+    let calls = RefCell::new(Vec::new());
+    let batch = get_icons_with(REQUESTS, |ext, size| {
+        calls.borrow_mut().push((ext.to_string(), size));
+        load(ext, size)
+    });
+    // `.txt` 16 and `.zzz` 16 are looked up once, `.PDF` is looked up as `.pdf`
+    assert_eq!(calls.borrow().len(), 5);
+    assert_eq!(batch.icons.len(), 2);
+    let indices: Vec<_> = batch.entries.iter().map(|entry| entry.as_ref().ok().copied()).collect();
+    assert_eq!(indices, [Some(0), Some(1), Some(0), Some(0), None, Some(1), None]);
+    assert!(matches!(batch.entries[6].as_ref().unwrap_err(), Error::NotFound { .. }));
+    assert_eq!(batch.get(2), Some(batch.icons[0].as_slice()));
+    assert_eq!(batch.get(4), None);
+}
+
+#[test]
+fn atlas_places_every_request() {
+    let batch = get_icons_with(REQUESTS, load);
+    let atlas = build_atlas_from(REQUESTS, batch, &AtlasOptions { padding: 2, max_width: 40 }).unwrap();
+    let sprites = &atlas.manifest.sprites;
+    assert_eq!(sprites.iter().map(|sprite| (sprite.input.as_str(), sprite.size)).collect::<Vec<_>>(), [(".txt", 16), (".PDF", 32), (".md", 16), (".pdf", 16)]);
+    // `.md` shares the rectangle of `.txt`, the 48 pixel `.pdf` icon is scaled to the requested sizes
+    assert_eq!((sprites[0].x, sprites[0].y), (sprites[2].x, sprites[2].y));
+    assert_eq!(sprites[1], Sprite { input: ".PDF".to_string(), size: 32, x: 0, y: 0, w: 32, h: 32 });
+    assert_eq!((sprites[3].w, sprites[3].h), (16, 16));
+    assert_eq!(atlas.errors.iter().map(|(input, size, _)| (input.as_str(), *size)).collect::<Vec<_>>(), [(".zzz", 16)]);
+
+    let image = image::load_from_memory(&atlas.png).unwrap().to_rgba8();
+    assert_eq!((image.width(), image.height()), (atlas.manifest.width, atlas.manifest.height));
+    for sprite in sprites {
+        assert!(sprite.x + sprite.w <= image.width() && sprite.y + sprite.h <= image.height());
+        let color = if sprite.input == ".txt" || sprite.input == ".md" { 10 } else { 20 };
+        assert_eq!(image.get_pixel(sprite.x + sprite.w / 2, sprite.y + sprite.h / 2).0, [color, 0, 0, 255]);
+    }
+    // the second row starts below the 32 pixel icon and the padding
+    assert!(sprites.iter().any(|sprite| sprite.y == 34));
+}
+
+#[test]
+fn css_sprites() {
+    let requests = [(".txt", 16), (".TXT", 16), (".tar.gz", 16)];
+    let atlas = build_atlas_from(&requests, get_icons_with(&requests, |_, size| Ok(png(size as u32, [0, 0, 0, 255]))), &AtlasOptions::default()).unwrap();
+    let css = atlas.manifest.to_css("icons.png", "icon");
+    let lines: Vec<_> = css.lines().collect();
+    assert_eq!(lines[0], ".icon { display: inline-block; background-image: url(\"icons.png\"); background-repeat: no-repeat; }");
+    assert_eq!(lines[1], ".icon-txt-16 { width: 16px; height: 16px; background-position: 0 0; }");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[2].starts_with(".icon-tar-gz-16 {"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_manifest() {
+    let requests = [(".txt", 16)];
+    let atlas = build_atlas_from(&requests, get_icons_with(&requests, load), &AtlasOptions::default()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&atlas.manifest.to_json().unwrap()).unwrap();
+    assert_eq!(json["sprites"][0]["input"], ".txt");
+    assert_eq!(json["width"], 16);
+}
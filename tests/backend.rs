@@ -0,0 +1,137 @@
+use systemicons::{
+    backend::{IconBackend, IconName, IconProvider, ResolvedIcon},
+    Error,
+};
+
+/// The icons of a company, only for `.acme` files
+struct CompanyIcons;
+
+impl IconBackend for CompanyIcons {
+    fn name(&self) -> &str {
+        "company"
+    }
+
+    fn resolve_mime_type(&self, ext: &str) -> Option<String> {
+        (ext == ".acme").then(|| "application/x-acme".to_string())
+    }
+
+    fn resolve(&self, name: IconName, size: i32) -> Result<ResolvedIcon, Error> {
+        match name {
+            IconName::Extension(".acme") | IconName::MimeType("application/x-acme") => Ok(ResolvedIcon::Data(format!("acme {}", size).into_bytes())),
+            _ => Err(Error::NotFound { input: String::new(), size: Some(size), reason: "Not a company file".to_string() }),
+        }
+    }
+}
+
+/// Has an icon for everything
+struct Fallback(&'static str);
+
+impl IconBackend for Fallback {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn resolve(&self, name: IconName, _size: i32) -> Result<ResolvedIcon, Error> {
+        Ok(ResolvedIcon::Data(format!("{} {}", self.0, name.as_str()).into_bytes()))
+    }
+}
+
+/// Like a system backend without display
+struct Unavailable;
+
+impl IconBackend for Unavailable {
+    fn name(&self) -> &str {
+        "unavailable"
+    }
+
+    fn resolve(&self, _name: IconName, _size: i32) -> Result<ResolvedIcon, Error> {
+        Err(Error::BackendUnavailable { backend: "GTK", reason: "No display".to_string(), source: None })
+    }
+}
+
+/// Delivers icons which cannot be read
+struct Broken;
+
+impl IconBackend for Broken {
+    fn name(&self) -> &str {
+        "broken"
+    }
+
+    fn resolve(&self, _name: IconName, _size: i32) -> Result<ResolvedIcon, Error> {
+        Ok(ResolvedIcon::File(std::env::temp_dir().join("systemicons-missing-icon.png")))
+    }
+}
+
+#[test]
+fn priority_order() {
+    let provider = IconProvider::new()
+        .with_backend(0, Fallback("system"))
+        .with_backend(100, CompanyIcons)
+        .with_backend(0, Fallback("second"))
+        .with_backend(-10, Unavailable);
+    assert_eq!(provider.backend_names(), ["company", "system", "second", "unavailable"]);
+    assert_eq!(provider.get_icon(".acme", 32).unwrap(), b"acme 32");
+    assert_eq!(provider.get_icon_for_mime_type("application/x-acme", 16).unwrap(), b"acme 16");
+    // the company icons do not know PDF files, so the next backend is asked
+    assert_eq!(provider.get_icon(".pdf", 32).unwrap(), b"system .pdf");
+    assert_eq!(provider.get_icon_by_name("folder", 32).unwrap(), b"system folder");
+    assert_eq!(provider.mime_type(".acme").unwrap(), "application/x-acme");
+    assert_eq!(provider.mime_type(".pdf"), None);
+}
+
+#[test]
+fn errors() {
+    let provider = IconProvider::new().with_backend(10, Unavailable).with_backend(0, CompanyIcons);
+    // a backend which is available knows better that there is no icon
+    let err = provider.get_icon(".pdf", 32).unwrap_err();
+    assert!(matches!(err, Error::NotFound { size: Some(32), .. }), "{:?}", err);
+    assert_eq!(err.input(), ".pdf");
+
+    let err = IconProvider::new().with_backend(0, Unavailable).get_icon(".pdf", 32).unwrap_err();
+    assert!(matches!(err, Error::BackendUnavailable { .. }));
+    assert!(matches!(IconProvider::new().get_icon(".pdf", 32), Err(Error::NotFound { .. })));
+
+    // other errors are not hidden by the next backend
+    let provider = IconProvider::new().with_backend(10, Broken).with_backend(0, Fallback("system"));
+    assert!(matches!(provider.get_icon(".pdf", 32), Err(Error::Io { .. })));
+}
+
+#[test]
+fn invalid_input() {
+    let provider = IconProvider::new().with_backend(0, Fallback("system"));
+    for (name, size) in [(".pdf\0", 32), ("", 32), (".pdf", 0)] {
+        let err = provider.get_icon(name, size).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{:?}", err);
+        assert_eq!(err.input(), name);
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "backend-freedesktop"))]
+#[test]
+fn freedesktop() {
+    use std::fs;
+    use systemicons::backend::FreedesktopBackend;
+
+    let dir = std::env::temp_dir().join(format!("systemicons-test-{}-backend", std::process::id()));
+    fs::create_dir_all(dir.join("mime")).unwrap();
+    fs::write(dir.join("mime/globs2"), "50:application/pdf:*.pdf\n").unwrap();
+    fs::write(dir.join("mime/aliases"), "application/x-pdf application/pdf\n").unwrap();
+    let theme = dir.join("icons/hicolor");
+    fs::create_dir_all(theme.join("16x16/mimetypes")).unwrap();
+    fs::create_dir_all(theme.join("16x16/places")).unwrap();
+    fs::write(theme.join("index.theme"), "[Icon Theme]\nName=Hicolor\nDirectories=16x16/mimetypes,16x16/places\n\n[16x16/mimetypes]\nSize=16\n\n[16x16/places]\nSize=16\n").unwrap();
+    fs::write(theme.join("16x16/mimetypes/application-pdf.png"), b"PDF").unwrap();
+    fs::write(theme.join("16x16/places/folder.png"), b"FOLDER").unwrap();
+
+    let backend = FreedesktopBackend::in_dirs(std::slice::from_ref(&dir), "hicolor");
+    assert_eq!(backend.resolve_mime_type(".PDF").unwrap(), "application/pdf");
+    assert_eq!(backend.resolve(IconName::Extension(".pdf"), 16).unwrap(), ResolvedIcon::File(theme.join("16x16/mimetypes/application-pdf.png")));
+
+    let provider = IconProvider::new().with_backend(0, backend);
+    assert_eq!(provider.get_icon(".pdf", 16).unwrap(), b"PDF");
+    assert_eq!(provider.get_icon_for_mime_type("application/x-pdf", 32).unwrap(), b"PDF");
+    assert_eq!(provider.get_icon_by_name("folder", 16).unwrap(), b"FOLDER");
+    let err = provider.get_icon(".zzz", 16).unwrap_err();
+    assert!(matches!(err, Error::NotFound { .. }), "{:?}", err);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,108 @@
+use systemicons::pe::ResourceId;
+
+pub const RT_ICON: u32 = 3;
+pub const RT_GROUP_ICON: u32 = 14;
+const SECTION_RVA: u32 = 0x1000;
+const SECTION_OFFSET: usize = 0x200;
+
+/// The resources of one type as (type, [(id, data)])
+pub type Resources = (u32, Vec<(ResourceId, Vec<u8>)>);
+
+/// Builds a PE32 file with a single `.rsrc` section
+pub fn build_pe(resources: &[Resources]) -> Vec<u8> {
+    let count: usize = resources.iter().map(|(_, entries)| entries.len()).sum();
+    let mut directory_size = 16 + 8 * resources.len();
+    let type_dirs: Vec<usize> = resources
+        .iter()
+        .map(|(_, entries)| {
+            let offset = directory_size;
+            directory_size += 16 + 8 * entries.len();
+            offset
+        })
+        .collect();
+    let language_dirs: Vec<usize> = (0..count).map(|i| directory_size + i * 40).collect();
+    let data_entries: Vec<usize> = (0..count).map(|i| directory_size + i * 40 + 24).collect();
+    let mut names_offset = directory_size + count * 40;
+
+    let mut rsrc = vec![0; names_offset];
+    let mut data = Vec::new();
+    let directory = |rsrc: &mut Vec<u8>, offset: usize, named: usize, ids: usize| {
+        rsrc[offset + 12..offset + 14].copy_from_slice(&(named as u16).to_le_bytes());
+        rsrc[offset + 14..offset + 16].copy_from_slice(&(ids as u16).to_le_bytes());
+    };
+    let entry = |rsrc: &mut Vec<u8>, offset: usize, name: u32, target: u32| {
+        rsrc[offset..offset + 4].copy_from_slice(&name.to_le_bytes());
+        rsrc[offset + 4..offset + 8].copy_from_slice(&target.to_le_bytes());
+    };
+
+    directory(&mut rsrc, 0, 0, resources.len());
+    let mut index = 0;
+    for (t, (resource_type, entries)) in resources.iter().enumerate() {
+        entry(&mut rsrc, 16 + t * 8, *resource_type, 0x8000_0000 | type_dirs[t] as u32);
+        let named = entries.iter().filter(|(id, _)| matches!(id, ResourceId::Name(_))).count();
+        directory(&mut rsrc, type_dirs[t], named, entries.len() - named);
+        for (e, (id, content)) in entries.iter().enumerate() {
+            let name = match id {
+                ResourceId::Id(id) => *id as u32,
+                ResourceId::Name(name) => {
+                    let offset = names_offset;
+                    let chars: Vec<u16> = name.encode_utf16().collect();
+                    let mut bytes = (chars.len() as u16).to_le_bytes().to_vec();
+                    bytes.extend(chars.iter().flat_map(|c| c.to_le_bytes()));
+                    names_offset += bytes.len();
+                    rsrc.extend(bytes);
+                    0x8000_0000 | offset as u32
+                }
+            };
+            entry(&mut rsrc, type_dirs[t] + 16 + e * 8, name, 0x8000_0000 | language_dirs[index] as u32);
+            directory(&mut rsrc, language_dirs[index], 0, 1);
+            entry(&mut rsrc, language_dirs[index] + 16, 0x409, data_entries[index] as u32);
+            data.push((data_entries[index], content));
+            index += 1;
+        }
+    }
+    for (data_entry, content) in data {
+        while !rsrc.len().is_multiple_of(4) {
+            rsrc.push(0);
+        }
+        let rva = SECTION_RVA + rsrc.len() as u32;
+        rsrc[data_entry..data_entry + 4].copy_from_slice(&rva.to_le_bytes());
+        rsrc[data_entry + 4..data_entry + 8].copy_from_slice(&(content.len() as u32).to_le_bytes());
+        rsrc.extend(content);
+    }
+
+    let mut pe = vec![0; SECTION_OFFSET];
+    pe[..2].copy_from_slice(b"MZ");
+    pe[0x3c..0x40].copy_from_slice(&64u32.to_le_bytes());
+    pe[64..68].copy_from_slice(b"PE\0\0");
+    // COFF header: i386, one section, 224 bytes optional header
+    pe[68..70].copy_from_slice(&0x14cu16.to_le_bytes());
+    pe[70..72].copy_from_slice(&1u16.to_le_bytes());
+    pe[84..86].copy_from_slice(&224u16.to_le_bytes());
+    let optional_header = 88;
+    pe[optional_header..optional_header + 2].copy_from_slice(&0x10bu16.to_le_bytes());
+    pe[optional_header + 92..optional_header + 96].copy_from_slice(&16u32.to_le_bytes());
+    pe[optional_header + 112..optional_header + 116].copy_from_slice(&SECTION_RVA.to_le_bytes());
+    pe[optional_header + 116..optional_header + 120].copy_from_slice(&(rsrc.len() as u32).to_le_bytes());
+    let section = optional_header + 224;
+    pe[section..section + 5].copy_from_slice(b".rsrc");
+    pe[section + 8..section + 12].copy_from_slice(&(rsrc.len() as u32).to_le_bytes());
+    pe[section + 12..section + 16].copy_from_slice(&SECTION_RVA.to_le_bytes());
+    pe[section + 16..section + 20].copy_from_slice(&(rsrc.len() as u32).to_le_bytes());
+    pe[section + 20..section + 24].copy_from_slice(&(SECTION_OFFSET as u32).to_le_bytes());
+    pe.extend(rsrc);
+    pe
+}
+
+/// Builds an `RT_GROUP_ICON` resource from (size, bit count, image size, `RT_ICON` id)
+pub fn group(entries: &[(u8, u16, usize, u16)]) -> Vec<u8> {
+    let mut group = vec![0, 0, 1, 0];
+    group.extend((entries.len() as u16).to_le_bytes());
+    for (size, bit_count, len, id) in entries {
+        group.extend([*size, *size, 0, 0, 1, 0]);
+        group.extend(bit_count.to_le_bytes());
+        group.extend((*len as u32).to_le_bytes());
+        group.extend(id.to_le_bytes());
+    }
+    group
+}
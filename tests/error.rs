@@ -0,0 +1,39 @@
+use std::error::Error as _;
+
+use systemicons::{icns::IconFamily, ico::IconEntry, Error};
+
+#[test]
+fn not_found_with_size() {
+    let err = IconFamily::new().get_png(32).unwrap_err();
+    assert!(matches!(err, Error::NotFound { size: Some(32), .. }));
+    assert_eq!(err.input(), "");
+    assert_eq!(err.to_string(), "No decodable icon found (32 pixels)");
+    assert!(err.source().is_none());
+}
+
+#[test]
+fn decode_with_source() {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x10\0\0\0\x10\x08\x06\0\0\0".to_vec();
+    png.extend_from_slice(b"broken");
+    let err = IconEntry::from_data(png).unwrap().decode().unwrap_err();
+    assert!(matches!(err, Error::Decode { .. }));
+    assert_eq!(err.to_string(), "Decoding failed: The image could not be decoded");
+    assert!(err.source().unwrap().is::<image::ImageError>());
+}
+
+#[test]
+fn io_error_keeps_kind() {
+    let err = systemicons::ico::get_icon(&std::env::temp_dir().join("systemicons-missing.ico"), 16).unwrap_err();
+    let Error::Io { source, .. } = &err else {
+        panic!("{:?}", err);
+    };
+    assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+    assert_eq!(err.to_string(), "I/O error");
+    assert_eq!(format!("{:#}", err), format!("I/O error: {}", source));
+}
+
+#[test]
+fn alternate_format_without_source() {
+    let err = IconFamily::new().get_png(32).unwrap_err();
+    assert_eq!(format!("{:#}", err), "No decodable icon found (32 pixels)");
+}
@@ -0,0 +1,126 @@
+#![cfg(target_os = "linux")]
+
+use std::{fs, path::PathBuf};
+
+use systemicons::{IconRequest, MimeReason, MimeStep};
+
+/// A data directory with a small shared MIME-info database, the theme `Child` inheriting from `Parent`,
+/// an empty `hicolor` theme and an unthemed icon
+fn data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("systemicons-test-{}-{}", std::process::id(), name));
+    let mime = dir.join("mime");
+    fs::create_dir_all(&mime).unwrap();
+    fs::write(mime.join("globs2"), "# comment\n50:application/pdf:*.pdf\n50:text/x-csrc:*.c:cs\n60:application/x-special:special.*\n").unwrap();
+    fs::write(mime.join("aliases"), "application/x-pdf application/pdf\n").unwrap();
+    fs::write(mime.join("subclasses"), "application/xml text/plain\nimage/svg+xml application/xml\n").unwrap();
+    fs::write(mime.join("generic-icons"), "application/pdf:x-office-document\n").unwrap();
+    fs::write(mime.join("magic"), b"MIME-Magic\0\n[50:application/x-sqlite3]\n>0=\0\x0fSQLite format 3\n").unwrap();
+
+    let icons = dir.join("icons");
+    let theme = |name: &str, index: &str, files: &[&str]| {
+        fs::create_dir_all(icons.join(name)).unwrap();
+        fs::write(icons.join(name).join("index.theme"), index).unwrap();
+        for file in files {
+            let file = icons.join(name).join(file);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, b"PNG").unwrap();
+        }
+    };
+    theme(
+        "Child",
+        "[Icon Theme]\nName=Child\nInherits=Parent\nDirectories=16x16/mimetypes,scalable/mimetypes\n\n\
+         [16x16/mimetypes]\nSize=16\nType=Fixed\n\n[scalable/mimetypes]\nSize=64\nType=Scalable\nMinSize=40\nMaxSize=256\n",
+        &["16x16/mimetypes/application-pdf.png", "scalable/mimetypes/image-svg+xml.png"],
+    );
+    theme(
+        "Parent",
+        "[Icon Theme]\nName=Parent\nDirectories=32x32/mimetypes,48x48/mimetypes\n\n\
+         [32x32/mimetypes]\nSize=32\n\n[48x48/mimetypes]\nSize=48\nThreshold=4\n",
+        &["32x32/mimetypes/application-x-generic.png", "48x48/mimetypes/application-x-generic.png"],
+    );
+    theme("hicolor", "[Icon Theme]\nName=Hicolor\nHidden=true\nDirectories=\n", &[]);
+    fs::write(icons.join("audio-x-generic.png"), b"PNG").unwrap();
+    dir
+}
+
+#[test]
+fn extension_found_in_first_theme() {
+    let dirs = [data_dir("explain-extension")];
+    let dir = &dirs[0];
+    let explanation = systemicons::explain_in(&IconRequest::extension(".PDF", 32), &dirs, "Child");
+    assert_eq!(explanation.mime_type, "application/pdf");
+    assert_eq!(explanation.reason, MimeReason::Glob { pattern: "*.pdf".to_string(), weight: 50 });
+    assert_eq!(explanation.icon_names, ["application-pdf", "gnome-mime-application-pdf", "x-office-document"]);
+    // themes are searched one after the other, so the 16 pixel icon of the child wins over the parent
+    assert_eq!(explanation.visits.len(), 1);
+    let candidate = &explanation.visits[0].candidates[0];
+    assert_eq!((candidate.matches_size, candidate.size_distance), (false, 16));
+    assert_eq!(explanation.icon, Some(dir.join("icons/Child/16x16/mimetypes/application-pdf.png")));
+    assert_eq!(explanation.failure, None);
+}
+
+#[test]
+fn alias_and_subclasses() {
+    let dirs = [data_dir("explain-alias")];
+    let explanation = systemicons::explain_in(&IconRequest::mime_type("application/x-pdf", 16), &dirs, "Child");
+    assert_eq!(explanation.reason, MimeReason::Given);
+    assert_eq!(
+        explanation.steps,
+        [
+            MimeStep::Alias { alias: "application/x-pdf".to_string(), canonical: "application/pdf".to_string() },
+            MimeStep::Subclass { child: "application/pdf".to_string(), parent: "application/octet-stream".to_string() },
+        ]
+    );
+    let explanation = systemicons::explain_in(&IconRequest::mime_type("image/svg+xml", 24).with_scale(2), &dirs, "Child");
+    assert!(explanation.steps.contains(&MimeStep::Subclass { child: "application/xml".to_string(), parent: "text/plain".to_string() }));
+    // 24 pixels at scale 2 do not match the scalable directory of scale 1, but are inside of its range
+    let candidate = &explanation.visits[0].candidates[0];
+    assert_eq!((candidate.matches_size, candidate.size_distance), (false, 0));
+}
+
+#[test]
+fn magic_and_inherited_theme() {
+    let dirs = [data_dir("explain-magic")];
+    let dir = &dirs[0];
+    fs::write(dir.join("database"), b"SQLite format 3\0rest").unwrap();
+    let explanation = systemicons::explain_in(&IconRequest::path(dir.join("database"), 48), &dirs, "Child");
+    assert_eq!(explanation.mime_type, "application/x-sqlite3");
+    assert_eq!(explanation.reason, MimeReason::Magic { priority: 50 });
+    assert_eq!(explanation.visits.iter().map(|visit| visit.theme.as_str()).collect::<Vec<_>>(), ["Child", "Parent"]);
+    let candidates = &explanation.visits[1].candidates;
+    assert_eq!(candidates.len(), 2);
+    assert!(candidates.iter().all(|candidate| candidate.icon_name == "application-x-generic"));
+    assert_eq!(explanation.icon, Some(dir.join("icons/Parent/48x48/mimetypes/application-x-generic.png")));
+}
+
+#[test]
+fn content_without_glob() {
+    let dirs = [data_dir("explain-content")];
+    let dir = &dirs[0];
+    fs::write(dir.join("notes"), "some text\n").unwrap();
+    fs::write(dir.join("empty"), "").unwrap();
+    fs::write(dir.join("binary"), [0u8, 1, 2, 255]).unwrap();
+    let reason = |name: &str| {
+        let explanation = systemicons::explain_in(&IconRequest::path(dir.join(name), 32), &dirs, "Child");
+        (explanation.mime_type, explanation.reason)
+    };
+    assert_eq!(reason("notes"), ("text/plain".to_string(), MimeReason::Text));
+    assert_eq!(reason("empty"), ("application/x-zerosize".to_string(), MimeReason::EmptyFile));
+    assert_eq!(reason("binary"), ("application/octet-stream".to_string(), MimeReason::Fallback));
+    assert_eq!(reason("mime"), ("inode/directory".to_string(), MimeReason::Directory));
+}
+
+#[test]
+fn unthemed_and_failure() {
+    let dirs = [data_dir("explain-failure")];
+    let dir = &dirs[0];
+    let explanation = systemicons::explain_in(&IconRequest::mime_type("audio/x-thing", 32), &dirs, "Child");
+    assert_eq!(explanation.visits.len(), 3);
+    assert_eq!(explanation.icon, Some(dir.join("icons/audio-x-generic.png")));
+
+    let explanation = systemicons::explain_in(&IconRequest::mime_type("video/x-thing", 32), &dirs, "Missing");
+    assert_eq!(explanation.visits.iter().map(|visit| visit.theme.as_str()).collect::<Vec<_>>(), ["Missing", "hicolor"]);
+    assert_eq!(explanation.visits[0].index, None);
+    assert_eq!(explanation.icon, None);
+    assert!(explanation.failure.unwrap().contains("video-x-generic"));
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "export")]
+
+use std::{fs, io::Cursor, path::PathBuf};
+
+use image::{ImageFormat, RgbaImage};
+use systemicons::{
+    export::{export_with, ExportInput, ExportOptions, ExportTarget, IconKey, IconSource},
+    Error,
+};
+
+const SVG: &[u8] = b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+
+/// Knows `.pdf` and `.txt`, where `.txt` and `text/markdown` share the same icon, which only exists in 32 pixels
+struct Source;
+
+fn png(size: u32, color: u8) -> Vec<u8> {
+    let mut png = Cursor::new(Vec::new());
+    RgbaImage::from_pixel(size, size, [color, 0, 0, 255].into()).write_to(&mut png, ImageFormat::Png).unwrap();
+    png.into_inner()
+}
+
+fn not_found() -> Error {
+    Error::NotFound { input: String::new(), size: None, reason: "No icon".to_string() }
+}
+
+impl IconSource for Source {
+    fn mime_type(&self, ext: &str) -> Option<String> {
+        match ext {
+            ".pdf" => Some("application/pdf".to_string()),
+            ".txt" => Some("text/plain".to_string()),
+            _ => None,
+        }
+    }
+
+    fn mime_types(&self) -> Vec<String> {
+        vec!["application/pdf".to_string(), "text/markdown".to_string(), "text/plain".to_string()]
+    }
+
+    fn png(&self, key: IconKey, size: i32) -> Result<Vec<u8>, Error> {
+        match key {
+            IconKey::MimeType("application/pdf") => Ok(png(size as u32, 1)),
+            IconKey::MimeType("text/plain" | "text/markdown") => Ok(png(32, 2)),
+            IconKey::Extension(".bin") if size <= 16 => Ok(png(size as u32, 3)),
+            _ => Err(not_found()),
+        }
+    }
+
+    fn svg(&self, key: IconKey, _size: i32) -> Result<Vec<u8>, Error> {
+        match key {
+            IconKey::MimeType("application/pdf") => Ok(SVG.to_vec()),
+            _ => Err(not_found()),
+        }
+    }
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("systemicons-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn options() -> ExportOptions {
+    ExportOptions { sizes: vec![16, 32], scales: vec![1, 2], svg: true }
+}
+
+#[test]
+fn directory_with_manifest() {
+    let dir = temp_dir("export-dir");
+    let inputs = [ExportInput::Extension("PDF".to_string()), ExportInput::Extension(".bin".to_string()), ExportInput::MimeType("text/markdown".to_string())];
+    let manifest = export_with(&Source, &inputs, &options(), &ExportTarget::Directory(dir.clone())).unwrap();
+
+    assert_eq!(manifest.extensions[".pdf"].mime_type.as_deref(), Some("application/pdf"));
+    assert!(manifest.extensions[".pdf"].icons.is_empty());
+    // without MIME type the extension has its own icons, only the ones which were found
+    assert_eq!(manifest.extensions[".bin"].mime_type, None);
+    assert_eq!(manifest.extensions[".bin"].icons.keys().collect::<Vec<_>>(), ["16"]);
+
+    let pdf = &manifest.mime_types["application/pdf"];
+    assert_eq!(pdf.keys().collect::<Vec<_>>(), ["16", "16@2x", "32", "32@2x", "svg"]);
+    // 16@2x and 32 are the same picture, so they share the file
+    assert_eq!(pdf["16@2x"], pdf["32"]);
+    assert!(pdf["svg"].starts_with("icons/") && pdf["svg"].ends_with(".svg"));
+    assert_eq!(fs::read(dir.join(&pdf["svg"])).unwrap(), SVG);
+
+    // the 32 pixel icon is scaled to every size
+    let markdown = &manifest.mime_types["text/markdown"];
+    let image = image::open(dir.join(&markdown["16"])).unwrap();
+    assert_eq!((image.width(), image.height()), (16, 16));
+    assert_eq!(fs::read_dir(dir.join("icons")).unwrap().count(), 8);
+
+    let json: serde_json::Value = serde_json::from_slice(&fs::read(dir.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(json["extensions"][".pdf"]["mime_type"], "application/pdf");
+    assert_eq!(json["mime_types"]["application/pdf"]["32@2x"], pdf["32@2x"].as_str());
+    assert_eq!(json["sizes"], serde_json::json!([16, 32]));
+}
+
+#[test]
+fn zip_with_all_mime_types() {
+    let dir = temp_dir("export-zip");
+    fs::create_dir_all(&dir).unwrap();
+    let zip = dir.join("icons.zip");
+    let options = ExportOptions { sizes: vec![16], scales: vec![1], svg: false };
+    let manifest = export_with(&Source, &[ExportInput::AllMimeTypes], &options, &ExportTarget::Zip(zip.clone())).unwrap();
+    assert_eq!(manifest.mime_types.keys().collect::<Vec<_>>(), ["application/pdf", "text/markdown", "text/plain"]);
+    assert_eq!(manifest.mime_types["text/markdown"], manifest.mime_types["text/plain"]);
+
+    let data = fs::read(&zip).unwrap();
+    assert!(data.starts_with(b"PK\x03\x04"));
+    // the end of central directory record counts two icons and the manifest
+    let end = &data[data.len() - 22..];
+    assert!(end.starts_with(b"PK\x05\x06"));
+    assert_eq!(u16::from_le_bytes([end[10], end[11]]), 3);
+    let names: Vec<&str> = manifest.mime_types.values().map(|files| files["16"].as_str()).collect();
+    for name in names.iter().chain(&["manifest.json"]) {
+        assert!(data.windows(name.len()).any(|window| window == name.as_bytes()), "{}", name);
+    }
+}
+
+#[test]
+fn invalid_options() {
+    let options = ExportOptions { sizes: vec![0], scales: vec![1], svg: false };
+    let err = export_with(&Source, &[ExportInput::AllMimeTypes], &options, &ExportTarget::Directory(temp_dir("export-invalid"))).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }));
+    // the size in pixels overflows
+    let dir = temp_dir("export-overflow");
+    let options = ExportOptions { sizes: vec![16, i32::MAX / 2], scales: vec![1, 3], svg: false };
+    let err = export_with(&Source, &[ExportInput::AllMimeTypes], &options, &ExportTarget::Directory(dir.clone())).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }));
+    assert!(!dir.exists());
+}
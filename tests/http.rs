@@ -0,0 +1,115 @@
+#![cfg(feature = "http")]
+
+use std::io::Cursor;
+
+use http::{Request, StatusCode};
+use image::{ImageFormat, RgbaImage};
+use systemicons::{
+    http::{handle_with, Format, IconParams},
+    Error,
+};
+
+const SVG: &[u8] = b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+
+fn png(size: u32) -> Vec<u8> {
+    let mut png = Cursor::new(Vec::new());
+    RgbaImage::from_pixel(size, size, [10, 20, 30, 255].into()).write_to(&mut png, ImageFormat::Png).unwrap();
+    png.into_inner()
+}
+
+/// PNG icons for `.txt` and `.svg`, a scalable icon only for `.svg`
+fn load(ext: &str, size: i32, format: Format) -> Result<Vec<u8>, Error> {
+    match (ext, format) {
+        (".svg", Format::Svg) => Ok(SVG.to_vec()),
+        (".txt" | ".svg", Format::Png) => Ok(png(size as u32)),
+        (".broken", _) => Err(Error::BackendUnavailable { backend: "GTK", reason: "No display".to_string(), source: None }),
+        _ => Err(Error::NotFound { input: ext.to_string(), size: Some(size), reason: "No icon".to_string() }),
+    }
+}
+
+fn get(uri: &str, headers: &[(&str, &str)]) -> http::Response<Vec<u8>> {
+    let mut request = Request::builder().uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    handle_with(&request.body(()).unwrap(), load)
+}
+
+fn header<'a>(response: &'a http::Response<Vec<u8>>, name: &str) -> &'a str {
+    response.headers().get(name).map(|value| value.to_str().unwrap()).unwrap_or_default()
+}
+
+#[test]
+fn parse_params() {
+    let params = IconParams::parse("ext=txt&size=16&scale=2&format=WEBP&other=1").unwrap();
+    assert_eq!(params, IconParams { ext: ".txt".to_string(), size: 16, scale: 2, format: Some(Format::WebP) });
+    assert_eq!(params.pixels(), 32);
+    assert_eq!(IconParams::parse("ext=%2Etar%2Egz").unwrap().ext, ".tar.gz");
+    for query in ["size=16", "ext=.txt&size=0", "ext=.txt&size=big", "ext=.txt&scale=9", "ext=.txt&format=gif", "ext=%zz"] {
+        assert!(IconParams::parse(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn png_with_caching_headers() {
+    let response = get("/icon?ext=.txt&size=16&scale=2", &[]);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(header(&response, "content-type"), "image/png");
+    assert_eq!(header(&response, "cache-control"), "public, max-age=31536000, immutable");
+    assert_eq!(header(&response, "vary"), "Accept");
+    assert_eq!(image::load_from_memory(response.body()).unwrap().width(), 32);
+    let etag = header(&response, "etag");
+    assert!(etag.starts_with('"') && etag.len() == 18, "{}", etag);
+    assert_eq!(get("/icon?ext=.txt&size=16&scale=2", &[]).headers()["etag"], etag);
+    assert_ne!(header(&get("/icon?ext=.txt&size=16", &[]), "etag"), etag);
+}
+
+#[test]
+fn not_modified() {
+    let etag = header(&get("/icon?ext=.txt", &[]), "etag").to_string();
+    for if_none_match in [etag.clone(), format!("\"other\", W/{}", etag), "*".to_string()] {
+        let response = get("/icon?ext=.txt", &[("if-none-match", &if_none_match)]);
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+        assert_eq!(header(&response, "etag"), etag);
+    }
+    assert_eq!(get("/icon?ext=.txt", &[("if-none-match", "\"other\"")]).status(), StatusCode::OK);
+}
+
+#[test]
+fn negotiation() {
+    let chrome = "image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8";
+    let firefox = "image/avif,image/webp,*/*";
+    let content_type = |ext: &str, accept: &str| header(&get(&format!("/icon?ext={}", ext), &[("accept", accept)]), "content-type").to_string();
+    assert_eq!(content_type(".svg", chrome), "image/svg+xml");
+    assert_eq!(content_type(".txt", chrome), "image/webp");
+    assert_eq!(content_type(".svg", firefox), "image/webp");
+    assert_eq!(content_type(".svg", "image/png, image/webp;q=0.5"), "image/png");
+    assert_eq!(content_type(".svg", "image/*, image/webp;q=0"), "image/svg+xml");
+
+    let response = get("/icon?ext=.txt", &[("accept", firefox)]);
+    assert_eq!(image::guess_format(response.body()).unwrap(), ImageFormat::WebP);
+    assert_eq!(get("/icon?ext=.txt", &[("accept", "text/html")]).status(), StatusCode::NOT_ACCEPTABLE);
+
+    let response = get("/icon?ext=.svg&format=svg", &[("accept", "image/png")]);
+    assert_eq!(response.body(), SVG);
+    assert_eq!(header(&response, "vary"), "");
+    assert_eq!(get("/icon?ext=.txt&format=svg", &[]).status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(get("/icon?size=16", &[]).status(), StatusCode::BAD_REQUEST);
+    assert_eq!(get("/icon?ext=.unknown", &[]).status(), StatusCode::NOT_FOUND);
+    let response = get("/icon?ext=.broken", &[]);
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(header(&response, "cache-control"), "no-store");
+
+    let response = handle_with(&Request::post("/icon?ext=.txt").body(()).unwrap(), load);
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(header(&response, "allow"), "GET, HEAD");
+    let response = handle_with(&Request::head("/icon?ext=.txt").body(()).unwrap(), load);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.body().is_empty());
+    assert_ne!(header(&response, "content-length"), "0");
+}
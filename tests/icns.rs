@@ -0,0 +1,90 @@
+use systemicons::{
+    icns::{Format, IconFamily, IconSize},
+    ico::IconImage,
+    Error,
+};
+
+/// A gradient with a transparent border and some runs, so that literals and repeats are encoded
+fn test_image(size: u32) -> IconImage {
+    let rgba = (0..size * size)
+        .flat_map(|i| {
+            let (x, y) = (i % size, i / size);
+            if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+                [0, 0, 0, 0]
+            } else {
+                [(x * 7) as u8, (y * 3) as u8, 0x80, if x < size / 2 { 0xff } else { 0x80 }]
+            }
+        })
+        .collect();
+    IconImage::from_rgba(size, size, rgba).unwrap()
+}
+
+fn decode_png(png: &[u8]) -> IconImage {
+    IconImage::from_png(png).unwrap()
+}
+
+#[test]
+fn rle_rgb_with_mask() {
+    for (os_type, size) in [(b"is32", 16), (b"il32", 32), (b"ih32", 48), (b"it32", 128)] {
+        let mut family = IconFamily::new();
+        family.add_image(os_type, &test_image(size)).unwrap();
+        let family = IconFamily::read(&family.write()).unwrap();
+        assert_eq!(family.elements.len(), 2);
+        assert_eq!(family.decode(os_type).unwrap(), test_image(size), "{}", String::from_utf8_lossy(os_type));
+    }
+}
+
+#[test]
+fn argb_and_png() {
+    let mut family = IconFamily::new();
+    family.add_image(b"ic04", &test_image(16)).unwrap();
+    family.add_image(b"ic07", &test_image(128)).unwrap();
+    family.add_image(b"ic12", &test_image(64)).unwrap();
+    let family = IconFamily::read(&family.write()).unwrap();
+    assert_eq!(
+        family.sizes(),
+        vec![
+            IconSize { os_type: *b"ic04", size: 16, scale: 1, format: Format::Argb },
+            IconSize { os_type: *b"ic07", size: 128, scale: 1, format: Format::Png },
+            IconSize { os_type: *b"ic12", size: 32, scale: 2, format: Format::Png },
+        ]
+    );
+    assert_eq!(family.decode(b"ic04").unwrap(), test_image(16));
+    assert_eq!(family.decode(b"ic12").unwrap(), test_image(64));
+}
+
+#[test]
+fn best_fit() {
+    let mut family = IconFamily::new();
+    family.add_image(b"is32", &test_image(16)).unwrap();
+    family.add_image(b"il32", &test_image(32)).unwrap();
+    family.add_image(b"ic11", &test_image(32)).unwrap();
+    family.add_image(b"ic13", &test_image(256)).unwrap();
+    assert_eq!(decode_png(&family.get_png(16).unwrap()), test_image(16));
+    assert_eq!(family.get_png(20).unwrap(), test_image(32).to_png().unwrap());
+    assert_eq!(decode_png(&family.get_png(100).unwrap()).width, 256);
+    assert_eq!(decode_png(&family.get_png(1024).unwrap()).width, 256);
+}
+
+#[test]
+fn jpeg2000_is_listed_but_not_decoded() {
+    let mut family = IconFamily::new();
+    family.add_image(b"il32", &test_image(32)).unwrap();
+    family.elements.push(systemicons::icns::IcnsElement {
+        os_type: *b"ic09",
+        data: b"\0\0\0\x0cjP  \r\n\x87\n".to_vec(),
+    });
+    let family = IconFamily::read(&family.write()).unwrap();
+    assert_eq!(family.sizes()[1].format, Format::Jpeg2000);
+    assert_eq!(family.sizes()[1].pixels(), 512);
+    assert!(matches!(family.decode(b"ic09").unwrap_err(), Error::Decode { .. }));
+    assert_eq!(decode_png(&family.get_png(512).unwrap()).width, 32);
+}
+
+#[test]
+fn invalid_input() {
+    assert!(matches!(IconFamily::read(b"\x89PNG").unwrap_err(), Error::Decode { .. }));
+    let err = IconFamily::new().add_image(b"ic07", &test_image(16)).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }));
+    assert!(matches!(IconFamily::new().get_png(32).unwrap_err(), Error::NotFound { .. }));
+}
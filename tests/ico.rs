@@ -0,0 +1,101 @@
+use systemicons::{
+    ico::{Encoding, IconDir, IconEntry, IconImage, ResourceType},
+    Error,
+};
+
+const RED: [u8; 4] = [0xff, 0, 0, 0xff];
+const BLUE: [u8; 4] = [0, 0, 0xff, 0xff];
+const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+/// A checkerboard of red and blue with a transparent border
+fn test_image(size: u32) -> IconImage {
+    let rgba = (0..size * size)
+        .flat_map(|i| {
+            let (x, y) = (i % size, i / size);
+            if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+                CLEAR
+            } else if (x + y) % 2 == 0 {
+                RED
+            } else {
+                BLUE
+            }
+        })
+        .collect();
+    IconImage::from_rgba(size, size, rgba).unwrap()
+}
+
+fn round_trip(resource_type: ResourceType, entries: Vec<IconEntry>) -> IconDir {
+    let mut dir = IconDir::new(resource_type);
+    dir.entries = entries;
+    IconDir::read(&dir.write()).unwrap()
+}
+
+#[test]
+fn bitmap_depths() {
+    for bit_count in [1, 4, 8, 24, 32] {
+        let image = test_image(13);
+        let dir = round_trip(ResourceType::Icon, vec![IconEntry::encode(&image, Encoding::Bmp(bit_count)).unwrap()]);
+        let entry = &dir.entries[0];
+        assert_eq!((entry.width, entry.height, entry.bit_count), (13, 13, bit_count));
+        assert_eq!(entry.decode().unwrap(), image, "{} bits per pixel", bit_count);
+    }
+}
+
+#[test]
+fn too_many_colors_for_palette() {
+    let rgba = (0..16u32 * 16).flat_map(|i| [i as u8, 0, 0, 255]).collect();
+    let image = IconImage::from_rgba(16, 16, rgba).unwrap();
+    assert!(IconEntry::encode(&image, Encoding::Bmp(8)).is_ok());
+    let err = IconEntry::encode(&image, Encoding::Bmp(4)).unwrap_err();
+    assert!(matches!(err, Error::InvalidInput { .. }));
+}
+
+#[test]
+fn alpha_channel() {
+    let rgba = (0..4u32 * 4).flat_map(|i| [10, 20, 30, (i * 16) as u8]).collect();
+    let image = IconImage::from_rgba(4, 4, rgba).unwrap();
+    let dir = round_trip(ResourceType::Icon, vec![IconEntry::encode(&image, Encoding::Bmp(32)).unwrap()]);
+    assert_eq!(dir.entries[0].decode().unwrap(), image);
+}
+
+#[test]
+fn multiple_images_and_png() {
+    let entries = vec![
+        IconEntry::encode(&test_image(16), Encoding::Bmp(32)).unwrap(),
+        IconEntry::encode(&test_image(32), Encoding::Bmp(8)).unwrap(),
+        IconEntry::encode(&test_image(256), Encoding::Png).unwrap(),
+    ];
+    let png = entries[2].data.clone();
+    let dir = round_trip(ResourceType::Icon, entries);
+    let sizes: Vec<_> = dir.entries.iter().map(|entry| (entry.width, entry.is_png())).collect();
+    assert_eq!(sizes, vec![(16, false), (32, false), (256, true)]);
+    assert_eq!(dir.entries[2].to_png().unwrap(), png);
+    assert_eq!(dir.entries[2].decode().unwrap(), test_image(256));
+    assert_eq!(dir.best_fit(20).unwrap().width, 32);
+    assert_eq!(dir.best_fit(512).unwrap().width, 256);
+}
+
+#[test]
+fn large_bitmap() {
+    let dir = round_trip(ResourceType::Icon, vec![IconEntry::encode(&test_image(256), Encoding::Bmp(32)).unwrap()]);
+    assert_eq!((dir.entries[0].width, dir.entries[0].height), (256, 256));
+    assert_eq!(dir.entries[0].decode().unwrap(), test_image(256));
+}
+
+#[test]
+fn cursor_hotspot() {
+    let entry = IconEntry::encode(&test_image(32), Encoding::Bmp(32)).unwrap().with_hotspot(5, 7);
+    let dir = round_trip(ResourceType::Cursor, vec![entry]);
+    assert_eq!(dir.resource_type, ResourceType::Cursor);
+    assert_eq!(dir.entries[0].hotspot, Some((5, 7)));
+}
+
+#[test]
+fn invalid_file() {
+    let err = IconDir::read(b"\x89PNG\r\n\x1a\n").unwrap_err();
+    assert!(matches!(err, Error::Decode { .. }));
+    let mut truncated = IconDir::new(ResourceType::Icon);
+    truncated.entries.push(IconEntry::encode(&test_image(16), Encoding::Bmp(24)).unwrap());
+    let data = truncated.write();
+    assert!(IconDir::read(&data[..data.len() - 10]).is_err());
+}
@@ -0,0 +1,46 @@
+#![cfg(all(target_os = "linux", feature = "backend-gtk"))]
+
+use std::{
+    fs::{self, File},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+const APPIMAGE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/appimage/type2-zstd.AppImage");
+/// The number of icons kept in a cache directory
+const MAX_CACHED_ICONS: usize = 1000;
+
+#[test]
+fn least_recently_used_icons_are_removed() {
+    let cache_home = std::env::temp_dir().join(format!("systemicons-test-{}-cache", std::process::id()));
+    let dir = cache_home.join("systemicons/appimage");
+    fs::create_dir_all(&dir).unwrap();
+    std::env::set_var("XDG_CACHE_HOME", &cache_home);
+
+    let file = systemicons::get_icon_for_path_as_file(APPIMAGE, 32).unwrap();
+    let name = Path::new(&file).file_name().unwrap().to_str().unwrap().to_string();
+    assert!(Path::new(&file).starts_with(&dir));
+    assert_eq!(name.len(), "0123456789abcdef-32.png".len());
+    assert!(name.ends_with("-32.png"));
+    assert!(fs::read(&file).unwrap().starts_with(b"\x89PNG\r\n\x1a\nSMALL"));
+
+    // fill the cache with older icons, the extracted one has been used again recently
+    let old = SystemTime::now() - Duration::from_secs(3600);
+    for i in 0..MAX_CACHED_ICONS {
+        let stale = dir.join(format!("{:016x}-16.png", i));
+        File::create(&stale).unwrap().set_modified(old + Duration::from_secs(i as u64)).unwrap();
+    }
+    File::options().append(true).open(&file).unwrap().set_modified(old).unwrap();
+    assert_eq!(systemicons::get_icon_for_path_as_file(APPIMAGE, 32).unwrap(), file);
+
+    let large = systemicons::get_icon_for_path_as_file(APPIMAGE, 256).unwrap();
+    let remaining = fs::read_dir(&dir).unwrap().count();
+    let oldest_remains = dir.join(format!("{:016x}-16.png", 0)).exists();
+    let used_remains = Path::new(&file).exists();
+    fs::remove_dir_all(&cache_home).unwrap();
+    assert_eq!(remaining, MAX_CACHED_ICONS);
+    assert!(!oldest_remains);
+    assert!(used_remains);
+    assert!(large.ends_with("-256.png"));
+    assert_eq!(large[..large.len() - "256.png".len()], file[..file.len() - "32.png".len()]);
+}
@@ -0,0 +1,111 @@
+use proptest::prelude::*;
+use systemicons::{
+    icns::IconFamily,
+    ico::{IconDir, IconEntry},
+    input::{self, MAX_LENGTH, MAX_SIZE},
+    pe::PeFile,
+    shortcut::Shortcut,
+    Error,
+};
+
+#[test]
+fn file_names() {
+    assert_eq!(input::file_name(".pdf").unwrap(), ".pdf");
+    assert_eq!(input::file_name("/tmp/report.pdf").unwrap(), "report.pdf");
+    assert_eq!(input::file_name("/tmp/dir/").unwrap(), "dir");
+    for path in ["", "..", "/", "/tmp/..", ".", "a\0b"] {
+        assert!(matches!(input::file_name(path), Err(Error::InvalidInput { .. })), "{:?}", path);
+    }
+}
+
+const INVALID_REQUESTS: &[(&str, i32)] = &[(".pdf\0", 16), ("", 16), (".pdf", 0), (".pdf", -1), (".pdf", MAX_SIZE + 1)];
+
+#[test]
+fn public_functions_reject_invalid_input() {
+    for &(ext, size) in INVALID_REQUESTS {
+        assert!(matches!(systemicons::get_app_bundle_icon(ext, size), Err(Error::InvalidInput { .. })));
+    }
+}
+
+#[cfg(any(not(target_os = "linux"), feature = "backend-gtk"))]
+#[test]
+fn get_icon_rejects_invalid_input() {
+    for &(ext, size) in INVALID_REQUESTS {
+        let err = systemicons::get_icon(ext, size).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{:?}", err);
+        assert_eq!(err.input(), ext);
+    }
+    assert!(matches!(systemicons::get_icon(&".".repeat(MAX_LENGTH + 1), 16), Err(Error::InvalidInput { .. })));
+}
+
+proptest! {
+    #[test]
+    fn sizes(size in any::<i32>()) {
+        prop_assert_eq!(input::size(size).is_ok(), (1..=MAX_SIZE).contains(&size));
+    }
+
+    #[test]
+    fn texts(text in any::<String>()) {
+        let valid = !text.is_empty() && !text.contains('\0') && text.len() <= MAX_LENGTH;
+        prop_assert_eq!(input::text(&text, "file extension").is_ok(), valid);
+    }
+
+    #[test]
+    fn file_names_have_no_separator(path in "[./a\\x00]{0,12}") {
+        if let Ok(name) = input::file_name(&path) {
+            prop_assert!(!name.is_empty() && !name.contains('/') && name != "." && name != "..");
+        }
+    }
+
+    #[test]
+    fn nul_characters_are_invalid(prefix in "[a-z.]{0,8}", suffix in "[a-z.]{0,8}", size in any::<i32>()) {
+        let ext = format!("{}\0{}", prefix, suffix);
+        let invalid = |result: Result<Vec<u8>, Error>| matches!(result, Err(Error::InvalidInput { .. }));
+        prop_assert!(invalid(systemicons::get_app_bundle_icon(&ext, size)));
+    }
+
+    #[cfg(any(not(target_os = "linux"), feature = "backend-gtk"))]
+    #[test]
+    fn get_icon_rejects_nul_characters(prefix in "[a-z.]{0,8}", suffix in "[a-z.]{0,8}", size in any::<i32>()) {
+        let ext = format!("{}\0{}", prefix, suffix);
+        let invalid = |result: Result<Vec<u8>, Error>| matches!(result, Err(Error::InvalidInput { .. }));
+        prop_assert!(invalid(systemicons::get_icon(&ext, size)));
+    }
+
+    #[test]
+    fn icon_files_do_not_panic(magic in prop::sample::select(vec![&b""[..], b"\0\0\x01\0", b"icns", b"MZ", b"\x89PNG\r\n\x1a\n", b"L\0\0\0"]), data in prop::collection::vec(any::<u8>(), 0..512)) {
+        let data = [magic, &data].concat();
+        if let Ok(dir) = IconDir::read(&data) {
+            for entry in &dir.entries {
+                let _ = entry.decode();
+            }
+        }
+        if let Ok(family) = IconFamily::read(&data) {
+            let _ = family.get_png(32);
+        }
+        if let Ok(pe_file) = PeFile::parse(data.clone()) {
+            for group in pe_file.icon_groups() {
+                let _ = pe_file.get_icon(&group, 32);
+            }
+        }
+        let _ = IconEntry::from_data(data.clone()).map(|entry| entry.decode());
+        let _ = Shortcut::parse_lnk(&data);
+        let _ = Shortcut::parse_url(&String::from_utf8_lossy(&data));
+    }
+}
+
+#[cfg(feature = "http")]
+proptest! {
+    #[test]
+    fn queries_do_not_panic(query in "[a-z=&%.0-9\\x00]{0,40}") {
+        if let Ok(params) = systemicons::http::IconParams::parse(&query) {
+            prop_assert!(params.ext.starts_with('.'));
+            prop_assert!(params.pixels() > 0);
+        }
+        let request = http::Request::builder().uri(format!("/icon?{}", query.replace('\0', "%00"))).body(());
+        if let Ok(request) = request {
+            let response = systemicons::http::handle_with(&request, |ext, size, _| Err(Error::NotFound { input: ext.to_string(), size: Some(size), reason: String::new() }));
+            prop_assert!(!response.status().is_server_error());
+        }
+    }
+}
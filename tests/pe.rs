@@ -0,0 +1,133 @@
+use std::io::Cursor;
+
+use common::{build_pe, group, RT_GROUP_ICON, RT_ICON};
+use image::{ImageFormat, Rgba, RgbaImage};
+use systemicons::{
+    pe::{self, PeFile, ResourceId},
+    Error,
+};
+
+mod common;
+
+const PIXEL: [u8; 4] = [0x10, 0x20, 0x30, 0xff];
+
+fn png_icon() -> Vec<u8> {
+    let mut png = Vec::new();
+    RgbaImage::from_pixel(256, 256, Rgba(PIXEL)).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+    png
+}
+
+/// A 16x16 32 bit DIB with an empty AND mask
+fn bmp_icon() -> Vec<u8> {
+    let mut dib = Vec::new();
+    for value in [40u32, 16, 32] {
+        dib.extend(value.to_le_bytes());
+    }
+    dib.extend(1u16.to_le_bytes());
+    dib.extend(32u16.to_le_bytes());
+    dib.extend([0; 24]);
+    for _ in 0..16 * 16 {
+        dib.extend([PIXEL[2], PIXEL[1], PIXEL[0], PIXEL[3]]);
+    }
+    dib.extend([0; 16 * 4]);
+    dib
+}
+
+fn test_pe() -> Vec<u8> {
+    let (png, bmp) = (png_icon(), bmp_icon());
+    let groups = vec![
+        (ResourceId::Name("APPICON".to_string()), group(&[(16, 32, bmp.len(), 2)])),
+        (ResourceId::Id(101), group(&[(0, 32, png.len(), 1), (16, 32, bmp.len(), 2)])),
+    ];
+    build_pe(&[(RT_ICON, vec![(ResourceId::Id(1), png), (ResourceId::Id(2), bmp)]), (RT_GROUP_ICON, groups)])
+}
+
+fn decode(png: &[u8]) -> RgbaImage {
+    image::load_from_memory_with_format(png, ImageFormat::Png).unwrap().to_rgba8()
+}
+
+#[test]
+fn lists_icon_groups() {
+    let pe_file = PeFile::parse(test_pe()).unwrap();
+    let groups = pe_file.icon_groups();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].id, ResourceId::Name("APPICON".to_string()));
+    assert_eq!(groups[1].id, ResourceId::Id(101));
+    let sizes: Vec<_> = groups[1].entries.iter().map(|entry| (entry.width, entry.height, entry.is_png)).collect();
+    assert_eq!(sizes, vec![(256, 256, true), (16, 16, false)]);
+}
+
+#[test]
+fn png_compressed_icon() {
+    let pe_file = PeFile::parse(test_pe()).unwrap();
+    let group = &pe_file.icon_groups()[1];
+    assert_eq!(pe_file.get_icon(group, 48).unwrap(), png_icon());
+}
+
+#[test]
+fn bitmap_icon() {
+    let pe_file = PeFile::parse(test_pe()).unwrap();
+    let group = &pe_file.icon_groups()[1];
+    let image = decode(&pe_file.get_icon(group, 16).unwrap());
+    assert_eq!(image.dimensions(), (16, 16));
+    assert_eq!(image.get_pixel(3, 5), &Rgba(PIXEL));
+}
+
+#[test]
+fn icon_by_index_and_resource_id() {
+    let file = std::env::temp_dir().join(format!("systemicons-test-{}.exe", std::process::id()));
+    std::fs::write(&file, test_pe()).unwrap();
+    let first = pe::get_icon(&file, 0, 256);
+    let by_id = pe::get_icon(&file, -101, 256);
+    let missing = pe::get_icon(&file, -102, 256);
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(decode(&first.unwrap()).dimensions(), (16, 16));
+    assert_eq!(by_id.unwrap(), png_icon());
+    assert!(matches!(missing.unwrap_err(), Error::NotFound { .. }));
+}
+
+#[test]
+fn resource_id_out_of_range() {
+    let file = std::env::temp_dir().join(format!("systemicons-test-{}-range.exe", std::process::id()));
+    std::fs::write(&file, test_pe()).unwrap();
+    // -65637 would be truncated to the resource ID 101
+    let results = [pe::get_icon(&file, -65637, 256), pe::get_icon(&file, i32::MIN, 256)];
+    std::fs::remove_file(&file).unwrap();
+    for result in results {
+        assert!(matches!(result.unwrap_err(), Error::InvalidInput { .. }));
+    }
+}
+
+#[test]
+fn only_the_resources_are_read() {
+    // a sparse file with gigabytes after the resource section, like an installer with its payload
+    let file = std::env::temp_dir().join(format!("systemicons-test-{}-large.exe", std::process::id()));
+    let pe = test_pe();
+    std::fs::write(&file, &pe).unwrap();
+    std::fs::File::options().write(true).open(&file).unwrap().set_len(pe.len() as u64 + (4 << 30)).unwrap();
+    let icon = pe::get_icon(&file, -101, 256);
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(icon.unwrap(), png_icon());
+}
+
+#[test]
+fn truncated_file() {
+    let file = std::env::temp_dir().join(format!("systemicons-test-{}-truncated.exe", std::process::id()));
+    let pe = test_pe();
+    let mut results = Vec::new();
+    for len in [0, 2, 64, 0x90, 0x200] {
+        std::fs::write(&file, &pe[..len]).unwrap();
+        results.push((len, PeFile::open(&file).map(|pe_file| pe_file.icon_groups().len())));
+    }
+    std::fs::remove_file(&file).unwrap();
+    for (len, result) in results {
+        assert!(matches!(result, Err(Error::Decode { .. }) | Ok(0)), "{} bytes", len);
+    }
+}
+
+#[test]
+fn no_pe_file() {
+    let err = PeFile::parse(b"\x7fELF".to_vec()).err().unwrap();
+    assert!(matches!(err, Error::Decode { .. }));
+    assert!(pe::has_pe_extension(std::path::Path::new("C:/Windows/notepad.EXE")));
+}
@@ -0,0 +1,194 @@
+use std::{fs, path::PathBuf};
+
+use systemicons::{
+    ico::{Encoding, IconDir, IconEntry, IconImage, ResourceType},
+    shortcut::{self, IconLocation, Shortcut},
+    Error,
+};
+
+const CLSID: [u8; 16] = [0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46];
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x01;
+const HAS_LINK_INFO: u32 = 0x02;
+const HAS_RELATIVE_PATH: u32 = 0x08;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+const HAS_EXP_ICON: u32 = 0x4000;
+
+fn utf16(string: &str) -> Vec<u8> {
+    string.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+/// Builds a Unicode Shell Link, `strings` are the relative path and the icon location
+fn build_lnk(flags: u32, id_list: Option<Vec<u8>>, local_path: Option<&str>, strings: &[&str], icon_environment: Option<&str>) -> Vec<u8> {
+    let mut data = 0x4cu32.to_le_bytes().to_vec();
+    data.extend(CLSID);
+    data.extend((flags | IS_UNICODE).to_le_bytes());
+    data.resize(0x38, 0);
+    data.extend(2i32.to_le_bytes());
+    data.resize(0x4c, 0);
+    if let Some(id_list) = id_list {
+        data.extend((id_list.len() as u16).to_le_bytes());
+        data.extend(id_list);
+    }
+    if let Some(local_path) = local_path {
+        // LinkInfo with an ANSI local base path and an empty suffix
+        let header_size = 0x1cu32;
+        let mut link_info = Vec::new();
+        for value in [0, header_size, 1, 0, header_size, 0, header_size + local_path.len() as u32 + 1] {
+            link_info.extend(value.to_le_bytes());
+        }
+        link_info.extend(local_path.as_bytes());
+        link_info.extend([0, 0]);
+        let size = link_info.len() as u32;
+        link_info[..4].copy_from_slice(&size.to_le_bytes());
+        data.extend(link_info);
+    }
+    for string in strings {
+        data.extend((string.encode_utf16().count() as u16).to_le_bytes());
+        data.extend(utf16(string));
+    }
+    if let Some(path) = icon_environment {
+        data.extend(0x314u32.to_le_bytes());
+        data.extend(0xa000_0007u32.to_le_bytes());
+        let mut ansi = path.as_bytes().to_vec();
+        ansi.resize(260, 0);
+        let mut unicode = utf16(path);
+        unicode.resize(520, 0);
+        data.extend(ansi);
+        data.extend(unicode);
+    }
+    data.extend(0u32.to_le_bytes());
+    data
+}
+
+fn shell_item(data: &[u8]) -> Vec<u8> {
+    let mut item = ((data.len() + 2) as u16).to_le_bytes().to_vec();
+    item.extend(data);
+    item
+}
+
+/// The IDList of "My Computer\C:\Program Files\Long Name.txt"
+fn id_list() -> Vec<u8> {
+    let mut my_computer = vec![0x1f, 0x50];
+    my_computer.extend([0xe0, 0x4f, 0xd0, 0x20, 0xea, 0x3a, 0x69, 0x10, 0xa2, 0xd8, 0x08, 0x00, 0x2b, 0x30, 0x30, 0x9d]);
+    let mut volume = b"\x2fC:\\".to_vec();
+    volume.resize(23, 0);
+    let file_entry = |item_type: u8, short_name: &str, long_name: &str| {
+        let mut item = vec![item_type, 0];
+        item.extend([0; 10]);
+        item.extend(short_name.as_bytes());
+        item.push(0);
+        if item.len() % 2 == 1 {
+            item.push(0);
+        }
+        let mut extension = vec![0, 0];
+        extension.extend(9u16.to_le_bytes());
+        extension.extend(0xbeef_0004u32.to_le_bytes());
+        extension.resize(46, 0);
+        extension.extend(utf16(long_name));
+        extension.extend([0, 0, 0, 0]);
+        let len = extension.len() as u16;
+        extension[..2].copy_from_slice(&len.to_le_bytes());
+        item.extend(extension);
+        item
+    };
+    let mut list = shell_item(&my_computer);
+    list.extend(shell_item(&volume));
+    list.extend(shell_item(&file_entry(0x31, "PROGRA~1", "Program Files")));
+    list.extend(shell_item(&file_entry(0x32, "LONGNA~1.TXT", "Long Name.txt")));
+    list.extend([0, 0]);
+    list
+}
+
+fn ico(color: [u8; 4]) -> Vec<u8> {
+    let image = IconImage::from_rgba(16, 16, color.repeat(256)).unwrap();
+    let mut dir = IconDir::new(ResourceType::Icon);
+    dir.entries.push(IconEntry::encode(&image, Encoding::Bmp(32)).unwrap());
+    dir.write()
+}
+
+fn color(png: &[u8]) -> [u8; 4] {
+    IconImage::from_png(png).unwrap().rgba[..4].try_into().unwrap()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("systemicons-test-{}-{}", std::process::id(), name));
+    fs::create_dir_all(dir.join("icons")).unwrap();
+    fs::write(dir.join("icons").join("custom.ico"), ico([1, 2, 3, 255])).unwrap();
+    fs::write(dir.join("target.ico"), ico([4, 5, 6, 255])).unwrap();
+    dir
+}
+
+#[test]
+fn explicit_icon_location() {
+    let dir = temp_dir("explicit");
+    let lnk = build_lnk(HAS_LINK_INFO | HAS_ICON_LOCATION, None, Some("C:\\Users\\Public\\report.txt"), &["icons\\custom.ico"], None);
+    fs::write(dir.join("Report.lnk"), lnk).unwrap();
+    let icon = shortcut::get_icon(&dir.join("Report.lnk"), 16);
+    let shortcut = Shortcut::open(&dir.join("Report.lnk"));
+    fs::remove_dir_all(&dir).unwrap();
+    let icon = icon.unwrap();
+    assert_eq!(color(&icon.icon), [1, 2, 3, 255]);
+    assert_eq!(icon.target.as_deref(), Some("C:\\Users\\Public\\report.txt"));
+    assert_eq!(shortcut.unwrap().icon_location, Some(IconLocation { path: "icons\\custom.ico".to_string(), index: 2 }));
+}
+
+#[test]
+fn icon_of_target() {
+    let dir = temp_dir("target");
+    let lnk = build_lnk(HAS_LINK_INFO | HAS_RELATIVE_PATH, None, Some("D:\\Icons\\target.ico"), &[".\\target.ico"], None);
+    fs::write(dir.join("Target.lnk"), lnk).unwrap();
+    let icon = shortcut::get_icon(&dir.join("Target.lnk"), 16);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(color(&icon.unwrap().icon), [4, 5, 6, 255]);
+}
+
+#[test]
+fn icon_environment_block() {
+    let dir = temp_dir("environment");
+    std::env::set_var("SYSTEMICONS_TEST_ICONS", dir.join("icons"));
+    let lnk = build_lnk(
+        HAS_LINK_INFO | HAS_ICON_LOCATION | HAS_EXP_ICON,
+        None,
+        Some("C:\\app.exe"),
+        &["C:\\Expanded\\custom.ico"],
+        Some("%SYSTEMICONS_TEST_ICONS%\\custom.ico"),
+    );
+    let shortcut = Shortcut::parse_lnk(&lnk).unwrap();
+    fs::write(dir.join("App.lnk"), lnk).unwrap();
+    let icon = shortcut::get_icon(&dir.join("App.lnk"), 16);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(shortcut.icon_location.unwrap().path, "%SYSTEMICONS_TEST_ICONS%\\custom.ico");
+    assert_eq!(color(&icon.unwrap().icon), [1, 2, 3, 255]);
+}
+
+#[test]
+fn target_id_list() {
+    let shortcut = Shortcut::parse_lnk(&build_lnk(HAS_LINK_TARGET_ID_LIST, Some(id_list()), None, &[], None)).unwrap();
+    assert_eq!(shortcut.target.as_deref(), Some("C:\\Program Files\\Long Name.txt"));
+    assert_eq!(shortcut.icon_location, None);
+}
+
+#[test]
+fn internet_shortcut() {
+    let dir = temp_dir("url");
+    let icon_file = dir.join("icons").join("custom.ico");
+    let url = format!("[{{000214A0-0000-0000-C000-000000000046}}]\r\nProp3=19,11\r\n[InternetShortcut]\r\nURL=https://example.org/\r\nIconFile={}\r\nIconIndex=0\r\n", icon_file.to_str().unwrap());
+    fs::write(dir.join("Example.url"), url).unwrap();
+    let icon = shortcut::get_icon(&dir.join("Example.url"), 16);
+    fs::remove_dir_all(&dir).unwrap();
+    let icon = icon.unwrap();
+    assert_eq!(icon.target.as_deref(), Some("https://example.org/"));
+    assert_eq!(color(&icon.icon), [1, 2, 3, 255]);
+}
+
+#[test]
+fn invalid_shortcuts() {
+    assert!(matches!(Shortcut::parse_lnk(b"MZ\0\0").unwrap_err(), Error::Decode { .. }));
+    assert!(matches!(Shortcut::parse_url("[Desktop Entry]\nIcon=x").unwrap_err(), Error::Decode { .. }));
+    let mut truncated = build_lnk(HAS_LINK_INFO, None, Some("C:\\x"), &[], None);
+    truncated.truncate(0x50);
+    assert!(Shortcut::parse_lnk(&truncated).is_err());
+    let err = shortcut::get_icon(&std::env::temp_dir().join("systemicons-missing.lnk"), 16).unwrap_err();
+    assert!(matches!(err, Error::Io { .. }));
+}
@@ -0,0 +1,65 @@
+#![cfg(target_os = "linux")]
+
+use std::fs;
+
+const MOUNTINFO: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/volume/mountinfo");
+/// The udev database entries are named like `b8_17`, because `:` is not allowed in file names on Windows
+const UDEV_DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/volume/udev");
+
+/// Looks up the icon names with a copy of the udev database, whose entries are named like `b8:17` as in `/run/udev/data`
+fn icon_names(mount_path: &str) -> Vec<String> {
+    let udev_data = std::env::temp_dir().join(format!("systemicons-test-{}-volume{}", std::process::id(), mount_path.replace(['/', ' '], "-")));
+    fs::create_dir_all(&udev_data).unwrap();
+    for entry in fs::read_dir(UDEV_DATA).unwrap() {
+        let entry = entry.unwrap();
+        fs::copy(entry.path(), udev_data.join(entry.file_name().to_string_lossy().replacen('_', ":", 1))).unwrap();
+    }
+    let names = systemicons::get_volume_icon_names_from(mount_path, MOUNTINFO, udev_data.to_str().unwrap());
+    fs::remove_dir_all(&udev_data).unwrap();
+    names.unwrap()
+}
+
+fn first_icon_name(mount_path: &str) -> String {
+    icon_names(mount_path).remove(0)
+}
+
+#[test]
+fn internal_disk() {
+    assert_eq!(first_icon_name("/"), "drive-harddisk");
+}
+
+#[test]
+fn usb_stick() {
+    assert_eq!(first_icon_name("/media/user/STICK"), "drive-removable-media-usb");
+}
+
+#[test]
+fn optical_disc() {
+    assert_eq!(first_icon_name("/media/user/DVD"), "media-optical");
+}
+
+#[test]
+fn network_shares() {
+    assert_eq!(first_icon_name("/mnt/nas"), "folder-remote");
+    assert_eq!(first_icon_name("/home/user/remote"), "folder-remote");
+}
+
+#[test]
+fn udisks_icon_name_wins() {
+    assert_eq!(icon_names("/media/user/My Backup"), vec!["drive-removable-media-backup", "drive-harddisk"]);
+}
+
+#[test]
+fn sd_card_without_udev_entry() {
+    assert_eq!(first_icon_name("/media/user/CARD"), "media-flash");
+}
+
+#[test]
+fn virtual_file_system() {
+    assert_eq!(first_icon_name("/run/user/1000"), "folder");
+}
+
+#[test]
+fn path_inside_a_mount() {
+    assert_eq!(first_icon_name("/media/user/STICK/photos/2024"), "drive-removable-media-usb");
+}
@@ -0,0 +1,97 @@
+use std::{fs, path::PathBuf};
+
+use common::{build_pe, group, RT_GROUP_ICON, RT_ICON};
+use systemicons::{
+    ico::{Encoding, IconDir, IconEntry, IconImage, ResourceType},
+    pe::ResourceId,
+    windows_root::WindowsRoot,
+    Error,
+};
+
+mod common;
+
+const COLORS: [[u8; 4]; 4] = [[10, 0, 0, 255], [20, 0, 0, 255], [30, 0, 0, 255], [40, 0, 0, 255]];
+
+fn image(color: [u8; 4]) -> IconImage {
+    IconImage::from_rgba(16, 16, color.repeat(256)).unwrap()
+}
+
+/// A library with an icon group of one color for each of the resource IDs 1 to 4
+fn library() -> Vec<u8> {
+    let images: Vec<_> = COLORS.iter().map(|&color| IconEntry::encode(&image(color), Encoding::Bmp(32)).unwrap().data).collect();
+    let icons = images.iter().enumerate().map(|(i, data)| (ResourceId::Id(i as u16 + 1), data.clone())).collect();
+    let groups = images.iter().enumerate().map(|(i, data)| (ResourceId::Id(i as u16 + 1), group(&[(16, 32, data.len(), i as u16 + 1)]))).collect();
+    build_pe(&[(RT_ICON, icons), (RT_GROUP_ICON, groups)])
+}
+
+fn ico(color: [u8; 4]) -> Vec<u8> {
+    let mut dir = IconDir::new(ResourceType::Icon);
+    dir.entries.push(IconEntry::encode(&image(color), Encoding::Bmp(32)).unwrap());
+    dir.write()
+}
+
+fn utf16_with_bom(text: &str) -> Vec<u8> {
+    let mut data = vec![0xff, 0xfe];
+    data.extend(text.encode_utf16().flat_map(|c| c.to_le_bytes()));
+    data
+}
+
+/// A Windows root with `WINDOWS\system32\shell32.dll` and `Windows\System32\imageres.dll` and some folders
+fn windows_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("systemicons-test-{}-{}", std::process::id(), name));
+    fs::create_dir_all(root.join("WINDOWS").join("system32")).unwrap();
+    fs::write(root.join("WINDOWS").join("system32").join("shell32.dll"), library()).unwrap();
+    fs::write(root.join("WINDOWS").join("system32").join("IMAGERES.DLL"), library()).unwrap();
+    for folder in ["Plain", "Resource", "File"] {
+        fs::create_dir_all(root.join("Users").join(folder)).unwrap();
+    }
+    let desktop_ini = "[.ShellClassInfo]\r\nIconResource=%SystemRoot%\\system32\\imageres.dll,-2\r\n";
+    fs::write(root.join("Users").join("Resource").join("desktop.ini"), utf16_with_bom(desktop_ini)).unwrap();
+    let desktop_ini = "[ViewState]\r\nMode=\r\n[.ShellClassInfo]\r\nIconFile=Folder.ico\r\nIconIndex=0\r\n";
+    fs::write(root.join("Users").join("File").join("Desktop.ini"), desktop_ini).unwrap();
+    fs::write(root.join("Users").join("File").join("folder.ico"), ico([50, 0, 0, 255])).unwrap();
+    root
+}
+
+fn color(png: &[u8]) -> [u8; 4] {
+    IconImage::from_png(png).unwrap().rgba[..4].try_into().unwrap()
+}
+
+#[test]
+fn resolves_system_paths() {
+    let root = windows_root("resolve");
+    let windows_root = WindowsRoot::new(&root);
+    let shell32 = windows_root.resolve("%SystemRoot%\\System32\\SHELL32.dll");
+    let drive = windows_root.resolve("C:\\Windows\\System32\\imageres.dll");
+    let outside = windows_root.resolve("%windir%\\..\\..\\etc");
+    let unknown = windows_root.resolve("%USERPROFILE%\\Desktop");
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(shell32, Some(root.join("WINDOWS").join("system32").join("shell32.dll")));
+    assert_eq!(drive, Some(root.join("WINDOWS").join("system32").join("IMAGERES.DLL")));
+    assert_eq!(outside, None);
+    assert_eq!(unknown, None);
+}
+
+#[test]
+fn desktop_ini_icons() {
+    let root = windows_root("desktop-ini");
+    let windows_root = WindowsRoot::new(&root);
+    let resource = windows_root.get_icon_for_path(&root.join("Users").join("Resource"), 16);
+    let file = windows_root.get_icon_for_path(&root.join("Users").join("File"), 16);
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(color(&resource.unwrap()), COLORS[1]);
+    assert_eq!(color(&file.unwrap()), [50, 0, 0, 255]);
+}
+
+#[test]
+fn default_folder_icon() {
+    let root = windows_root("default");
+    let windows_root = WindowsRoot::new(&root);
+    let icon = windows_root.get_icon_for_path(&root.join("Users").join("Plain"), 16);
+    let dll = windows_root.get_icon_for_path(&root.join("WINDOWS").join("system32").join("shell32.dll"), 16);
+    let other = windows_root.get_icon_for_path(&root.join("Users").join("File").join("Desktop.ini"), 16);
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(color(&icon.unwrap()), COLORS[3]);
+    assert_eq!(color(&dll.unwrap()), COLORS[0]);
+    assert!(matches!(other.unwrap_err(), Error::NotFound { .. }));
+}